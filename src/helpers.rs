@@ -1,8 +1,9 @@
-use anyhow::{Context, Result};
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, Utc};
 use regex::Regex;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
 
 /// Helper struct for parsing GitHub rate limit information
 #[derive(Debug, Deserialize)]
@@ -16,7 +17,6 @@ pub struct RateLimit {
 #[derive(Debug, Deserialize)]
 pub struct GitHubError {
     pub message: String,
-    pub documentation_url: Option<String>,
 }
 
 /// Parse GitHub rate limit information from response headers
@@ -47,69 +47,256 @@ pub fn parse_rate_limit(headers: &reqwest::header::HeaderMap) -> Option<RateLimi
     }
 }
 
-/// Format a timestamp as a human-readable date
-pub fn format_date(date_str: &str) -> Result<String> {
-    let date = chrono::DateTime::parse_from_rfc3339(date_str)
-        .context("Failed to parse date")?
-        .naive_utc()
-        .date();
+/// Wait requested by a `Retry-After` header, either in seconds or as an HTTP-date
+#[derive(Debug, PartialEq)]
+pub enum RetryAfter {
+    Seconds(u64),
+    Date(DateTime<Utc>),
+}
+
+impl RetryAfter {
+    /// Parse a header value like `120` or `Wed, 21 Oct 2015 07:28:00 GMT`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(RetryAfter::Seconds(seconds));
+        }
+        DateTime::parse_from_rfc2822(value)
+            .ok()
+            .map(|date| RetryAfter::Date(date.with_timezone(&Utc)))
+    }
     
-    Ok(date.format("%Y-%m-%d").to_string())
+    /// How long to wait from `now`; dates in the past mean no wait
+    pub fn delay_from(&self, now: DateTime<Utc>) -> Duration {
+        match self {
+            RetryAfter::Seconds(seconds) => Duration::from_secs(*seconds),
+            RetryAfter::Date(date) => (*date - now).to_std().unwrap_or(Duration::ZERO),
+        }
+    }
+}
+
+/// Parse the `Retry-After` header GitHub sends with secondary rate limit responses
+pub fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<RetryAfter> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(RetryAfter::parse)
 }
 
+/// Format a date for humans using a strftime format such as `%Y-%m-%d`, already checked by `parse_date_format`
+pub fn format_date(date: NaiveDate, format: &str) -> String {
+    date.format(format).to_string()
+}
+
+/// Format a byte count for humans, e.g. `512 B` or `1.5 MB` (binary multiples)
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+static VERSION_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[vV]?(.+)$").unwrap());
+
 /// Extract version number from tag name (e.g., "v1.2.3" -> "1.2.3")
 pub fn extract_version(tag_name: &str) -> String {
-    let re = Regex::new(r"^[vV]?(.+)$").unwrap();
-    if let Some(caps) = re.captures(tag_name) {
+    if let Some(caps) = VERSION_TAG.captures(tag_name) {
         caps.get(1).unwrap().as_str().to_string()
     } else {
         tag_name.to_string()
     }
 }
 
-/// Normalize section name for consistent matching
-pub fn normalize_section_name(name: &str) -> String {
-    name.trim().to_lowercase()
-}
-
-/// Group items by section and version
-pub fn group_by_section_and_version(
-    items: Vec<(String, String, String, NaiveDate)>,
-) -> HashMap<String, HashMap<(String, NaiveDate), Vec<String>>> {
-    let mut result: HashMap<String, HashMap<(String, NaiveDate), Vec<String>>> = HashMap::new();
-    
-    for (section, content, version, date) in items {
-        result
-            .entry(section)
-            .or_insert_with(HashMap::new)
-            .entry((version, date))
-            .or_insert_with(Vec::new)
-            .push(content);
+/// Whether a release tag is the one a selection names, ignoring case and a leading `v`
+pub fn tag_matches(tag_name: &str, tag: &str) -> bool {
+    tag_name.eq_ignore_ascii_case(tag) || extract_version(tag_name).to_lowercase() == extract_version(tag).to_lowercase()
+}
+
+/// Levenshtein distance between two strings, counted in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
     }
     
-    result
+    previous[b.len()]
+}
+
+/// Normalize section name for consistent matching; a trailing colon or item count, as in
+/// "Bug Fixes:" or "Features (3)", doesn't make it a different section
+pub fn normalize_section_name(name: &str) -> String {
+    let name = name.trim().trim_end_matches(':').trim_end();
+    let name = name
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .filter(|(_, count)| !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()))
+        .map_or(name, |(rest, _)| rest.trim_end().trim_end_matches(':').trim_end());
+    name.to_lowercase()
+}
+
+static VERSION_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bv?\d+(?:\.\d+)+(?:-[0-9a-z]+(?:\.[0-9a-z]+)*)?\b").unwrap());
+
+/// Key under which near-identical bullets count as duplicates: lowercased, whitespace collapsed,
+/// trailing punctuation dropped and version numbers replaced by a placeholder
+pub fn fuzzy_dedup_key(item: &str) -> String {
+    let collapsed = item.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let trimmed = collapsed.trim_end_matches(['.', ',', ';', ':', '!']);
+    VERSION_NUMBER.replace_all(trimmed, "<version>").into_owned()
+}
+
+/// Turn `\r\n` and lone `\r` line endings into `\n`
+pub fn normalize_line_endings(content: &str) -> String {
+    content.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+// GitHub emoji shortcodes common in release notes (gitmoji included), sorted for binary search
+const EMOJI_SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"), ("-1", "👎"), ("100", "💯"), ("adhesive_bandage", "🩹"), ("alembic", "⚗️"), ("alien", "👽"),
+    ("ambulance", "🚑"), ("arrow_down", "⬇️"), ("arrow_up", "⬆️"), ("art", "🎨"), ("bangbang", "‼️"),
+    ("bar_chart", "📊"), ("beetle", "🪲"), ("bell", "🔔"), ("bookmark", "🔖"), ("books", "📚"), ("boom", "💥"),
+    ("bricks", "🧱"), ("broom", "🧹"), ("bug", "🐛"), ("building_construction", "🏗️"), ("bulb", "💡"),
+    ("busts_in_silhouette", "👥"), ("calendar", "📅"), ("camera_flash", "📸"), ("card_file_box", "🗃️"),
+    ("chart_with_downwards_trend", "📉"), ("chart_with_upwards_trend", "📈"), ("checkered_flag", "🏁"),
+    ("children_crossing", "🚸"), ("clap", "👏"), ("clipboard", "📋"), ("closed_lock_with_key", "🔐"),
+    ("coffin", "⚰️"), ("computer", "💻"), ("construction", "🚧"), ("dart", "🎯"), ("dizzy", "💫"),
+    ("electric_plug", "🔌"), ("exclamation", "❗"), ("eyes", "👀"), ("fire", "🔥"), ("floppy_disk", "💾"),
+    ("gear", "⚙️"), ("gift", "🎁"), ("globe_with_meridians", "🌐"), ("goal_net", "🥅"), ("green_heart", "💚"),
+    ("hammer", "🔨"), ("hammer_and_wrench", "🛠️"), ("handshake", "🤝"), ("heart", "❤️"),
+    ("heavy_check_mark", "✔️"), ("heavy_minus_sign", "➖"), ("heavy_plus_sign", "➕"), ("hourglass", "⌛"),
+    ("information_source", "ℹ️"), ("iphone", "📱"), ("jigsaw", "🧩"), ("key", "🔑"), ("label", "🏷️"),
+    ("lady_beetle", "🐞"), ("link", "🔗"), ("lipstick", "💄"), ("lock", "🔒"), ("loud_sound", "🔊"), ("mag", "🔍"),
+    ("mega", "📣"), ("memo", "📝"), ("monocle_face", "🧐"), ("muscle", "💪"), ("mute", "🔇"), ("necktie", "👔"),
+    ("new", "🆕"), ("no_entry", "⛔"), ("no_entry_sign", "🚫"), ("package", "📦"), ("pencil2", "✏️"),
+    ("pray", "🙏"), ("pushpin", "📌"), ("question", "❓"), ("raised_hands", "🙌"), ("recycle", "♻️"),
+    ("rewind", "⏪"), ("rocket", "🚀"), ("rotating_light", "🚨"), ("safety_vest", "🦺"), ("scroll", "📜"),
+    ("see_no_evil", "🙈"), ("seedling", "🌱"), ("shield", "🛡️"), ("smile", "😄"), ("sparkles", "✨"),
+    ("speech_balloon", "💬"), ("star", "⭐"), ("star2", "🌟"), ("stethoscope", "🩺"), ("tada", "🎉"),
+    ("technologist", "🧑‍💻"), ("test_tube", "🧪"), ("thread", "🧵"), ("thumbsdown", "👎"), ("thumbsup", "👍"),
+    ("tools", "🛠️"), ("triangular_flag_on_post", "🚩"), ("trophy", "🏆"), ("truck", "🚚"),
+    ("twisted_rightwards_arrows", "🔀"), ("unlock", "🔓"), ("warning", "⚠️"), ("wastebasket", "🗑️"),
+    ("wave", "👋"), ("wheelchair", "♿"), ("white_check_mark", "✅"), ("wrench", "🔧"), ("x", "❌"), ("zap", "⚡"),
+];
+
+static SHORTCODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r":([a-z0-9_+-]+):").unwrap());
+
+/// Replace known GitHub `:shortcode:` emoji with the Unicode emoji they render as; unknown ones are left alone
+pub fn render_emoji(text: &str) -> String {
+    SHORTCODE
+        .replace_all(text, |captures: &regex::Captures| {
+            match EMOJI_SHORTCODES.binary_search_by(|(code, _)| (*code).cmp(&captures[1])) {
+                Ok(index) => EMOJI_SHORTCODES[index].1.to_string(),
+                Err(_) => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+static FENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^ {0,3}(`{3,}|~{3,})").unwrap());
+
+/// The backtick or tilde run opening a fenced code block, indented by at most three spaces
+pub fn fence_marker(line: &str) -> Option<String> {
+    FENCE
+        .captures(line)
+        .map(|captures| captures[1].to_string())
+}
+
+/// A closing fence uses the same character as the opening one, at least as many times, and nothing else
+pub fn is_closing_fence(line: &str, open_fence: &str) -> bool {
+    let trimmed = line.trim();
+    let fence_char = open_fence.chars().next().unwrap();
+    line.len() - line.trim_start().len() < 4
+        && trimmed.len() >= open_fence.len()
+        && trimmed.chars().all(|c| c == fence_char)
 }
 
 /// Clean up markdown content by removing extra blank lines and ensuring proper spacing
+///
+/// Fenced code blocks are copied verbatim, so `#` comments and blank lines inside them survive.
 pub fn clean_markdown(content: &str) -> String {
-    // Remove multiple consecutive blank lines
-    let re = Regex::new(r"\n{3,}").unwrap();
-    let content = re.replace_all(content, "\n\n").to_string();
+    let heading_regex = Regex::new(r"^#+\s").unwrap();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut open_fence: Option<String> = None;
     
-    // Ensure headings are preceded by a blank line (except at the start)
-    let re = Regex::new(r"(?m)^(?!#)(.+)\n(#+\s)").unwrap();
-    let content = re.replace_all(&content, "$1\n\n$2").to_string();
+    for line in content.split('\n') {
+        if let Some(fence) = &open_fence {
+            lines.push(line);
+            if is_closing_fence(line, fence) {
+                open_fence = None;
+            }
+            continue;
+        }
+        
+        // Remove multiple consecutive blank lines
+        if line.is_empty() && lines.last().is_some_and(|previous| previous.is_empty()) {
+            continue;
+        }
+        
+        // Ensure headings are preceded by a blank line (except at the start and after another heading)
+        if heading_regex.is_match(line)
+            && lines.last().is_some_and(|previous| !previous.is_empty() && !previous.starts_with('#'))
+        {
+            lines.push("");
+        }
+        
+        lines.push(line);
+        open_fence = fence_marker(line);
+    }
+    
+    lines.join("\n")
+}
+
+/// Push every heading `levels` deeper, capped at `######`, for embedding in a larger document
+///
+/// Lines inside fenced code blocks are left alone.
+pub fn shift_headings(content: &str, levels: usize) -> String {
+    let heading_regex = Regex::new(r"^(#{1,6})(\s)").unwrap();
+    let mut open_fence: Option<String> = None;
     
     content
+        .split('\n')
+        .map(|line| {
+            if let Some(fence) = &open_fence {
+                if is_closing_fence(line, fence) {
+                    open_fence = None;
+                }
+                return line.to_string();
+            }
+            if let Some(fence) = fence_marker(line) {
+                open_fence = Some(fence);
+                return line.to_string();
+            }
+            heading_regex
+                .replace(line, |captures: &regex::Captures| {
+                    format!("{}{}", "#".repeat((captures[1].len() + levels).min(6)), &captures[2])
+                })
+                .into_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-/// Extract sections from Markdown content
-pub fn extract_sections(content: &str) -> HashMap<String, Vec<String>> {
-    let mut sections = HashMap::new();
+/// Extract sections from Markdown content, splitting on headings no deeper than `max_depth`
+pub fn extract_sections(content: &str, max_depth: usize) -> HashMap<String, Vec<String>> {
+    let mut sections: HashMap<String, Vec<String>> = HashMap::new();
     let heading_regex = Regex::new(r"^(#+)\s+(.+)$").unwrap();
     
     let mut current_section = "Uncategorized".to_string();
-    let mut current_level = 0;
     let mut current_content = Vec::new();
     
     for line in content.lines() {
@@ -117,16 +304,15 @@ pub fn extract_sections(content: &str) -> HashMap<String, Vec<String>> {
             let level = captures.get(1).unwrap().as_str().len();
             let heading = captures.get(2).unwrap().as_str().trim();
             
-            // Only consider top-level and second-level headings as section dividers
-            if level <= 2 {
-                // Save the previous section
+            // Only shallow enough headings are section dividers
+            if level <= max_depth {
+                // Save the previous section, appending if the heading already appeared
                 if !current_content.is_empty() {
-                    sections.insert(current_section, current_content);
+                    sections.entry(current_section).or_default().extend(current_content);
                 }
                 
                 // Start a new section
                 current_section = heading.to_string();
-                current_level = level;
                 current_content = Vec::new();
             } else {
                 // For deeper headings, include them in the content
@@ -139,7 +325,7 @@ pub fn extract_sections(content: &str) -> HashMap<String, Vec<String>> {
     
     // Save the last section
     if !current_content.is_empty() {
-        sections.insert(current_section, current_content);
+        sections.entry(current_section).or_default().extend(current_content);
     }
     
     sections
@@ -167,8 +353,12 @@ pub fn compare_semver(tag1: &str, tag2: &str) -> std::cmp::Ordering {
         return clean1.cmp(&clean2);
     }
     
-    let v1: Vec<&str> = clean1.split('.').collect();
-    let v2: Vec<&str> = clean2.split('.').collect();
+    // Build metadata never affects precedence; the pre-release suffix is compared last
+    let (core1, pre1) = split_prerelease(&clean1);
+    let (core2, pre2) = split_prerelease(&clean2);
+    
+    let v1: Vec<&str> = core1.split('.').collect();
+    let v2: Vec<&str> = core2.split('.').collect();
     
     for i in 0..3 {
         if i >= v1.len() || i >= v2.len() {
@@ -184,5 +374,45 @@ pub fn compare_semver(tag1: &str, tag2: &str) -> std::cmp::Ordering {
         }
     }
     
-    std::cmp::Ordering::Equal
-}
\ No newline at end of file
+    // A pre-release sorts before the release it leads up to
+    match (pre1, pre2) {
+        (None, None) => std::cmp::Ordering::Equal,
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (Some(a), Some(b)) => compare_prerelease(a, b),
+    }
+}
+
+// Semver §11.4: dot-separated identifiers compare left to right, numbers numerically and below
+// alphanumerics, and a shorter list of otherwise equal identifiers comes first
+fn compare_prerelease(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut ids1 = a.split('.');
+    let mut ids2 = b.split('.');
+    
+    loop {
+        let ordering = match (ids1.next(), ids2.next()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(id1), Some(id2)) => match (id1.parse::<u64>(), id2.parse::<u64>()) {
+                (Ok(n1), Ok(n2)) => n1.cmp(&n2),
+                (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+                (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+                (Err(_), Err(_)) => id1.cmp(id2),
+            },
+        };
+        
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// Split `1.2.3-rc.1+build` into `1.2.3` and `Some("rc.1")`
+fn split_prerelease(version: &str) -> (&str, Option<&str>) {
+    let version = version.split('+').next().unwrap_or(version);
+    match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    }
+}
@@ -0,0 +1,4055 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+// Added for logging
+use log::{debug, info, warn, error};
+
+mod helpers;
+pub mod logging;
+mod metrics;
+mod progress;
+mod sources;
+mod template;
+#[cfg(test)]
+mod tests;
+
+use metrics::{Metrics, Phase, RequestCounter};
+use progress::Progress;
+pub use sources::ApiError;
+use sources::{create_gist, fetch_rate_limit, FileSource, GitHubSource, GitLabSource, GiteaSource, ReleaseSource, RetryPolicy, GITHUB_API_URL};
+use template::render_template_file;
+use helpers::{clean_markdown, compare_semver, edit_distance, extract_sections, extract_version, fence_marker, format_date, format_size, fuzzy_dedup_key, is_closing_fence, is_semver, normalize_line_endings, normalize_section_name, render_emoji, shift_headings, tag_matches};
+
+/// Everything `aggregate` needs to know: which releases to fetch and how to render them
+#[derive(Debug, Clone)]
+pub struct AggregatorConfig {
+    /// Repository owners (users or organizations), one for every repo or one per repo
+    pub owner: Vec<String>,
+
+    /// Repository names; several are aggregated into one document
+    pub repo: Vec<String>,
+
+    /// Extra repositories as `owner/repo` or `owner/repo:Label`, with their notes attributed to the label
+    pub source: Vec<String>,
+
+    /// Start tag (older version)
+    pub start_tag: Option<String>,
+
+    /// End tag (newer version)
+    pub end_tag: Option<String>,
+
+    /// GitHub personal access token (for higher rate limits)
+    pub token: Option<String>,
+
+    /// Hosting service to fetch releases from
+    pub provider: Provider,
+
+    /// Base URL of the provider API (e.g. GitHub Enterprise, a self-hosted GitLab or a Gitea instance)
+    pub base_url: Option<String>,
+
+    /// Maximum retries for transient API failures (5xx gateway errors, connection resets, rate limits)
+    pub max_retries: u32,
+
+    /// Fetch the releases again, up to N times with a growing delay, while a tag named by --versions, --start-tag or --end-tag is missing (for just-published releases)
+    pub retry_missing_tags: u32,
+
+    /// Cache GitHub release lists here and revalidate them with ETags on later runs
+    pub cache_dir: Option<PathBuf>,
+
+    /// Read releases from a JSON file (GitHub API format) instead of calling the API
+    pub from_file: Option<PathBuf>,
+
+    /// Aggregate annotated tag messages instead of GitHub Releases, for repositories that only push tags
+    pub from_tags: bool,
+
+    /// Have GitHub generate notes for `end_tag`, since `start_tag` if given, instead of fetching published releases
+    pub generate: bool,
+
+    /// Fetch releases through the GitHub GraphQL API, 100 at a time with full bodies, instead of the REST list; needs a token
+    pub use_graphql: bool,
+
+    /// Refetch release bodies that the list endpoint omitted or truncated
+    pub fetch_full_bodies: bool,
+
+    /// Maximum number of concurrent requests when fetching full bodies
+    pub concurrency: usize,
+
+    /// Include pre-releases
+    pub include_prereleases: bool,
+
+    /// Fetch pre-releases so range tags can name them, but leave them out of the output
+    pub exclude_prereleases_from_output: bool,
+
+    /// Include draft releases, dated by when they were created
+    pub include_drafts: bool,
+
+    /// Arbitrary versions to merge (comma-separated list of tag names)
+    pub versions: Option<String>,
+
+    /// Read tag names to merge from a file, one per line (blank lines and # comments are ignored), in addition to --versions
+    pub versions_file: Option<PathBuf>,
+
+    /// Only consider releases whose tag matches this shell-style glob (e.g. "backend-v*")
+    pub tag_pattern: Option<String>,
+
+    /// Only consider releases whose tag starts with this prefix (e.g. "frontend-"), showing their versions without it
+    pub tag_prefix: Option<String>,
+
+    /// Only aggregate releases whose semver is at least this version (non-semver tags are skipped)
+    pub min_version: Option<String>,
+
+    /// Fail with `NoReleases::Matched` when the selection leaves no releases to aggregate (for CI checks);
+    /// a repository without any releases always fails with `NoReleases::Found`
+    pub fail_on_empty: bool,
+
+    /// Check every release parses cleanly instead of aggregating; `aggregate` fails with a `ValidationFailure` listing the problems
+    pub validate: bool,
+
+    /// Deepest heading level that starts a section; deeper headings stay inside the current one (and fail `validate`)
+    pub max_heading_depth: usize,
+
+    /// Only aggregate the N most recent releases (by publish date)
+    pub latest: Option<usize>,
+
+    /// List a bullet repeated across consecutive releases once, annotated with the version range
+    pub collapse_repeated: bool,
+
+    /// Treat bullets differing only in case, trailing punctuation or version numbers as duplicates (with --merge-headings)
+    pub fuzzy_dedup: bool,
+
+    /// Merge by heading (combine content under common headings instead of keeping versions separate)
+    pub merge_headings: bool,
+
+    /// Emit a one-line-per-release digest (name or first line of the notes) instead of sections
+    pub summarize: bool,
+
+    /// Leave out the version annotations under each item merged by heading
+    pub no_source_annotations: bool,
+
+    /// Ordering used to resolve `start_tag`/`end_tag` ranges
+    pub order_by: ReleaseOrder,
+
+    /// Fail when `start_tag` is newer than `end_tag` instead of swapping them
+    pub strict_range: bool,
+
+    /// Leave the start tag itself out of the range, for everything released after it
+    pub exclusive_start: bool,
+
+    /// Leave the end tag itself out of the range, for everything released up to it
+    pub exclusive_end: bool,
+
+    /// Only include releases published on or after this date (YYYY-MM-DD)
+    pub since: Option<NaiveDate>,
+
+    /// Only include releases published on or before this date (YYYY-MM-DD)
+    pub until: Option<NaiveDate>,
+
+    /// Only include releases from the last N days or weeks (e.g. 30d, 2w), counted back from today
+    pub window: Option<chrono::Duration>,
+
+    /// Comma-separated section names to leave out (case-insensitive)
+    pub exclude_sections: Option<String>,
+
+    /// Comma-separated section names to keep, dropping all others (`exclude_sections` wins on overlap)
+    pub include_sections: Option<String>,
+
+    /// TOML or JSON file mapping canonical section names to lists of aliases, on top of the built-in synonyms
+    pub section_aliases: Option<PathBuf>,
+
+    /// Turn `#123` and `owner/repo#123` issue/PR references into GitHub links
+    pub link_refs: bool,
+
+    /// Group heading-less notes into sections by their conventional-commit prefix (feat:, fix:, ...)
+    pub parse_conventional: bool,
+
+    /// File the notes of a release without any headings under a section named after the release (its name, or else its tag) instead of Uncategorized
+    pub name_as_section: bool,
+
+    /// Join an indented line that continues a wrapped bullet onto that bullet, so it stays one item
+    pub join_wrapped_bullets: bool,
+
+    /// Replace GitHub emoji shortcodes such as :rocket: with the emoji itself, in section names and notes
+    pub render_emoji: bool,
+
+    /// Leave releases without any notes out of version-separated output
+    pub skip_empty: bool,
+
+    /// Collect the "Full Changelog" compare links into a "Changelog Links" section instead of dropping them
+    pub keep_compare_links: bool,
+
+    /// List each release's downloadable assets with their size in an Assets section
+    pub list_assets: bool,
+
+    /// Comma-separated section names to list first, in order; the rest follow alphabetically
+    pub section_order: Option<String>,
+
+    /// Name of the section collecting notes that appear before any heading; it is always listed last
+    pub uncategorized_label: String,
+
+    /// Keep at most N items per section for each release, noting how many were left out (Markdown output)
+    pub max_items_per_section: Option<usize>,
+
+    /// Order of the items listed under each version: as-is, alphabetical or length (Markdown output)
+    pub sort_items: ItemOrder,
+
+    /// Keep each section at the heading level it had in the release notes (Markdown output)
+    pub preserve_levels: bool,
+
+    /// Annotate version headings with how long ago each release was published (Markdown output)
+    pub relative_dates: bool,
+
+    /// strftime format for the dates in version headings (default: %Y-%m-%d)
+    pub date_format: Option<String>,
+
+    /// Link each version heading to its release page (version-separated Markdown output)
+    pub link_versions: bool,
+
+    /// Push every Markdown heading N levels deeper (capped at h6), for embedding in another document
+    pub shift_headings: usize,
+
+    /// How to group Markdown output: by section, by repository within sections, or by release
+    pub group_by: GroupBy,
+
+    /// Prepend a table of contents linking to each section (Markdown output)
+    pub toc: bool,
+
+    /// Render Markdown output through a Tera template file instead of the built-in layout; `default` selects the shipped template
+    pub template: Option<PathBuf>,
+
+    /// Existing Markdown document to merge the new releases into, skipping releases it already lists
+    pub append_to: Option<PathBuf>,
+
+    /// Only emit items not already present in this previously generated Markdown file
+    pub diff_against: Option<PathBuf>,
+
+    /// Compare exactly two selected releases and only emit the items the newer one has that the older one doesn't
+    pub changed_only: bool,
+
+    /// Append a summary table of release, item and per-section counts (Markdown output)
+    pub stats: bool,
+
+    /// Append a Contributors section listing every @mentioned handle (Markdown output)
+    pub contributors: bool,
+
+    /// Output format
+    pub format: OutputFormat,
+
+    /// Whether `Terminal` output uses ANSI colors; `Auto` checks that stdout is a terminal
+    pub color: ColorChoice,
+
+    /// Extra section-to-category mappings for keepachangelog output (e.g. "Improvements=Changed,Breaking=Removed")
+    pub changelog_map: Option<String>,
+
+    /// Also write a JSON manifest of the processed releases and whether each contributed content
+    pub manifest: Option<PathBuf>,
+
+    /// Write each section to its own file in `output_dir`, returning an index that links to them
+    pub split_by: Option<SplitBy>,
+
+    /// Directory for the files written by `split_by`; the index's links are relative to it
+    pub output_dir: Option<PathBuf>,
+
+    /// Enable verbose logging
+    pub verbose: bool,
+}
+
+// Mirrors the command-line defaults
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        AggregatorConfig {
+            owner: Vec::new(),
+            repo: Vec::new(),
+            source: Vec::new(),
+            start_tag: None,
+            end_tag: None,
+            token: None,
+            provider: Provider::Github,
+            base_url: None,
+            max_retries: 3,
+            retry_missing_tags: 0,
+            cache_dir: None,
+            from_file: None,
+            from_tags: false,
+            generate: false,
+            use_graphql: false,
+            fetch_full_bodies: false,
+            concurrency: 8,
+            include_prereleases: false,
+            exclude_prereleases_from_output: false,
+            include_drafts: false,
+            versions: None,
+            versions_file: None,
+            tag_pattern: None,
+            tag_prefix: None,
+            min_version: None,
+            fail_on_empty: false,
+            validate: false,
+            max_heading_depth: DEFAULT_MAX_HEADING_DEPTH,
+            latest: None,
+            collapse_repeated: false,
+            fuzzy_dedup: false,
+            merge_headings: false,
+            summarize: false,
+            no_source_annotations: false,
+            order_by: ReleaseOrder::Date,
+            strict_range: false,
+            exclusive_start: false,
+            exclusive_end: false,
+            since: None,
+            until: None,
+            window: None,
+            exclude_sections: None,
+            include_sections: None,
+            section_aliases: None,
+            link_refs: false,
+            parse_conventional: false,
+            name_as_section: false,
+            join_wrapped_bullets: false,
+            render_emoji: false,
+            skip_empty: false,
+            keep_compare_links: false,
+            list_assets: false,
+            section_order: None,
+            uncategorized_label: DEFAULT_UNCATEGORIZED_LABEL.to_string(),
+            max_items_per_section: None,
+            sort_items: ItemOrder::AsIs,
+            preserve_levels: false,
+            relative_dates: false,
+            date_format: None,
+            link_versions: false,
+            shift_headings: 0,
+            group_by: GroupBy::Section,
+            toc: false,
+            template: None,
+            append_to: None,
+            diff_against: None,
+            changed_only: false,
+            stats: false,
+            contributors: false,
+            format: OutputFormat::Markdown,
+            color: ColorChoice::Auto,
+            changelog_map: None,
+            manifest: None,
+            split_by: None,
+            output_dir: None,
+            verbose: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Markdown document
+    Markdown,
+    /// Pretty-printed JSON of the merged sections
+    Json,
+    /// YAML of the merged sections, same schema as JSON
+    Yaml,
+    /// TOML of the merged sections, same schema as JSON
+    Toml,
+    /// Standalone HTML document with a linked table of contents
+    Html,
+    /// Per-release changelog following the Keep a Changelog conventions
+    Keepachangelog,
+    /// Atom 1.0 feed with one entry per release
+    Atom,
+    /// Plain text for reading in a terminal, with ANSI colors when enabled
+    Terminal,
+    /// One `section,version,date,content` row per item, for spreadsheets
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    /// Always emit ANSI colors
+    Always,
+    /// Never emit ANSI colors
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// File extension conventionally used for this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown | OutputFormat::Keepachangelog => "md",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Html => "html",
+            OutputFormat::Atom => "atom",
+            OutputFormat::Terminal => "txt",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum GroupBy {
+    /// Sections contain versions (or merged items) directly
+    #[default]
+    Section,
+    /// Sections are split by repository before versions
+    Repo,
+    /// Sections are split by source label (or repository when unlabeled) before versions
+    Source,
+    /// Releases come first, each with its own sections (version-separated output only)
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Provider {
+    /// GitHub releases API
+    Github,
+    /// GitLab releases API (owner/repo is the project path)
+    Gitlab,
+    /// Gitea or Forgejo releases API (requires --base-url)
+    Gitea,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SplitBy {
+    /// One Markdown file per section
+    Section,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ItemOrder {
+    /// Keep items in the order they appear in the release notes
+    #[default]
+    AsIs,
+    /// Sort items alphabetically, ignoring the bullet marker and case
+    Alphabetical,
+    /// Sort items from shortest to longest
+    Length,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReleaseOrder {
+    /// Order releases by publication date
+    Date,
+    /// Order releases by semantic version of their tags
+    Semver,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+struct Release {
+    id: u64,
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    /// Empty for drafts, which haven't been published yet
+    #[serde(default, deserialize_with = "null_as_empty")]
+    published_at: String,
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    /// Creation time, which stands in for the publication date of drafts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+    /// Downloadable files attached to the release
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    assets: Vec<ReleaseAsset>,
+    /// `owner/repo` the release was fetched from, filled in after fetching
+    #[serde(skip)]
+    repo: String,
+    /// Friendly name given to the repository with `--source owner/repo:Label`
+    #[serde(skip)]
+    label: Option<String>,
+    /// Web page of the release
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    html_url: Option<String>,
+}
+
+/// A file attached to a release, such as a prebuilt binary
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+struct ReleaseAsset {
+    name: String,
+    /// Size in bytes
+    #[serde(default)]
+    size: u64,
+    browser_download_url: String,
+}
+
+impl Release {
+    // Publication time in UTC, failing with the offending value when it isn't RFC 3339
+    fn published(&self) -> Result<chrono::NaiveDateTime> {
+        chrono::DateTime::parse_from_rfc3339(&self.published_at)
+            .map(|date| date.naive_utc())
+            .with_context(|| format!("Invalid published_at '{}' for release '{}'", self.published_at, self.tag_name))
+    }
+}
+
+// Drafts come back with `"published_at": null`
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Options controlling how the Markdown generators lay out their output
+#[derive(Debug, Default, Clone)]
+struct MarkdownOptions {
+    /// Prepend a table of contents linking to each `##` section
+    toc: bool,
+    /// Grouping applied inside each section
+    group_by: GroupBy,
+    /// Sections to list first and the bucket to list last
+    section_order: SectionOrder,
+    /// Items kept per section for each release; the rest are summarized in a count
+    max_items_per_section: Option<usize>,
+    /// Emit sections at the heading depth they had in the release bodies
+    preserve_levels: bool,
+    /// Annotate version headings with their age as of this date
+    relative_to: Option<NaiveDate>,
+    /// strftime format for version heading dates, `DEFAULT_DATE_FORMAT` when unset
+    date_format: Option<String>,
+    /// Link version headings to the release page
+    link_versions: bool,
+    /// Order of the items listed under each version
+    sort_items: ItemOrder,
+    /// Leave out the versions each heading-merged item appeared in
+    hide_source_annotations: bool,
+}
+
+/// Options controlling which release note content the merge functions keep
+#[derive(Debug, Clone)]
+struct MergeOptions {
+    /// Normalized section names to keep; all sections are kept when unset
+    include_sections: Option<HashSet<String>>,
+    /// Normalized section names to drop, taking precedence over `include_sections`
+    exclude_sections: HashSet<String>,
+    /// Collect stripped "Full Changelog" compare URLs into a "Changelog Links" section
+    keep_compare_links: bool,
+    /// Leave releases without notes out entirely instead of emitting a placeholder
+    skip_empty: bool,
+    /// Turn `#123` and `owner/repo#123` references into links
+    link_refs: bool,
+    /// Sort heading-less bodies into sections by conventional-commit type
+    parse_conventional: bool,
+    /// Give heading-less bodies a section named after their release instead of the uncategorized one
+    name_as_section: bool,
+    /// Join indented continuation lines onto the bullet they wrap from
+    join_wrapped_bullets: bool,
+    /// Replace `:shortcode:` emoji with Unicode ones in section names and content
+    render_emoji: bool,
+    /// Treat bullets differing only in case, trailing punctuation or version numbers as duplicates
+    fuzzy_dedup: bool,
+    /// List each release's downloadable assets in an "Assets" section
+    list_assets: bool,
+    /// Synonymous section names to merge under one canonical name
+    section_aliases: SectionAliases,
+    /// Section collecting content that appears before any heading
+    uncategorized: String,
+    /// Deepest heading level that starts a section
+    max_heading_depth: usize,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            include_sections: None,
+            exclude_sections: HashSet::new(),
+            keep_compare_links: false,
+            skip_empty: false,
+            link_refs: false,
+            parse_conventional: false,
+            name_as_section: false,
+            join_wrapped_bullets: false,
+            render_emoji: false,
+            fuzzy_dedup: false,
+            list_assets: false,
+            section_aliases: SectionAliases::default(),
+            uncategorized: DEFAULT_UNCATEGORIZED_LABEL.to_string(),
+            max_heading_depth: DEFAULT_MAX_HEADING_DEPTH,
+        }
+    }
+}
+
+impl MergeOptions {
+    fn allows_section(&self, section_name: &str) -> bool {
+        let normalized = normalize_section_name(section_name);
+        if self.exclude_sections.contains(&normalized) {
+            return false;
+        }
+        self.include_sections
+            .as_ref()
+            .is_none_or(|include| include.contains(&normalized))
+    }
+}
+
+/// Maps normalized section names onto the canonical section they should be merged into
+#[derive(Debug, Clone)]
+struct SectionAliases {
+    canonical: HashMap<String, String>,
+}
+
+impl Default for SectionAliases {
+    fn default() -> Self {
+        let defaults: [(&str, &[&str]); 6] = [
+            ("Features", &["feature", "new features", "new feature"]),
+            ("Bug Fixes", &["fixes", "fixed", "bug fix", "bugfixes", "bugfix", "bugs fixed"]),
+            ("Breaking Changes", &["breaking", "breaking change"]),
+            ("Documentation", &["docs", "doc"]),
+            ("Performance", &["performance improvements", "perf"]),
+            ("Dependencies", &["dependency updates", "deps"]),
+        ];
+        
+        let mut aliases = SectionAliases { canonical: HashMap::new() };
+        for (canonical, names) in defaults {
+            aliases.add(canonical, names.iter().copied());
+        }
+        aliases
+    }
+}
+
+impl SectionAliases {
+    fn add<'a>(&mut self, canonical: &str, names: impl IntoIterator<Item = &'a str>) {
+        for name in names {
+            self.canonical.insert(normalize_section_name(name), canonical.trim().to_string());
+        }
+    }
+    
+    // The built-in synonyms plus a `Canonical = ["alias", ...]` table from a TOML or JSON file
+    fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read section aliases file: {:?}", path))?;
+        let table: BTreeMap<String, Vec<String>> = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Section aliases file {:?} is not a JSON object of name lists", path))?
+        } else {
+            toml::from_str(&contents)
+                .with_context(|| format!("Section aliases file {:?} is not a TOML table of name lists", path))?
+        };
+        
+        let mut aliases = SectionAliases::default();
+        for (canonical, names) in &table {
+            aliases.add(canonical, names.iter().map(String::as_str));
+        }
+        debug!("Loaded {} section aliases from {:?}", table.len(), path);
+        Ok(aliases)
+    }
+    
+    // The name a section is merged under; unknown names are kept as written
+    fn resolve(&self, section_name: &str) -> String {
+        self.canonical
+            .get(&normalize_section_name(section_name))
+            .cloned()
+            .unwrap_or_else(|| section_name.to_string())
+    }
+}
+
+/// Where sections appear in the output: the listed ones first, the uncategorized bucket always last
+#[derive(Debug, Clone)]
+struct SectionOrder {
+    /// Normalized section names to list first, in this order
+    leading: Vec<String>,
+    /// Section collecting content that appears before any heading
+    uncategorized: String,
+}
+
+impl SectionOrder {
+    fn new(leading: Vec<String>, uncategorized: &str) -> Self {
+        SectionOrder {
+            leading,
+            uncategorized: uncategorized.to_string(),
+        }
+    }
+}
+
+impl Default for SectionOrder {
+    fn default() -> Self {
+        SectionOrder::new(Vec::new(), DEFAULT_UNCATEGORIZED_LABEL)
+    }
+}
+
+// Like `parse_section_list`, but keeping the order the sections were given in
+fn parse_section_order(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(normalize_section_name)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+// Split a comma-separated list of section names into their normalized forms
+fn parse_section_list(list: &str) -> HashSet<String> {
+    list.split(',')
+        .map(normalize_section_name)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Serialize, Default)]
+struct ReleaseNoteItem {
+    /// Raw line of release note content
+    content: String,
+    /// Tag name of the release the line came from
+    version: String,
+    /// Publication date of the release, serialized as `YYYY-MM-DD`
+    date: NaiveDate,
+    /// Repository (`owner/repo`) the release belongs to
+    repo: String,
+    /// Friendly name of the repository, when one was given with `--source`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    /// Release name, when it says something beyond the tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Depth of the heading the line appeared under in the release body
+    #[serde(skip)]
+    level: Option<usize>,
+    /// Web page of the release, linked from version headings with `--link-versions`
+    #[serde(skip)]
+    url: Option<String>,
+}
+
+impl ReleaseNoteItem {
+    // Name the item is attributed to: its source label, or else its repository
+    fn source(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.repo)
+    }
+}
+
+/// Fetch, select and merge releases as described by `config`, returning the rendered document.
+///
+/// Fails with [`NoReleases::Found`] when the repositories have no releases at all, with [`NoReleases::Matched`]
+/// when `fail_on_empty` is set and the selection leaves none, and with [`TagNotFound`] when a tag the selection
+/// names doesn't exist. A successful `validate` run returns an empty string, as there's nothing to render.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = ghnotes::AggregatorConfig {
+///     owner: vec!["microsoft".to_string()],
+///     repo: vec!["vscode".to_string()],
+///     latest: Some(5),
+///     ..Default::default()
+/// };
+/// let markdown = ghnotes::aggregate(config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn aggregate(mut config: AggregatorConfig) -> Result<String> {
+    // Tags from the file join any listed inline, so everything downstream sees a single list
+    if let Some(path) = &config.versions_file {
+        let file_tags = load_versions_file(path)?;
+        config.versions = Some(config.versions.iter().cloned().chain(file_tags).collect::<Vec<_>>().join(","));
+    }
+    // Tags named for the selection may be given with or without the prefix; sources and selection both see them without
+    if let Some(prefix) = config.tag_prefix.clone() {
+        let strip = |tag: &str| tag.strip_prefix(prefix.as_str()).unwrap_or(tag).to_string();
+        config.start_tag = config.start_tag.as_deref().map(strip);
+        config.end_tag = config.end_tag.as_deref().map(strip);
+        config.versions = config
+            .versions
+            .as_deref()
+            .map(|versions| versions.split(',').map(|tag| strip(tag.trim())).collect::<Vec<_>>().join(","));
+    }
+    let repositories = resolve_repositories(&config.owner, &config.repo, &config.source)?;
+    let mut metrics = Metrics::start();
+    metrics.begin(Phase::Fetching);
+
+    if config.from_tags && config.from_file.is_none() && config.provider != Provider::Github {
+        return Err(anyhow::anyhow!("--from-tags is only supported for GitHub repositories"));
+    }
+    
+    if config.generate && (config.from_file.is_some() || config.provider != Provider::Github) {
+        return Err(anyhow::anyhow!("--generate is only supported for GitHub repositories"));
+    }
+    
+    if config.use_graphql && config.from_file.is_none() && config.provider != Provider::Github {
+        return Err(anyhow::anyhow!("--use-graphql is only supported for GitHub repositories"));
+    }
+    
+    if config.generate && config.end_tag.is_none() {
+        return Err(anyhow::anyhow!("--generate needs an --end-tag naming the release to generate notes for"));
+    }
+    
+    // The index is written where the section files go, so another document would have nowhere to go
+    if config.split_by.is_some() && (config.format != OutputFormat::Markdown || config.template.is_some()) {
+        return Err(anyhow::anyhow!("--split-by only writes the built-in Markdown layouts and can't be combined with another --format or --template"));
+    }
+
+    // Only the small anonymous quota is worth a request up front; with a token it's just logged as requests go
+    if config.from_file.is_none() && config.provider == Provider::Github && config.token.is_none() {
+        check_remaining_quota(&config, metrics.requests()).await?;
+    }
+
+    // Debug logging would keep scrolling the bar away
+    let progress = Progress::new(!config.verbose);
+    
+    let mut all_releases = fetch_component_releases(&config, &repositories, metrics.requests(), &progress).await?;
+    
+    // A just-published release can take a moment to show up in the list; library callers bypass the CLI's cap
+    let retry_missing_tags = config.retry_missing_tags.min(MAX_MISSING_TAG_RETRIES);
+    for attempt in 1..=retry_missing_tags {
+        let missing = missing_selection_tags(&config, &all_releases);
+        if missing.is_empty() {
+            break;
+        }
+        let delay = MISSING_TAG_RETRY_DELAY * attempt;
+        warn!(
+            "Tags {} not found yet, fetching again in {:?} (retry {}/{})",
+            missing.join(", "),
+            delay,
+            attempt,
+            retry_missing_tags
+        );
+        tokio::time::sleep(delay).await;
+        all_releases = fetch_component_releases(&config, &repositories, metrics.requests(), &progress).await?;
+    }
+    progress.finish();
+    metrics.begin(Phase::Parsing);
+
+    // Nothing to select from is always an error, unlike a selection that matched nothing
+    if all_releases.is_empty() {
+        metrics.log_summary();
+        return Err(NoReleases::Found.into());
+    }
+
+    // Determine which releases to process based on CLI flags
+    let releases_to_process = if config.generate {
+        // GitHub already limited the generated notes to the requested range
+        all_releases
+    } else if let Some(versions) = &config.versions {
+        // Process arbitrary versions
+        let version_tags: Vec<&str> = versions.split(',').map(|s| s.trim()).collect();
+        debug!("Processing specific versions: {:?}", version_tags);
+        filter_releases_by_tags(&all_releases, &version_tags)?
+    } else if config.start_tag.is_some() || config.end_tag.is_some() {
+        // Process range of versions
+        debug!("Processing range: start={:?}, end={:?}", config.start_tag, config.end_tag);
+        filter_releases_by_range(
+            &all_releases,
+            config.start_tag.as_deref(),
+            config.end_tag.as_deref(),
+            config.order_by,
+            config.strict_range,
+            config.exclusive_start,
+            config.exclusive_end,
+        )?
+    } else if let Some(count) = config.latest {
+        // Process only the newest releases
+        debug!("Processing latest {} releases", count);
+        take_latest_releases(all_releases, count)
+    } else {
+        // Process all releases
+        debug!("Processing all releases");
+        all_releases
+    };
+
+    // Pre-releases may have served as range boundaries, but don't belong in the output
+    let releases_to_process = if config.exclude_prereleases_from_output {
+        exclude_prereleases(releases_to_process)
+    } else {
+        releases_to_process
+    };
+
+    // Narrow the selection further by publication date; a window is a start date counted back from today
+    let since = match config.window {
+        Some(window) => Some(chrono::Utc::now().date_naive() - window),
+        None => config.since,
+    };
+    let releases_to_process = if since.is_some() || config.until.is_some() {
+        debug!("Processing date range: since={:?}, until={:?}", since, config.until);
+        filter_releases_by_date(&releases_to_process, since, config.until)?
+    } else {
+        releases_to_process
+    };
+
+    // Apply the semver floor on top of any tag or date selection
+    let releases_to_process = match &config.min_version {
+        Some(min_version) => filter_releases_by_min_version(&releases_to_process, min_version)?,
+        None => releases_to_process,
+    };
+
+    // The older of two releases only serves as the baseline the newer one is compared against
+    let (releases_to_process, changed_since) = if config.changed_only {
+        let mut pair = releases_to_process;
+        if pair.len() != 2 {
+            return Err(anyhow::anyhow!(
+                "--changed-only compares exactly two releases, but the selection has {}",
+                pair.len()
+            ));
+        }
+        sort_releases_by_date(&mut pair);
+        let older = pair.remove(1);
+        info!("Comparing {} against {}", pair[0].tag_name, older.tag_name);
+        (pair, Some(older))
+    } else {
+        (releases_to_process, None)
+    };
+
+    if releases_to_process.is_empty() && config.fail_on_empty {
+        return Err(NoReleases::Matched.into());
+    }
+
+    info!("Processing {} releases", releases_to_process.len());
+
+    let merge_options = MergeOptions {
+        include_sections: config.include_sections.as_deref().map(parse_section_list),
+        exclude_sections: config.exclude_sections.as_deref().map(parse_section_list).unwrap_or_default(),
+        keep_compare_links: config.keep_compare_links,
+        skip_empty: config.skip_empty,
+        link_refs: config.link_refs,
+        parse_conventional: config.parse_conventional,
+        name_as_section: config.name_as_section,
+        join_wrapped_bullets: config.join_wrapped_bullets,
+        render_emoji: config.render_emoji,
+        fuzzy_dedup: config.fuzzy_dedup,
+        list_assets: config.list_assets,
+        section_aliases: match &config.section_aliases {
+            Some(path) => SectionAliases::load(path)?,
+            None => SectionAliases::default(),
+        },
+        uncategorized: config.uncategorized_label.clone(),
+        max_heading_depth: config.max_heading_depth,
+    };
+    
+    if config.validate {
+        metrics.log_summary();
+        let releases = validate_releases(&releases_to_process, &merge_options);
+        if !releases.is_empty() {
+            return Err(ValidationFailure { checked: releases_to_process.len(), releases }.into());
+        }
+        info!("All {} releases passed validation", releases_to_process.len());
+        return Ok(String::new());
+    }
+
+    let markdown_options = MarkdownOptions {
+        toc: config.toc,
+        group_by: config.group_by,
+        section_order: SectionOrder::new(
+            config.section_order.as_deref().map(parse_section_order).unwrap_or_default(),
+            &config.uncategorized_label,
+        ),
+        max_items_per_section: config.max_items_per_section,
+        preserve_levels: config.preserve_levels,
+        relative_to: config.relative_dates.then(|| chrono::Utc::now().date_naive()),
+        date_format: config.date_format.clone(),
+        link_versions: config.link_versions,
+        sort_items: config.sort_items,
+        hide_source_annotations: config.no_source_annotations,
+    };
+
+    let mut changelog_mapping = ChangelogMapping::default();
+    if let Some(overrides) = &config.changelog_map {
+        changelog_mapping.apply_overrides(overrides)?;
+    }
+
+    if config.collapse_repeated && config.merge_headings {
+        warn!("--merge-headings already lists each item once, ignoring --collapse-repeated");
+    }
+
+    if config.append_to.is_some()
+        && (config.merge_headings || config.format != OutputFormat::Markdown || config.template.is_some() || config.group_by != GroupBy::Section)
+    {
+        warn!("--append only merges the default version-separated Markdown layout, replacing the output instead");
+    }
+
+    if config.append_to.is_some() && config.date_format.is_some() {
+        warn!("--append orders releases by the ISO dates in their headings, ignoring --date-format");
+    }
+
+    if config.append_to.is_some() && (config.stats || config.contributors) {
+        warn!("--stats and --contributors only describe this run and can't be appended, ignoring them");
+    }
+
+    if config.preserve_levels && (config.merge_headings || config.group_by == GroupBy::Release || config.template.is_some()) {
+        warn!("--preserve-levels only applies to the built-in version-separated layout grouped by section or repo, ignoring");
+    }
+
+    if config.link_versions && (config.merge_headings || config.template.is_some() || config.format != OutputFormat::Markdown) {
+        warn!("--link-versions only applies to the built-in version-separated Markdown layout, ignoring");
+    }
+
+    if config.max_items_per_section.is_some() && (config.merge_headings || config.template.is_some()) {
+        warn!("--max-items-per-section only applies to the built-in version-separated layout, ignoring");
+    }
+
+    if config.sort_items != ItemOrder::AsIs && (config.merge_headings || config.template.is_some()) {
+        warn!("--sort-items only applies to the built-in version-separated layout, ignoring");
+    }
+
+    if config.no_source_annotations && (!config.merge_headings || !matches!(config.format, OutputFormat::Markdown | OutputFormat::Terminal)) {
+        warn!("--no-source-annotations only applies to Markdown output merged by heading, ignoring");
+    }
+
+    if config.fuzzy_dedup && !config.merge_headings {
+        warn!("--fuzzy-dedup only applies when merging by heading, ignoring");
+    }
+
+    if config.group_by == GroupBy::Release && config.merge_headings {
+        warn!("--group-by release needs version-separated output, ignoring it with --merge-headings");
+    }
+
+    if config.template.is_some() && (config.merge_headings || config.format != OutputFormat::Markdown) {
+        warn!("--template only applies to Markdown output merged by version, ignoring");
+    }
+
+    // The document returned then serves as an index linking to the per-section files
+    let split_dir = match (config.split_by, &config.output_dir) {
+        (Some(SplitBy::Section), Some(dir)) => Some(dir),
+        (Some(_), None) => return Err(anyhow::anyhow!("--split-by needs an --output-dir to write the files to")),
+        (None, _) => None,
+    };
+
+    if split_dir.is_some() && (config.toc || config.group_by == GroupBy::Release) {
+        warn!("--split-by writes one file per section, ignoring --toc and --group-by release");
+    }
+
+    for warning in uncategorized_diagnostics(&releases_to_process, &merge_options, config.verbose) {
+        warn!("{}", warning);
+    }
+
+    if let Some(path) = &config.manifest {
+        let manifest = ReleaseManifest::new(&releases_to_process, &merge_options);
+        let json = serde_json::to_string_pretty(&manifest).context("Failed to serialize release manifest")?;
+        write_output_atomic(path, format!("{}\n", json).as_bytes())?;
+        info!("Wrote manifest of {} releases to {:?}", manifest.releases.len(), path);
+    }
+
+    // Each split file holds a single section, so neither a table of contents nor release grouping fits
+    let split_options = MarkdownOptions {
+        toc: false,
+        group_by: if markdown_options.group_by == GroupBy::Release { GroupBy::Section } else { markdown_options.group_by },
+        ..markdown_options.clone()
+    };
+
+    let baseline = match (&changed_since, &config.diff_against) {
+        (Some(older), _) => Some(Baseline::from_merged(&merge_release_notes(std::slice::from_ref(older), &merge_options))),
+        (None, Some(path)) => Some(Baseline::load(path)?),
+        (None, None) => None,
+    };
+
+    // Statistics are taken from the merged sections so they reflect filtering and dedup
+    let mut stats = None;
+    
+    let mut markdown = if config.summarize {
+        // A digest skips section parsing altogether
+        if config.format != OutputFormat::Markdown {
+            warn!("--summarize always writes a Markdown list, ignoring --format");
+        }
+        metrics.begin(Phase::Generating);
+        generate_summary(&releases_to_process)
+    } else if config.merge_headings {
+        // Merge content under common headings
+        debug!("Merging release notes by heading");
+        let mut merged_by_heading = merge_release_notes_by_heading(&releases_to_process, &merge_options);
+        if let Some(baseline) = &baseline {
+            baseline.remove_known_items(&mut merged_by_heading, |item| &item.content);
+        }
+        if config.stats {
+            stats = Some(SummaryStats::from_merged_headings(&merged_by_heading, &releases_to_process, &config.uncategorized_label));
+        }
+        metrics.begin(Phase::Generating);
+        match config.format {
+            OutputFormat::Markdown => match split_dir {
+                Some(dir) => write_section_files(dir, merged_by_heading, &markdown_options.section_order, |section| {
+                    generate_markdown_merged_headings(section, &split_options)
+                })?,
+                None => generate_markdown_merged_headings(&merged_by_heading, &markdown_options),
+            },
+            OutputFormat::Json => generate_json(&merged_by_heading)?,
+            OutputFormat::Yaml => generate_yaml(&merged_by_heading)?,
+            OutputFormat::Toml => generate_toml(&merged_by_heading)?,
+            OutputFormat::Html => generate_html_merged_headings(&merged_by_heading, &markdown_options.section_order),
+            OutputFormat::Keepachangelog => {
+                warn!("Keep a Changelog output is per release, ignoring --merge-headings");
+                let merged_sections = merge_release_notes(&releases_to_process, &merge_options);
+                generate_keep_a_changelog(&merged_sections, &changelog_mapping, &config.uncategorized_label)
+            }
+            OutputFormat::Atom => {
+                warn!("Atom feeds are per release, ignoring --merge-headings");
+                generate_atom_feed(&releases_to_process)
+            }
+            OutputFormat::Csv => {
+                warn!("CSV rows are per release, ignoring --merge-headings");
+                let merged_sections = merge_release_notes(&releases_to_process, &merge_options);
+                generate_csv(&merged_sections, &markdown_options.section_order)?
+            }
+            OutputFormat::Terminal => render_terminal(
+                &clean_markdown(&generate_markdown_merged_headings(&merged_by_heading, &markdown_options)),
+                config.color.enabled(),
+            ),
+        }
+    } else {
+        // Traditional merge - keep versions separate under each heading
+        debug!("Merging release notes by version");
+        let mut merged_sections = merge_release_notes(&releases_to_process, &merge_options);
+        if config.collapse_repeated {
+            let merged_by_heading = merge_release_notes_by_heading(&releases_to_process, &merge_options);
+            collapse_repeated_items(&mut merged_sections, &merged_by_heading, &releases_to_process);
+        }
+        if let Some(baseline) = &baseline {
+            baseline.remove_known_items(&mut merged_sections, |item| &item.content);
+        }
+        if config.stats {
+            stats = Some(SummaryStats::from_merged_sections(&merged_sections, &config.uncategorized_label));
+        }
+        metrics.begin(Phase::Generating);
+        match config.format {
+            OutputFormat::Markdown => match (&config.template, &config.append_to) {
+                (Some(path), _) => render_template_file(path, &merged_sections, &markdown_options.section_order)?,
+                (None, Some(path)) => append_to_existing(path, &merged_sections, &markdown_options)?,
+                (None, None) => match split_dir {
+                    Some(dir) => write_section_files(dir, merged_sections, &markdown_options.section_order, |section| {
+                        generate_markdown(section, &split_options)
+                    })?,
+                    None => generate_markdown(&merged_sections, &markdown_options),
+                },
+            },
+            OutputFormat::Json => generate_json(&merged_sections)?,
+            OutputFormat::Yaml => generate_yaml(&merged_sections)?,
+            OutputFormat::Toml => generate_toml(&merged_sections)?,
+            OutputFormat::Html => generate_html(&merged_sections, &markdown_options.section_order, markdown_options.date_format.as_deref()),
+            OutputFormat::Keepachangelog => generate_keep_a_changelog(&merged_sections, &changelog_mapping, &config.uncategorized_label),
+            OutputFormat::Atom => generate_atom_feed(&releases_to_process),
+            OutputFormat::Terminal => render_terminal(
+                &clean_markdown(&generate_markdown(&merged_sections, &markdown_options)),
+                config.color.enabled(),
+            ),
+            OutputFormat::Csv => generate_csv(&merged_sections, &markdown_options.section_order)?,
+        }
+    };
+
+    let appending = config.append_to.is_some();
+
+    if config.contributors && !appending {
+        if config.format == OutputFormat::Markdown {
+            let contributors = collect_contributors(&releases_to_process);
+            markdown.push_str(&generate_contributors_markdown(&contributors));
+        } else {
+            warn!("--contributors is only supported for Markdown output, ignoring");
+        }
+    }
+
+    if let Some(stats) = stats.filter(|_| !appending) {
+        if config.format == OutputFormat::Markdown {
+            markdown.push_str(&generate_stats_markdown(&stats));
+        } else {
+            warn!("--stats is only supported for Markdown output, ignoring");
+        }
+    }
+
+    // Tidy the built-in Markdown layouts; template output is left exactly as rendered
+    if config.format == OutputFormat::Markdown && (config.template.is_none() || config.merge_headings) {
+        markdown = clean_markdown(&markdown);
+    }
+
+    if config.shift_headings > 0 {
+        if config.format != OutputFormat::Markdown {
+            warn!("--shift-headings only applies to Markdown output, ignoring");
+        } else if appending {
+            warn!("--append needs the file's headings at their usual levels, ignoring --shift-headings");
+        } else {
+            markdown = shift_headings(&markdown, config.shift_headings);
+        }
+    }
+
+    metrics.log_summary();
+    Ok(markdown)
+}
+
+/// Items of a previously generated document, keyed by normalized section name and trimmed line
+struct Baseline {
+    items: HashSet<(String, String)>,
+}
+
+impl Baseline {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read --diff-against file: {:?}", path))?;
+        Ok(Self::parse(&content))
+    }
+    
+    fn parse(content: &str) -> Self {
+        let items = extract_sections(content, DEFAULT_MAX_HEADING_DEPTH)
+            .into_iter()
+            .flat_map(|(section, lines)| {
+                let section = normalize_section_name(&section);
+                lines
+                    .into_iter()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .map(move |line| (section.clone(), line))
+            })
+            .collect();
+        Baseline { items }
+    }
+    
+    // Items of another release, merged with the same options so their content compares like for like
+    fn from_merged(merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>) -> Self {
+        let items = merged_sections
+            .iter()
+            .flat_map(|(section, items)| {
+                let section = normalize_section_name(section);
+                items
+                    .iter()
+                    .flat_map(|item| item.content.lines())
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(move |line| (section.clone(), line.to_string()))
+            })
+            .collect();
+        Baseline { items }
+    }
+    
+    // An item is known when every one of its lines appears under the same section in the baseline
+    fn contains(&self, section: &str, content: &str) -> bool {
+        let section = normalize_section_name(section);
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .all(|line| self.items.contains(&(section.clone(), line.to_string())))
+    }
+    
+    // Drop items the baseline already lists, and any section left empty
+    fn remove_known_items<T>(&self, merged_sections: &mut HashMap<String, Vec<T>>, content: impl Fn(&T) -> &str) {
+        let mut removed = 0;
+        for (section_name, items) in merged_sections.iter_mut() {
+            let before = items.len();
+            items.retain(|item| !self.contains(section_name, content(item)));
+            removed += before - items.len();
+        }
+        merged_sections.retain(|_, items| !items.is_empty());
+        info!("Left out {} items already in the baseline", removed);
+    }
+}
+
+/// File name of the index written alongside per-section files; no section is slugged to it
+pub const SPLIT_INDEX_FILE: &str = "index.md";
+
+// Write each section to `<dir>/<slug>.md`, rendered on its own, and return an index linking to them
+fn write_section_files<T>(
+    dir: &Path,
+    mut merged_sections: HashMap<String, Vec<T>>,
+    section_order: &SectionOrder,
+    render: impl Fn(&HashMap<String, Vec<T>>) -> String,
+) -> Result<String> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create output directory: {:?}", dir))?;
+    
+    let section_names: Vec<String> = sort_section_names(&merged_sections, section_order)
+        .into_iter()
+        .cloned()
+        .collect();
+    let section_count = section_names.len();
+    let mut slugs = HtmlAnchors::default();
+    slugs.unique(SPLIT_INDEX_FILE.trim_end_matches(".md"));
+    let mut index = String::from("# Aggregated Release Notes\n\n");
+    
+    for section_name in section_names {
+        let Some(items) = merged_sections.remove(&section_name) else {
+            continue;
+        };
+        let file_name = format!("{}.md", slugs.unique(&section_name));
+        let document = render(&HashMap::from([(section_name.clone(), items)]));
+        // Drop the document title so each file starts at its section heading
+        let content = document.split_once("\n\n").map_or(document.as_str(), |(_, rest)| rest);
+        
+        let path = dir.join(&file_name);
+        debug!("Writing section {} to {:?}", section_name, path);
+        write_output_atomic(&path, clean_markdown(content).as_bytes())?;
+        index.push_str(&format!("- [{}]({})\n", section_name, file_name));
+    }
+    
+    info!("Wrote {} section files to {:?}", section_count, dir);
+    Ok(index)
+}
+
+/// Share rendered output as a secret GitHub Gist, returning its URL; needs a token with the gist scope
+pub async fn publish_gist(config: &AggregatorConfig, content: &str) -> Result<String> {
+    let token = config
+        .token
+        .as_deref()
+        .context("Creating a gist needs a GitHub token with the gist scope (--token or GITHUB_TOKEN)")?;
+    
+    let repositories: Vec<String> = resolve_repositories(&config.owner, &config.repo, &config.source)?
+        .into_iter()
+        .map(|source| format!("{}/{}", source.owner, source.repo))
+        .collect();
+    let description = format!("Aggregated release notes for {}", repositories.join(", "));
+    let file_name = format!("release-notes.{}", config.format.extension());
+    
+    create_gist(github_api_url(config), token, &file_name, &description, content).await
+}
+
+/// Describe the GitHub API quota available to `config.token` without aggregating anything
+pub async fn check_rate_limit(config: &AggregatorConfig) -> Result<String> {
+    let rate_limit = fetch_rate_limit(github_api_url(config), config.token.as_deref(), &retry_policy(config), &RequestCounter::default()).await?;
+    Ok(format_rate_limit(&rate_limit, config.token.is_some()))
+}
+
+fn github_api_url(config: &AggregatorConfig) -> &str {
+    config.base_url.as_deref().unwrap_or(GITHUB_API_URL)
+}
+
+fn retry_policy(config: &AggregatorConfig) -> RetryPolicy {
+    RetryPolicy {
+        max_retries: config.max_retries,
+        ..RetryPolicy::default()
+    }
+}
+
+fn format_rate_limit(rate_limit: &helpers::RateLimit, authenticated: bool) -> String {
+    let reset = chrono::DateTime::from_timestamp(rate_limit.reset as i64, 0)
+        .map_or_else(|| rate_limit.reset.to_string(), |reset| reset.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+    format!(
+        "GitHub API rate limit ({}): {}/{} requests remaining, resets at {}\n",
+        if authenticated { "authenticated" } else { "unauthenticated" },
+        rate_limit.remaining,
+        rate_limit.limit,
+        reset
+    )
+}
+
+// Log the anonymous quota before fetching and stop early when it is used up. Not retried: the
+// check is only a courtesy and shouldn't hold up the run when `/rate_limit` is struggling
+async fn check_remaining_quota(config: &AggregatorConfig, requests: &RequestCounter) -> Result<()> {
+    let retry = RetryPolicy { max_retries: 0, ..retry_policy(config) };
+    let rate_limit = match fetch_rate_limit(github_api_url(config), None, &retry, requests).await {
+        Ok(rate_limit) => rate_limit,
+        Err(err) => {
+            // Some GitHub Enterprise servers have rate limiting turned off entirely
+            debug!("Couldn't check the GitHub rate limit: {:#}", err);
+            return Ok(());
+        }
+    };
+    
+    info!("{}", format_rate_limit(&rate_limit, false).trim_end());
+    if rate_limit.remaining == 0 {
+        return Err(anyhow::anyhow!(
+            "{}Pass --token or set GITHUB_TOKEN for a higher limit, or wait for the reset",
+            format_rate_limit(&rate_limit, false)
+        ));
+    }
+    Ok(())
+}
+
+/// Write to a temporary file beside `path` and rename it into place, so readers never see a partial file
+pub fn write_output_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Output path has no file name: {:?}", path))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    
+    let result = (|| -> Result<()> {
+        let mut file = File::create(&temp_path)
+            .with_context(|| format!("Failed to create output file: {:?}", temp_path))?;
+        file.write_all(contents)
+            .with_context(|| format!("Failed to write to output file: {:?}", temp_path))?;
+        file.sync_all()
+            .with_context(|| format!("Failed to flush output file: {:?}", temp_path))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to move output into place: {:?}", path))
+    })();
+    
+    if result.is_err() {
+        debug!("Removing temporary output file {:?}", temp_path);
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Compress output with gzip, for archiving large aggregations
+pub fn gzip(contents: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(contents).context("Failed to compress output")?;
+    let compressed = encoder.finish().context("Failed to compress output")?;
+    debug!("Compressed {} bytes of output to {}", contents.len(), compressed.len());
+    Ok(compressed)
+}
+
+/// A repository to fetch releases from, with the name its notes are attributed to
+#[derive(Debug, Clone, PartialEq)]
+struct RepositorySource {
+    owner: String,
+    repo: String,
+    label: Option<String>,
+}
+
+impl RepositorySource {
+    fn new(owner: &str, repo: &str) -> Self {
+        RepositorySource {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            label: None,
+        }
+    }
+    
+    /// Parse `owner/repo` or `owner/repo:Label`; the owner may hold GitLab subgroups
+    fn parse(spec: &str) -> Result<Self> {
+        let (path, label) = match spec.split_once(':') {
+            Some((path, label)) => (path, Some(label.trim()).filter(|label| !label.is_empty())),
+            None => (spec, None),
+        };
+        let (owner, repo) = path
+            .trim()
+            .rsplit_once('/')
+            .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid --source '{}', expected owner/repo or owner/repo:Label", spec))?;
+        
+        Ok(RepositorySource {
+            label: label.map(str::to_string),
+            ..RepositorySource::new(owner, repo)
+        })
+    }
+}
+
+// Pair each --repo with its owner: a single owner applies to every repo, otherwise they pair up in order.
+// Repositories given with --source follow
+fn resolve_repositories(owners: &[String], repos: &[String], sources: &[String]) -> Result<Vec<RepositorySource>> {
+    let mut repositories: Vec<RepositorySource> = match owners {
+        [owner] => repos.iter().map(|repo| RepositorySource::new(owner, repo)).collect(),
+        _ if owners.len() == repos.len() => owners
+            .iter()
+            .zip(repos)
+            .map(|(owner, repo)| RepositorySource::new(owner, repo))
+            .collect(),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Got {} --owner values for {} --repo values; pass one owner, or one per repo",
+                owners.len(),
+                repos.len()
+            ))
+        }
+    };
+    
+    for spec in sources {
+        repositories.push(RepositorySource::parse(spec)?);
+    }
+    
+    if repositories.is_empty() {
+        return Err(anyhow::anyhow!("No repositories given; pass --owner and --repo, or --source"));
+    }
+    Ok(repositories)
+}
+
+/// Check that a strftime format only uses specifiers chrono understands, so a typo fails before anything is fetched
+pub fn parse_date_format(value: &str) -> Result<String> {
+    use std::fmt::Write as _;
+    
+    let sample = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+    let mut formatted = String::new();
+    write!(formatted, "{}", sample.format(value))
+        .map_err(|_| anyhow::anyhow!("Invalid date format '{}', expected strftime specifiers such as %Y-%m-%d or %d %b %Y", value))?;
+    Ok(value.to_string())
+}
+
+/// Parse a window such as `30d` or `2w` into the span of days it covers
+pub fn parse_window(value: &str) -> Result<chrono::Duration> {
+    let value = value.trim();
+    let invalid = || anyhow::anyhow!("Invalid window '{}', expected a number of days or weeks like 30d or 2w", value);
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let count: i64 = value[..split].parse().map_err(|_| invalid())?;
+    if count == 0 {
+        return Err(invalid());
+    }
+    
+    match &value[split..] {
+        "d" => Ok(chrono::Duration::days(count)),
+        "w" => Ok(chrono::Duration::weeks(count)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Prefer a token given explicitly, falling back to a non-blank environment value
+pub fn resolve_token(flag_token: Option<String>, env_token: Option<String>) -> Option<String> {
+    if flag_token.is_some() {
+        debug!("Using GitHub token from --token flag");
+        return flag_token;
+    }
+
+    match env_token.filter(|token| !token.trim().is_empty()) {
+        Some(token) => {
+            debug!("Using GitHub token from GITHUB_TOKEN environment variable");
+            Some(token)
+        }
+        None => {
+            debug!("No GitHub token found in --token flag or GITHUB_TOKEN environment variable");
+            None
+        }
+    }
+}
+
+// Fetch every repository's releases, from a local file if one was given, tagged with the repository they came from
+async fn fetch_all_releases(
+    config: &AggregatorConfig,
+    repositories: &[RepositorySource],
+    requests: &RequestCounter,
+    progress: &Progress,
+) -> Result<Vec<Release>> {
+    let fetch_prereleases = config.include_prereleases || config.exclude_prereleases_from_output;
+    let mut fetched = Vec::new();
+    
+    for RepositorySource { owner, repo, label } in repositories {
+        let name = format!("{}/{}", owner, repo);
+        logging::set_current_repo(Some(&name));
+        info!("Fetching release notes for {}", name);
+        
+        let releases = match (&config.from_file, config.provider) {
+            (Some(path), _) => collect_releases(&FileSource::new(path), fetch_prereleases, config.include_drafts).await?,
+            (None, Provider::Github) => {
+                let source = GitHubSource {
+                    progress: progress.clone(),
+                    ..GitHubSource::from_config(config, owner, repo, requests)
+                };
+                collect_releases(&source, fetch_prereleases, config.include_drafts).await?
+            }
+            (None, Provider::Gitlab) => {
+                collect_releases(&GitLabSource::from_config(config, owner, repo, requests), fetch_prereleases, config.include_drafts).await?
+            }
+            (None, Provider::Gitea) => {
+                collect_releases(&GiteaSource::from_config(config, owner, repo, requests)?, fetch_prereleases, config.include_drafts).await?
+            }
+        };
+        fetched.push((name, label.clone(), releases));
+        
+        // A local file holds a single repository's releases
+        if config.from_file.is_some() {
+            break;
+        }
+    }
+    
+    Ok(combine_repository_releases(fetched))
+}
+
+// Fetch every repository's releases, narrowed to one component's tags before anything else looks at them
+async fn fetch_component_releases(
+    config: &AggregatorConfig,
+    repositories: &[RepositorySource],
+    requests: &RequestCounter,
+    progress: &Progress,
+) -> Result<Vec<Release>> {
+    let releases = fetch_all_releases(config, repositories, requests, progress).await?;
+    
+    // Later log lines concern every repository at once
+    if repositories.len() > 1 {
+        logging::set_current_repo(None);
+    }
+    info!("Found {} releases total", releases.len());
+    
+    let releases = match &config.tag_pattern {
+        Some(pattern) => filter_releases_by_tag_pattern(releases, pattern)?,
+        None => releases,
+    };
+    Ok(match &config.tag_prefix {
+        Some(prefix) => filter_releases_by_tag_prefix(releases, prefix),
+        None => releases,
+    })
+}
+
+/// Most retries `--retry-missing-tags` accepts, so a mistyped tag can't stall a run for long
+pub const MAX_MISSING_TAG_RETRIES: u32 = 5;
+
+// Wait before the first refetch for missing tags; each further retry waits one step longer
+const MISSING_TAG_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Tags named by --versions, --start-tag or --end-tag that none of the releases match
+fn missing_selection_tags(config: &AggregatorConfig, releases: &[Release]) -> Vec<String> {
+    // Generated notes are for a tag that needn't be released yet
+    if config.generate {
+        return Vec::new();
+    }
+    
+    let versions = config.versions.iter().flat_map(|versions| versions.split(',')).map(str::trim);
+    config
+        .start_tag
+        .as_deref()
+        .into_iter()
+        .chain(config.end_tag.as_deref())
+        .chain(versions)
+        .filter(|tag| !tag.is_empty() && tag_position(releases, tag).is_none())
+        .map(str::to_string)
+        .collect()
+}
+
+async fn collect_releases<S: ReleaseSource>(
+    source: &S,
+    include_prereleases: bool,
+    include_drafts: bool,
+) -> Result<Vec<Release>> {
+    let releases = source.fetch().await?;
+    Ok(prepare_releases(releases, include_prereleases, include_drafts))
+}
+
+// Apply draft and prerelease filtering and sort by published date (newest first)
+fn prepare_releases(releases: Vec<Release>, include_prereleases: bool, include_drafts: bool) -> Vec<Release> {
+    // Drafts are dropped unless asked for, in which case their creation time serves as their date
+    let releases: Vec<Release> = if include_drafts {
+        releases
+            .into_iter()
+            .map(|release| match (&release.created_at, release.published_at.is_empty()) {
+                (Some(created_at), true) => Release {
+                    published_at: created_at.clone(),
+                    ..release
+                },
+                _ => release,
+            })
+            .collect()
+    } else {
+        let draft_count = releases.iter().filter(|r| r.draft).count();
+        debug!("Filtered out {} drafts", draft_count);
+        releases.into_iter().filter(|r| !r.draft).collect()
+    };
+    
+    // Filter out prereleases if not included
+    let filtered_releases = if !include_prereleases {
+        let prerelease_count = releases.iter().filter(|r| r.prerelease).count();
+        let filtered = releases.into_iter().filter(|r| !r.prerelease).collect::<Vec<_>>();
+        debug!("Filtered out {} prereleases", prerelease_count);
+        filtered
+    } else {
+        releases
+    };
+
+    // A missing or malformed date would break every later date comparison
+    let mut sorted_releases: Vec<Release> = filtered_releases
+        .into_iter()
+        .filter(|release| match release.published() {
+            Ok(_) => true,
+            Err(err) => {
+                warn!("Skipping release: {:#}", err);
+                false
+            }
+        })
+        // Bodies written on Windows keep a `\r` on every line, which would defeat exact comparisons later
+        .map(|release| Release {
+            body: release.body.as_deref().map(normalize_line_endings),
+            ..release
+        })
+        .collect();
+    sort_releases_by_date(&mut sorted_releases);
+    sorted_releases
+}
+
+// Label each release with its repository and interleave them newest first
+fn combine_repository_releases(fetched: Vec<(String, Option<String>, Vec<Release>)>) -> Vec<Release> {
+    let mut combined = Vec::new();
+    for (repo, label, releases) in fetched {
+        combined.extend(releases.into_iter().map(|release| Release {
+            repo: repo.clone(),
+            label: label.clone(),
+            ..release
+        }));
+    }
+    
+    sort_releases_by_date(&mut combined);
+    combined
+}
+
+fn exclude_prereleases(releases: Vec<Release>) -> Vec<Release> {
+    let (prereleases, releases): (Vec<Release>, Vec<Release>) = releases.into_iter().partition(|r| r.prerelease);
+    debug!("Excluding {} pre-releases from output", prereleases.len());
+    releases
+}
+
+// Keep releases whose tag matches a shell-style glob such as `backend-v*`
+fn filter_releases_by_tag_pattern(releases: Vec<Release>, pattern: &str) -> Result<Vec<Release>> {
+    let glob = glob::Pattern::new(pattern).with_context(|| format!("Invalid --tag-pattern '{}'", pattern))?;
+    let total = releases.len();
+    let filtered: Vec<Release> = releases
+        .into_iter()
+        .filter(|release| glob.matches(&release.tag_name))
+        .collect();
+    
+    info!("{} of {} releases match tag pattern '{}'", filtered.len(), total, pattern);
+    Ok(filtered)
+}
+
+// Keep releases whose tag starts with `prefix`, stripping it so only the version is shown
+fn filter_releases_by_tag_prefix(releases: Vec<Release>, prefix: &str) -> Vec<Release> {
+    let total = releases.len();
+    let filtered: Vec<Release> = releases
+        .into_iter()
+        .filter_map(|release| {
+            let version = release.tag_name.strip_prefix(prefix)?.to_string();
+            Some(Release { tag_name: version, ..release })
+        })
+        .collect();
+    
+    info!("{} of {} releases have tag prefix '{}'", filtered.len(), total, prefix);
+    filtered
+}
+
+// Keep the `count` most recently published releases
+fn take_latest_releases(mut releases: Vec<Release>, count: usize) -> Vec<Release> {
+    sort_releases_by_date(&mut releases);
+    releases.truncate(count);
+    releases
+}
+
+// Sort by published date (newest first)
+fn sort_releases_by_date(releases: &mut [Release]) {
+    // Releases without a usable date sort last instead of aborting the run
+    releases.sort_by_key(|release| std::cmp::Reverse(release.published().ok()));
+    
+    debug!("Releases sorted by date (newest first)");
+}
+
+fn filter_releases_by_range(
+    releases: &[Release], 
+    start_tag: Option<&str>,
+    end_tag: Option<&str>,
+    order_by: ReleaseOrder,
+    strict: bool,
+    exclusive_start: bool,
+    exclusive_end: bool,
+) -> Result<Vec<Release>> {
+    let ordered = match order_by {
+        ReleaseOrder::Date => releases.to_vec(),
+        ReleaseOrder::Semver => sort_releases_by_semver(releases, start_tag, end_tag),
+    };
+    let releases = &ordered[..];
+
+    let mut filtered = releases.to_vec();
+    
+    if let (Some(start_tag), Some(end_tag)) = (start_tag, end_tag) {
+        debug!("Filtering releases between tags '{}' and '{}'", start_tag, end_tag);
+        let start_index = tag_position(releases, start_tag)
+            .ok_or_else(|| TagNotFound(format!("Start tag '{}' not found{}", start_tag, missing_tag_hint(releases, start_tag))))?;
+        
+        let end_index = tag_position(releases, end_tag)
+            .ok_or_else(|| TagNotFound(format!("End tag '{}' not found{}", end_tag, missing_tag_hint(releases, end_tag))))?;
+
+        // Releases are newest first, so a start tag before the end tag is the newer of the two
+        if strict && start_index < end_index {
+            return Err(anyhow::anyhow!(
+                "Start tag '{}' ({}) is newer than end tag '{}' ({}); swap them, or drop --strict-range to accept either order",
+                releases[start_index].tag_name,
+                release_date_label(&releases[start_index]),
+                releases[end_index].tag_name,
+                release_date_label(&releases[end_index])
+            ));
+        }
+        
+        // Ensure we get releases between the two tags (inclusive)
+        let (lower_index, higher_index) = if start_index <= end_index {
+            (start_index, end_index)
+        } else {
+            (end_index, start_index)
+        };
+        
+        debug!("Tag indices: start={}, end={}", lower_index, higher_index);
+
+        filtered = releases.iter().enumerate()
+            .filter(|(i, _)| *i >= lower_index && *i <= higher_index)
+            .filter(|(i, _)| !(exclusive_start && *i == start_index))
+            .filter(|(i, _)| !(exclusive_end && *i == end_index))
+            .map(|(_, r)| r.clone())
+            .collect();
+    } else if let Some(start_tag) = start_tag {
+        // Only start tag specified - get from that tag to the latest, which come before it
+        debug!("Filtering releases from tag '{}' to latest", start_tag);
+        let start_index = tag_position(releases, start_tag)
+            .ok_or_else(|| TagNotFound(format!("Start tag '{}' not found{}", start_tag, missing_tag_hint(releases, start_tag))))?;
+            
+        filtered = releases.iter().enumerate()
+            .filter(|(i, _)| *i < start_index || (*i == start_index && !exclusive_start))
+            .map(|(_, r)| r.clone())
+            .collect();
+    } else if let Some(end_tag) = end_tag {
+        // Only end tag specified - get from the earliest, which come after it, to that tag
+        debug!("Filtering releases from earliest to tag '{}'", end_tag);
+        let end_index = tag_position(releases, end_tag)
+            .ok_or_else(|| TagNotFound(format!("End tag '{}' not found{}", end_tag, missing_tag_hint(releases, end_tag))))?;
+            
+        filtered = releases.iter().enumerate()
+            .filter(|(i, _)| *i > end_index || (*i == end_index && !exclusive_end))
+            .map(|(_, r)| r.clone())
+            .collect();
+    }
+    
+    info!("Filtered to {} releases", filtered.len());
+    Ok(filtered)
+}
+
+// The `YYYY-MM-DD` part of a release's publication timestamp
+fn release_date_label(release: &Release) -> &str {
+    release.published_at.get(..10).unwrap_or(&release.published_at)
+}
+
+// Sort releases by semantic version (newest first), falling back to the existing date order
+// when the range boundaries can't be compared as semver
+fn sort_releases_by_semver(
+    releases: &[Release],
+    start_tag: Option<&str>,
+    end_tag: Option<&str>,
+) -> Vec<Release> {
+    let non_semver_bounds: Vec<&str> = [start_tag, end_tag]
+        .into_iter()
+        .flatten()
+        .filter(|tag| !is_semver(tag))
+        .collect();
+
+    if !non_semver_bounds.is_empty() {
+        warn!(
+            "Tags {:?} are not valid semver, falling back to date ordering",
+            non_semver_bounds
+        );
+        return releases.to_vec();
+    }
+
+    let (mut sorted, skipped): (Vec<Release>, Vec<Release>) = releases
+        .iter()
+        .cloned()
+        .partition(|r| is_semver(&r.tag_name));
+
+    if !skipped.is_empty() {
+        warn!(
+            "Skipping {} releases with non-semver tags when ordering by semver",
+            skipped.len()
+        );
+    }
+
+    sorted.sort_by(|a, b| compare_semver(&b.tag_name, &a.tag_name));
+    debug!("Releases sorted by semver (newest first)");
+    sorted
+}
+
+fn filter_releases_by_tags(releases: &[Release], tags: &[&str]) -> Result<Vec<Release>> {
+    debug!("Filtering releases by specific tags: {:?}", tags);
+    let mut filtered_releases = Vec::new();
+    let mut missing_tags = Vec::new();
+    
+    for tag in tags {
+        match tag_position(releases, tag) {
+            Some(index) => filtered_releases.push(releases[index].clone()),
+            None => missing_tags.push(format!("{}{}", tag, missing_tag_hint(releases, tag))),
+        }
+    }
+    
+    if !missing_tags.is_empty() {
+        error!("Missing tags: {:?}", missing_tags);
+        return Err(TagNotFound(format!("Could not find the following tags: {}", missing_tags.join(", "))).into());
+    }
+    
+    // Sort by published date (newest first)
+    sort_releases_by_date(&mut filtered_releases);
+    
+    info!("Filtered to {} releases", filtered_releases.len());
+    Ok(filtered_releases)
+}
+
+// Read tag names from a --versions-file
+fn load_versions_file(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read versions file: {:?}", path))?;
+    let tags = parse_versions_file(&content);
+    if tags.is_empty() {
+        return Err(anyhow::anyhow!("Versions file {:?} doesn't list any tags", path));
+    }
+    debug!("Read {} tags from {:?}", tags.len(), path);
+    Ok(tags)
+}
+
+// One tag per line; blank lines and lines starting with `#` are skipped
+fn parse_versions_file(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+// Keep releases at or above `min_version` by semver precedence, so `2.0.0-rc.1` falls below `2.0.0`
+fn filter_releases_by_min_version(releases: &[Release], min_version: &str) -> Result<Vec<Release>> {
+    if !is_semver(min_version) {
+        return Err(anyhow::anyhow!("--min-version '{}' is not a valid semantic version", min_version));
+    }
+    
+    let (semver, skipped): (Vec<&Release>, Vec<&Release>) = releases.iter().partition(|r| is_semver(&r.tag_name));
+    if !skipped.is_empty() {
+        let tags: Vec<&str> = skipped.iter().map(|r| r.tag_name.as_str()).collect();
+        warn!("Skipping {} releases with non-semver tags for --min-version: {:?}", skipped.len(), tags);
+    }
+    
+    let filtered: Vec<Release> = semver
+        .into_iter()
+        .filter(|r| compare_semver(&r.tag_name, min_version) != std::cmp::Ordering::Less)
+        .cloned()
+        .collect();
+    
+    info!("Filtered to {} releases at or above {}", filtered.len(), min_version);
+    Ok(filtered)
+}
+
+// Find a release by tag, falling back to case-insensitive and `v`-prefix-insensitive matches
+fn tag_position(releases: &[Release], tag: &str) -> Option<usize> {
+    releases
+        .iter()
+        .position(|r| r.tag_name == tag)
+        .or_else(|| releases.iter().position(|r| r.tag_name.eq_ignore_ascii_case(tag)))
+        .or_else(|| releases.iter().position(|r| tag_matches(&r.tag_name, tag)))
+}
+
+// Suggest the closest existing tag for one that wasn't found, e.g. " (did you mean 'v1.2.3'?)"
+fn missing_tag_hint(releases: &[Release], tag: &str) -> String {
+    let tag = tag.to_lowercase();
+    releases
+        .iter()
+        .map(|r| (edit_distance(&tag, &r.tag_name.to_lowercase()), &r.tag_name))
+        .filter(|(distance, _)| *distance <= (tag.chars().count() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, closest)| format!(" (did you mean '{}'?)", closest))
+        .unwrap_or_default()
+}
+
+fn filter_releases_by_date(
+    releases: &[Release],
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> Result<Vec<Release>> {
+    match (since, until) {
+        (Some(since), Some(until)) => {
+            debug!("Filtering releases published between {} and {}", since, until);
+            if since > until {
+                return Err(anyhow::anyhow!(
+                    "Since date {} is after until date {}",
+                    since, until
+                ));
+            }
+        }
+        (Some(since), None) => debug!("Filtering releases published on or after {}", since),
+        (None, Some(until)) => debug!("Filtering releases published on or before {}", until),
+        (None, None) => {}
+    }
+
+    let mut filtered = Vec::new();
+    for release in releases {
+        let date = release.published()?.date();
+
+        let after_since = since.is_none_or(|since| date >= since);
+        let before_until = until.is_none_or(|until| date <= until);
+
+        if after_since && before_until {
+            filtered.push(release.clone());
+        }
+    }
+
+    info!("Filtered to {} releases", filtered.len());
+    Ok(filtered)
+}
+
+// Split a release body into sections, also returning the heading level each section was introduced at. Headings
+// deeper than `max_depth` are kept as content; a body without any section headings goes under `release_name` if
+// given rather than `uncategorized`
+fn parse_release_notes(
+    body: &str,
+    uncategorized: &str,
+    release_name: Option<&str>,
+    max_depth: usize,
+) -> (HashMap<String, Vec<String>>, HashMap<String, usize>) {
+    let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+    let mut levels: HashMap<String, usize> = HashMap::new();
+    let mut current_section = uncategorized.to_string();
+    
+    // Initialize with uncategorized section
+    sections.insert(current_section.clone(), Vec::new());
+    
+    // Define a regex for Markdown headings
+    let heading_regex = Regex::new(r"^(#{1,6})\s+(.+)$").unwrap();
+    // Setext underlines: `===` marks an h1 and `---` an h2 when they follow a paragraph line
+    let setext_regex = Regex::new(r"^ {0,3}(=+|-+)\s*$").unwrap();
+    
+    let mut lines = body.lines().peekable();
+    
+    // Skip a YAML front-matter block so its closing `---` isn't read as an underline
+    if lines.peek().map(|line| line.trim_end()) == Some("---") {
+        if let Some(length) = body.lines().skip(1).position(|line| line.trim_end() == "---") {
+            debug!("Skipping {} lines of front matter", length);
+            lines.nth(length + 1);
+        }
+    }
+    
+    // Whether the previous line was plain paragraph text that an underline could turn into a heading
+    let mut previous_is_paragraph = false;
+    // The opening marker of the code fence we're inside, if any
+    let mut open_fence: Option<String> = None;
+    
+    for line in lines {
+        if let Some(fence) = &open_fence {
+            // Code is kept verbatim, blank lines and `#` comments included
+            sections.get_mut(&current_section).unwrap().push(line.to_string());
+            if is_closing_fence(line, fence) {
+                open_fence = None;
+            }
+            continue;
+        }
+        
+        if let Some(fence) = fence_marker(line) {
+            sections.get_mut(&current_section).unwrap().push(line.to_string());
+            open_fence = Some(fence);
+            previous_is_paragraph = false;
+        } else if let Some(captures) = heading_regex.captures(line).filter(|captures| captures[1].len() <= max_depth) {
+            current_section = captures.get(2).unwrap().as_str().trim().to_string();
+            if !sections.contains_key(&current_section) {
+                sections.insert(current_section.clone(), Vec::new());
+            }
+            record_level(&mut levels, &current_section, captures.get(1).unwrap().as_str().len());
+            previous_is_paragraph = false;
+        } else if previous_is_paragraph && setext_regex.is_match(line) && setext_level(line) <= max_depth {
+            // Promote the paragraph line we just recorded to a section heading
+            let heading = sections.get_mut(&current_section).unwrap().pop().unwrap();
+            current_section = heading.trim().to_string();
+            if !sections.contains_key(&current_section) {
+                sections.insert(current_section.clone(), Vec::new());
+            }
+            record_level(&mut levels, &current_section, setext_level(line));
+            previous_is_paragraph = false;
+        } else if compare_link(line).is_some() {
+            // GitHub's auto-generated "**Full Changelog**: <compare url>" footer isn't release content
+            debug!("Skipping compare link: {}", line.trim());
+            previous_is_paragraph = false;
+        } else if !line.trim().is_empty() {
+            // Add non-empty lines to the current section
+            sections.get_mut(&current_section).unwrap().push(line.to_string());
+            previous_is_paragraph = is_paragraph_line(line) && !setext_regex.is_match(line);
+        } else {
+            previous_is_paragraph = false;
+        }
+    }
+    
+    if let Some(release_name) = release_name.filter(|_| levels.is_empty()) {
+        if let Some(lines) = sections.remove(uncategorized) {
+            sections.insert(release_name.to_string(), lines);
+        }
+    }
+    
+    // Remove sections with no content
+    sections.retain(|_, lines| !lines.is_empty());
+    levels.retain(|name, _| sections.contains_key(name));
+    
+    debug!("Parsed {} sections from release notes", sections.len());
+    (sections, levels)
+}
+
+// `===` underlines an h1 and `---` an h2
+fn setext_level(underline: &str) -> usize {
+    if underline.trim_start().starts_with('=') { 1 } else { 2 }
+}
+
+// A heading repeated at several depths keeps its shallowest one
+fn record_level(levels: &mut HashMap<String, usize>, section: &str, level: usize) {
+    let recorded = levels.entry(section.to_string()).or_insert(level);
+    *recorded = (*recorded).min(level);
+}
+
+static COMPARE_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*\*\*Full Changelog\*\*:?\s*(https?://\S+/(?:compare|commits)/\S+)\s*$").unwrap()
+});
+
+// URL of an auto-generated "**Full Changelog**: https://github.com/o/r/compare/v1...v2" line
+fn compare_link(line: &str) -> Option<&str> {
+    COMPARE_LINK
+        .captures(line)
+        .map(|captures| captures.get(1).unwrap().as_str())
+}
+
+static HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s{0,3}#{1,6}\s").unwrap());
+
+// A reference must start a word, so URL fragments and existing `[#1](...)` links are skipped
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(^|[\s(,;])((?:[A-Za-z0-9][\w.-]*/[\w.-]+)?)#(\d+)\b").unwrap());
+
+// Link `#123` to `repo`'s issue and `owner/repo#123` to the other repository's, leaving code spans and headings alone
+fn link_references(line: &str, repo: &str) -> String {
+    if HEADING.is_match(line) {
+        return line.to_string();
+    }
+    
+    // Odd-numbered pieces between backticks are code spans
+    line.split('`')
+        .enumerate()
+        .map(|(i, piece)| {
+            if i % 2 == 1 {
+                return piece.to_string();
+            }
+            REFERENCE
+                .replace_all(piece, |captures: &regex::Captures| {
+                    let target = match captures.get(2).map_or("", |m| m.as_str()) {
+                        "" => repo,
+                        other => other,
+                    };
+                    if target.is_empty() {
+                        return captures[0].to_string();
+                    }
+                    format!(
+                        "{}[{}#{}](https://github.com/{}/issues/{})",
+                        &captures[1], &captures[2], &captures[3], target, &captures[3]
+                    )
+                })
+                .into_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("`")
+}
+
+// Plain text that can serve as a Setext heading (not a list item, quote, or indented code)
+fn is_paragraph_line(line: &str) -> bool {
+    let indent = line.len() - line.trim_start().len();
+    
+    indent < 4 && !line.trim_start().starts_with('>') && !is_list_item(line)
+}
+
+// Append each indented line that isn't a list item itself to the bullet it continues. Lines in fenced
+// code blocks are left alone, as is indented text that doesn't follow a bullet
+fn join_wrapped_bullets(lines: Vec<String>) -> Vec<String> {
+    let mut joined: Vec<String> = Vec::with_capacity(lines.len());
+    let mut open_fence: Option<String> = None;
+    let mut in_bullet = false;
+    
+    for line in lines {
+        if let Some(fence) = &open_fence {
+            if is_closing_fence(&line, fence) {
+                open_fence = None;
+            }
+            joined.push(line);
+            continue;
+        }
+        if let Some(fence) = fence_marker(&line) {
+            open_fence = Some(fence);
+            in_bullet = false;
+            joined.push(line);
+            continue;
+        }
+        
+        let indented = line.starts_with([' ', '\t']);
+        match joined.last_mut() {
+            Some(previous) if in_bullet && indented && !is_list_item(&line) => {
+                previous.push(' ');
+                previous.push_str(line.trim());
+            }
+            _ => {
+                in_bullet = is_list_item(&line);
+                joined.push(line);
+            }
+        }
+    }
+    
+    joined
+}
+
+// Render emoji shortcodes in each line outside fenced code blocks, where GitHub shows them literally
+fn render_emoji_lines(lines: Vec<String>) -> Vec<String> {
+    let mut open_fence: Option<String> = None;
+    lines
+        .into_iter()
+        .map(|line| {
+            if let Some(fence) = &open_fence {
+                if is_closing_fence(&line, fence) {
+                    open_fence = None;
+                }
+                line
+            } else if let Some(fence) = fence_marker(&line) {
+                open_fence = Some(fence);
+                line
+            } else {
+                render_emoji(&line)
+            }
+        })
+        .collect()
+}
+
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*([-*+]|\d+[.)])(\s|$)").unwrap());
+
+// A bullet or numbered list item, at any nesting depth
+fn is_list_item(line: &str) -> bool {
+    LIST_ITEM.is_match(line)
+}
+
+// Section a conventional-commit type is listed under
+fn conventional_section(commit_type: &str) -> Option<&'static str> {
+    match commit_type.to_lowercase().as_str() {
+        "feat" | "feature" => Some("Features"),
+        "fix" | "bugfix" => Some("Bug Fixes"),
+        "perf" => Some("Performance"),
+        "refactor" => Some("Refactoring"),
+        "docs" => Some("Documentation"),
+        "test" | "tests" => Some("Tests"),
+        "build" => Some("Build System"),
+        "ci" => Some("Continuous Integration"),
+        "style" => Some("Styles"),
+        "chore" => Some("Chores"),
+        "revert" => Some("Reverts"),
+        _ => None,
+    }
+}
+
+// Group `- feat(scope): text` bullets by type, keeping the scope in bold; anything else stays uncategorized
+fn group_conventional_commits(lines: Vec<String>, uncategorized: &str) -> HashMap<String, Vec<String>> {
+    let commit_regex = Regex::new(r"^(\s*[-*+]\s+)(\w+)(?:\(([^)]*)\))?!?:\s+(.+)$").unwrap();
+    let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current_section = uncategorized.to_string();
+    
+    for line in lines {
+        // Indented lines continue the previous bullet wherever it went
+        if line.starts_with(char::is_whitespace) {
+            sections.entry(current_section.clone()).or_default().push(line);
+            continue;
+        }
+        
+        let conventional = commit_regex.captures(&line).and_then(|captures| {
+            let section = conventional_section(&captures[2])?;
+            let text = match captures.get(3).map(|scope| scope.as_str().trim()) {
+                Some(scope) if !scope.is_empty() => format!("{}**{}:** {}", &captures[1], scope, &captures[4]),
+                _ => format!("{}{}", &captures[1], &captures[4]),
+            };
+            Some((section, text))
+        });
+        
+        let line = match conventional {
+            Some((section, text)) => {
+                current_section = section.to_string();
+                text
+            }
+            None => {
+                current_section = uncategorized.to_string();
+                line
+            }
+        };
+        sections.entry(current_section.clone()).or_default().push(line);
+    }
+    
+    debug!("Grouped conventional commits into {} sections", sections.len());
+    sections
+}
+
+// Parse a release's body, dropping sections excluded by the merge options; also returns the source heading levels
+fn parse_sections_for_merge(release: &Release, options: &MergeOptions) -> (HashMap<String, Vec<String>>, HashMap<String, usize>) {
+    let body = release.body.as_deref().unwrap_or_default();
+    let release_name = options
+        .name_as_section
+        .then(|| distinct_release_name(release).unwrap_or_else(|| release.tag_name.clone()));
+    let (mut sections, mut levels) = parse_release_notes(body, &options.uncategorized, release_name.as_deref(), options.max_heading_depth);
+    
+    if options.join_wrapped_bullets {
+        for lines in sections.values_mut() {
+            *lines = join_wrapped_bullets(std::mem::take(lines));
+        }
+    }
+    
+    // `:bug: Bug Fixes` and `🐛 Bug Fixes` become one section
+    if options.render_emoji {
+        let mut rendered: HashMap<String, Vec<String>> = HashMap::new();
+        for (section_name, lines) in sections {
+            rendered.entry(render_emoji(&section_name)).or_default().extend(render_emoji_lines(lines));
+        }
+        sections = rendered;
+        let mut rendered_levels = HashMap::new();
+        for (section_name, level) in levels {
+            record_level(&mut rendered_levels, &render_emoji(&section_name), level);
+        }
+        levels = rendered_levels;
+    }
+    
+    // Without any headings everything lands in the uncategorized bucket, which commit prefixes can break up
+    if options.parse_conventional && sections.keys().all(|name| *name == options.uncategorized) {
+        if let Some(lines) = sections.remove(&options.uncategorized) {
+            sections = group_conventional_commits(lines, &options.uncategorized);
+        }
+    }
+    
+    // Synonyms share one section; a release using two of them keeps both sets of lines
+    let mut aliased: HashMap<String, Vec<String>> = HashMap::new();
+    for (section_name, lines) in sections {
+        aliased.entry(options.section_aliases.resolve(&section_name)).or_default().extend(lines);
+    }
+    let mut sections = aliased;
+    let mut aliased_levels = HashMap::new();
+    for (section_name, level) in levels {
+        record_level(&mut aliased_levels, &options.section_aliases.resolve(&section_name), level);
+    }
+    
+    if options.keep_compare_links {
+        let links: Vec<String> = body
+            .lines()
+            .filter_map(compare_link)
+            .map(|url| format!("- {}", url))
+            .collect();
+        if !links.is_empty() {
+            sections.entry("Changelog Links".to_string()).or_default().extend(links);
+        }
+    }
+    
+    sections.retain(|section_name, _| {
+        let allowed = options.allows_section(section_name);
+        if !allowed {
+            debug!("Skipping filtered section: {}", section_name);
+        }
+        allowed
+    });
+    (sections, aliased_levels)
+}
+
+// Notes with content but no section headings, which all end up in the uncategorized section
+fn is_unstructured(release: &Release, options: &MergeOptions) -> bool {
+    let has_content = |lines: &Vec<String>| lines.iter().any(|line| !line.trim().is_empty());
+    let (sections, _) = parse_sections_for_merge(release, options);
+    sections.get(&options.uncategorized).is_some_and(has_content)
+        && sections
+            .iter()
+            .all(|(section_name, lines)| *section_name == options.uncategorized || !has_content(lines))
+}
+
+// Point out releases whose notes have no headings at all, so everything in them ended up uncategorized;
+// one summary line normally, one line per release with --verbose
+fn uncategorized_diagnostics(releases: &[Release], options: &MergeOptions, verbose: bool) -> Vec<String> {
+    let unstructured: Vec<&Release> = releases
+        .iter()
+        .filter(|release| is_unstructured(release, options))
+        .collect();
+    
+    if unstructured.is_empty() {
+        return Vec::new();
+    }
+    
+    if verbose {
+        let multiple_repos = has_multiple_repos(releases.iter().map(|release| release.repo.as_str()));
+        unstructured
+            .iter()
+            .map(|release| {
+                format!(
+                    "Release {} has no section headings, so all of its notes are listed under {}",
+                    source_label(&release.repo, &release.tag_name, multiple_repos),
+                    options.uncategorized
+                )
+            })
+            .collect()
+    } else {
+        vec![format!(
+            "{} of {} releases have no section headings, so all of their notes are listed under {} (run with --verbose to see which)",
+            unstructured.len(),
+            releases.len(),
+            options.uncategorized
+        )]
+    }
+}
+
+/// Something `--validate` found wrong with a release's notes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    /// The release has no notes at all
+    EmptyBody,
+    /// The notes have content but no section headings, so everything would be uncategorized
+    NoSections,
+    /// A heading is nested deeper than `--max-heading-depth` allows, so it doesn't start a section
+    HeadingTooDeep { heading: String, level: usize, max: usize },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::EmptyBody => write!(f, "has no release notes"),
+            Problem::NoSections => write!(f, "has no section headings, so all of its notes would be uncategorized"),
+            Problem::HeadingTooDeep { heading, level, max } => {
+                write!(f, "heading \"{}\" is level {}, deeper than the maximum of {}", heading, level, max)
+            }
+        }
+    }
+}
+
+/// The problems found in one release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReleaseViolations {
+    pub repo: String,
+    pub tag_name: String,
+    pub problems: Vec<Problem>,
+}
+
+/// Returned (as the error) by `aggregate` when `validate` is set and any release has problems
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationFailure {
+    /// How many releases were checked
+    pub checked: usize,
+    /// Only the releases that failed, in the order they were checked
+    pub releases: Vec<ReleaseViolations>,
+}
+
+impl std::fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} of {} releases failed validation:", self.releases.len(), self.checked)?;
+        let multiple_repos = has_multiple_repos(self.releases.iter().map(|release| release.repo.as_str()));
+        for release in &self.releases {
+            for problem in &release.problems {
+                write!(f, "\n  {}: {}", source_label(&release.repo, &release.tag_name, multiple_repos), problem)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationFailure {}
+
+/// Returned by `aggregate` when there's nothing to aggregate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoReleases {
+    /// The repositories have no releases at all
+    Found,
+    /// Releases exist, but the selection left none of them; only an error with `fail_on_empty`
+    Matched,
+}
+
+impl std::fmt::Display for NoReleases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoReleases::Found => write!(f, "No releases found"),
+            NoReleases::Matched => write!(f, "No releases matched the selection"),
+        }
+    }
+}
+
+impl std::error::Error for NoReleases {}
+
+/// Returned by `aggregate` when a tag named by `start_tag`, `end_tag` or `versions` isn't among the releases
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagNotFound(String);
+
+impl std::fmt::Display for TagNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TagNotFound {}
+
+// Check each release's notes, returning only the releases with problems
+fn validate_releases(releases: &[Release], options: &MergeOptions) -> Vec<ReleaseViolations> {
+    releases
+        .iter()
+        .filter_map(|release| {
+            let body = release.body.as_deref().unwrap_or_default();
+            let mut problems = Vec::new();
+            
+            if body.trim().is_empty() {
+                problems.push(Problem::EmptyBody);
+            } else if is_unstructured(release, options) {
+                problems.push(Problem::NoSections);
+            }
+            
+            for (heading, level) in headings(body) {
+                if level > options.max_heading_depth {
+                    problems.push(Problem::HeadingTooDeep { heading, level, max: options.max_heading_depth });
+                }
+            }
+            
+            (!problems.is_empty()).then(|| ReleaseViolations {
+                repo: release.repo.clone(),
+                tag_name: release.tag_name.clone(),
+                problems,
+            })
+        })
+        .collect()
+}
+
+// Every ATX heading outside fenced code blocks, with its level
+fn headings(body: &str) -> Vec<(String, usize)> {
+    let heading_regex = Regex::new(r"^ {0,3}(#{1,6})\s+(.+?)(?:\s+#+)?\s*$").unwrap();
+    let mut open_fence: Option<String> = None;
+    let mut found = Vec::new();
+    
+    for line in body.lines() {
+        if let Some(fence) = &open_fence {
+            if is_closing_fence(line, fence) {
+                open_fence = None;
+            }
+            continue;
+        }
+        if let Some(fence) = fence_marker(line) {
+            open_fence = Some(fence);
+            continue;
+        }
+        if let Some(captures) = heading_regex.captures(line) {
+            found.push((captures[2].to_string(), captures[1].len()));
+        }
+    }
+    
+    found
+}
+
+// The release name, unless it is blank or just repeats the tag
+fn distinct_release_name(release: &Release) -> Option<String> {
+    release
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && *name != release.tag_name)
+        .map(str::to_string)
+}
+
+// Version heading text, e.g. `v1.0.0 — "Spring Release" (2023-01-01)`, with the release's age as of `today` if given
+// and the label linked to `url` if given
+fn version_heading(label: &str, item: &ReleaseNoteItem, today: Option<NaiveDate>, date_format: Option<&str>, url: Option<&str>) -> String {
+    let label = match url {
+        Some(url) => format!("[{}]({})", label, url),
+        None => label.to_string(),
+    };
+    let date = format_date(item.date, date_format.unwrap_or(DEFAULT_DATE_FORMAT));
+    let date = match today {
+        Some(today) => format!("{}, {}", date, relative_date(item.date, today)),
+        None => date,
+    };
+    match &item.name {
+        Some(name) => format!("{} \u{2014} \"{}\" ({})", label, name, date),
+        None => format!("{} ({})", label, date),
+    }
+}
+
+// Release page to link a version heading to, when --link-versions asks for one and the release has one
+fn version_url<'a>(item: &'a ReleaseNoteItem, options: &MarkdownOptions) -> Option<&'a str> {
+    item.url.as_deref().filter(|_| options.link_versions)
+}
+
+// How long before `today` a date was, in the largest whole unit: "yesterday", "3 weeks ago", "2 years ago"
+fn relative_date(date: NaiveDate, today: NaiveDate) -> String {
+    let days = (today - date).num_days();
+    let ago = |count: i64, unit: &str| {
+        format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+    };
+    match days {
+        i64::MIN..=-1 => "upcoming".to_string(),
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        2..=6 => ago(days, "day"),
+        7..=29 => ago(days / 7, "week"),
+        30..=364 => ago(days / 30, "month"),
+        _ => ago(days / 365, "year"),
+    }
+}
+
+/// Date format of version headings, unless `--date-format` replaces it
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Section collecting content that appears before any heading, unless `--uncategorized-label` renames it
+pub const DEFAULT_UNCATEGORIZED_LABEL: &str = "Uncategorized";
+
+/// Deepest heading level that starts a section unless `--max-heading-depth` says otherwise
+pub const DEFAULT_MAX_HEADING_DEPTH: usize = 2;
+
+// Stands in for the notes of a release published without a body
+const EMPTY_RELEASE_PLACEHOLDER: &str = "*No release notes provided.*";
+
+// Section listing release assets with --list-assets
+const ASSETS_SECTION: &str = "Assets";
+
+// One `- [name](url) (size)` bullet per asset, when assets are listed and their section is kept
+fn asset_items(release: &Release, options: &MergeOptions) -> Vec<String> {
+    if !options.list_assets || !options.allows_section(ASSETS_SECTION) {
+        return Vec::new();
+    }
+    release
+        .assets
+        .iter()
+        .map(|asset| format!("- [{}]({}) ({})", asset.name, asset.browser_download_url, format_size(asset.size)))
+        .collect()
+}
+
+// Name each section is shown under, keyed by its normalized name: the heading most releases
+// wrote it as, or the first one seen on a tie
+fn section_display_names(releases: &[Release], options: &MergeOptions) -> HashMap<String, String> {
+    // Normalized name -> each way it was written and how often, in first-seen order
+    let mut spellings: HashMap<String, Vec<(String, usize)>> = HashMap::new();
+    
+    for release in releases {
+        if release.body.is_some() {
+            let (sections, _) = parse_sections_for_merge(release, options);
+            let mut section_names: Vec<String> = sections.into_keys().collect();
+            section_names.sort();
+            for section_name in section_names {
+                let counts = spellings.entry(normalize_section_name(&section_name)).or_default();
+                match counts.iter_mut().find(|(name, _)| *name == section_name) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((section_name, 1)),
+                }
+            }
+        }
+    }
+    
+    spellings
+        .into_iter()
+        .filter_map(|(key, counts)| {
+            // `max_by_key` keeps the last of equal counts, so look from the back
+            let (name, _) = counts.into_iter().rev().max_by_key(|(_, count)| *count)?;
+            Some((key, name))
+        })
+        .collect()
+}
+
+fn merge_release_notes(releases: &[Release], options: &MergeOptions) -> HashMap<String, Vec<ReleaseNoteItem>> {
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    
+    // First pass - collect all possible sections
+    let known_sections = section_display_names(releases, options);
+    
+    debug!("Found {} unique section names across all releases", known_sections.len());
+    
+    // Initialize merged sections
+    for display_name in known_sections.values() {
+        merged_sections.insert(display_name.clone(), Vec::new());
+    }
+    
+    // Second pass - populate sections with items
+    for release in releases {
+        let date = match release.published() {
+            Ok(published) => published.date(),
+            Err(err) => {
+                warn!("Skipping release: {:#}", err);
+                continue;
+            }
+        };
+        
+        if release.body.is_some() {
+            let version = release.tag_name.clone();
+            
+            debug!("Processing release {} ({})", version, date);
+            let (sections, levels) = parse_sections_for_merge(release, options);
+            // Auto-generated notes sometimes repeat a bullet; keep only its first occurrence
+            let mut seen_items: HashMap<String, HashSet<String>> = HashMap::new();
+            
+            for (section_name, items) in sections {
+                let section_key = normalize_section_name(&section_name);
+                let seen_items = seen_items.entry(section_key.clone()).or_default();
+                
+                for item in items {
+                    // Other lines, such as code fence markers, legitimately repeat
+                    if is_list_item(&item) && !seen_items.insert(item.trim_end().to_string()) {
+                        debug!("Skipping duplicate item in {} / {}: {}", version, section_name, item);
+                        continue;
+                    }
+                    
+                    let item = if options.link_refs { link_references(&item, &release.repo) } else { item };
+                    let note_item = ReleaseNoteItem {
+                        content: item,
+                        version: version.clone(),
+                        date,
+                        repo: release.repo.clone(),
+                        label: release.label.clone(),
+                        name: distinct_release_name(release),
+                        level: levels.get(&section_name).copied(),
+                        url: release.html_url.clone(),
+                    };
+                    
+                    merged_sections.get_mut(&known_sections[&section_key]).unwrap().push(note_item);
+                }
+            }
+        }
+        
+        for item in asset_items(release, options) {
+            merged_sections.entry(ASSETS_SECTION.to_string()).or_default().push(ReleaseNoteItem {
+                content: item,
+                version: release.tag_name.clone(),
+                date,
+                repo: release.repo.clone(),
+                label: release.label.clone(),
+                name: distinct_release_name(release),
+                level: None,
+                url: release.html_url.clone(),
+            });
+        }
+        
+        if release.body.as_deref().is_none_or(|body| body.trim().is_empty()) {
+            debug!("Release {} has no body content", release.tag_name);
+            
+            // Keep the version visible unless asked to drop it
+            if !options.skip_empty {
+                let note_item = ReleaseNoteItem {
+                    content: EMPTY_RELEASE_PLACEHOLDER.to_string(),
+                    version: release.tag_name.clone(),
+                    date,
+                    repo: release.repo.clone(),
+                    label: release.label.clone(),
+                    name: distinct_release_name(release),
+                    level: None,
+                    url: release.html_url.clone(),
+                };
+                merged_sections.entry(options.uncategorized.clone()).or_default().push(note_item);
+            }
+        }
+    }
+    
+    debug!("Merged all release notes into sections");
+    merged_sections
+}
+
+// New function for merging content under common headings
+#[derive(Debug, Serialize)]
+struct MergedHeadingItem {
+    /// Release note item, including any nested lines beneath it
+    content: String,
+    /// Tag names of every release the line appeared in
+    sources: Vec<String>,
+    /// Repository (`owner/repo`) the releases belong to
+    repo: String,
+    /// Friendly name of the repository, when one was given with `--source`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+impl MergedHeadingItem {
+    // Name the item is attributed to: its source label, or else its repository
+    fn source(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.repo)
+    }
+}
+
+fn merge_release_notes_by_heading(releases: &[Release], options: &MergeOptions) -> HashMap<String, Vec<MergedHeadingItem>> {
+    let mut merged_sections: HashMap<String, Vec<MergedHeadingItem>> = HashMap::new();
+    
+    // First pass - collect all possible sections, by normalized name
+    let mut known_sections = section_display_names(releases, options);
+    
+    debug!("Found {} unique section names across all releases", known_sections.len());
+    
+    // Initialize merged sections
+    for display_name in known_sections.values() {
+        merged_sections.insert(display_name.clone(), Vec::new());
+    }
+    
+    // Second pass - collect all content items by normalized section name, keeping repositories apart
+    // (repository, dedup key) -> first-seen content and the versions it appeared in
+    type SectionContent = HashMap<(String, String), (String, Vec<String>)>;
+    let mut content_map: HashMap<String, SectionContent> = HashMap::new();
+    
+    for release in releases {
+        if release.body.is_some() {
+            let version = release.tag_name.clone();
+            debug!("Processing release {} for heading merge", version);
+            let (sections, _) = parse_sections_for_merge(release, options);
+            
+            for (section_name, items) in sections {
+                let section_key = normalize_section_name(&section_name);
+                let section_content = content_map.entry(section_key).or_default();
+                
+                for item in group_nested_items(&items) {
+                    // Only trailing whitespace is trimmed so nested list indentation survives
+                    let mut normalized_content = item.trim_end().to_string();
+                    if options.link_refs {
+                        normalized_content = link_references(&normalized_content, &release.repo);
+                    }
+                    
+                    let key = if options.fuzzy_dedup {
+                        fuzzy_dedup_key(&normalized_content)
+                    } else {
+                        normalized_content.clone()
+                    };
+                    let (_, versions) = section_content
+                        .entry((release.repo.clone(), key))
+                        .or_insert_with(|| (normalized_content, Vec::new()));
+                    // Near-duplicates within one release still count it only once
+                    if versions.last() != Some(&version) {
+                        versions.push(version.clone());
+                    }
+                }
+            }
+        }
+        
+        for item in asset_items(release, options) {
+            known_sections
+                .entry(normalize_section_name(ASSETS_SECTION))
+                .or_insert_with(|| ASSETS_SECTION.to_string());
+            content_map
+                .entry(normalize_section_name(ASSETS_SECTION))
+                .or_default()
+                .entry((release.repo.clone(), item.clone()))
+                .or_insert_with(|| (item, Vec::new()))
+                .1
+                .push(release.tag_name.clone());
+        }
+    }
+    
+    let labels: HashMap<&str, &str> = releases
+        .iter()
+        .filter_map(|release| Some((release.repo.as_str(), release.label.as_deref()?)))
+        .collect();
+    
+    // Third pass - create merged items
+    for (section_key, content_items) in content_map {
+        let section_name = known_sections[&section_key].clone();
+        let mut merged_items = Vec::new();
+        
+        for ((repo, _), (content, versions)) in content_items {
+            let merged_item = MergedHeadingItem {
+                content,
+                sources: versions,
+                label: labels.get(repo.as_str()).map(|label| label.to_string()),
+                repo,
+            };
+            
+            merged_items.push(merged_item);
+        }
+        
+        // Sort items by how many versions they appear in (most common first)
+        merged_items.sort_by(|a, b| {
+            // First by number of sources (descending)
+            let source_cmp = b.sources.len().cmp(&a.sources.len());
+            
+            // Then alphabetically by content (and repository) if tied
+            if source_cmp == std::cmp::Ordering::Equal {
+                a.content.cmp(&b.content).then_with(|| a.repo.cmp(&b.repo))
+            } else {
+                source_cmp
+            }
+        });
+        
+        merged_sections.insert(section_name, merged_items);
+    }
+    
+    debug!("Merged release notes by heading");
+    merged_sections
+}
+
+// Sort sections alphabetically, but put the uncategorized bucket at the end
+fn sort_section_names<'a, V>(merged_sections: &'a HashMap<String, V>, section_order: &SectionOrder) -> Vec<&'a String> {
+    // Sections named in --section-order come first, in that order
+    let priority = |name: &str| {
+        let normalized = normalize_section_name(name);
+        section_order
+            .leading
+            .iter()
+            .position(|ordered| *ordered == normalized)
+            .unwrap_or(section_order.leading.len())
+    };
+    
+    let mut section_names: Vec<&String> = merged_sections.keys().collect();
+    section_names.sort_by(|a, b| {
+        if **a == section_order.uncategorized {
+            std::cmp::Ordering::Greater
+        } else if **b == section_order.uncategorized {
+            std::cmp::Ordering::Less
+        } else {
+            priority(a).cmp(&priority(b)).then_with(|| a.cmp(b))
+        }
+    });
+    section_names
+}
+
+// Group a section's items by release, sorted by date (newest first)
+fn group_items_by_version<'a>(
+    items: impl IntoIterator<Item = &'a ReleaseNoteItem>,
+) -> Vec<Vec<&'a ReleaseNoteItem>> {
+    let mut versions: HashMap<(&str, &str, NaiveDate), Vec<&ReleaseNoteItem>> = HashMap::new();
+    for item in items {
+        versions
+            .entry((&item.repo, &item.version, item.date))
+            .or_default()
+            .push(item);
+    }
+    
+    let mut version_entries: Vec<_> = versions.into_iter().collect();
+    version_entries.sort_by(|a, b| {
+        b.0.2.cmp(&a.0.2)
+            .then_with(|| a.0.0.cmp(b.0.0))
+            .then_with(|| a.0.1.cmp(b.0.1))
+    });
+    version_entries.into_iter().map(|(_, items)| items).collect()
+}
+
+// True when the items span more than one repository
+fn has_multiple_repos<'a>(repos: impl IntoIterator<Item = &'a str>) -> bool {
+    repos.into_iter().collect::<HashSet<_>>().len() > 1
+}
+
+// Label a version, prefixed with its repository when several repositories are aggregated
+fn source_label(repo: &str, version: &str, show_repo: bool) -> String {
+    if show_repo && !repo.is_empty() {
+        format!("{}@{}", repo, version)
+    } else {
+        version.to_string()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Contributor {
+    handle: String,
+    contributions: usize,
+}
+
+// Collect @handles from release bodies, counting each distinct PR (or unlinked line) as one contribution
+fn collect_contributors(releases: &[Release]) -> Vec<Contributor> {
+    // Handles can't follow a word character, which keeps email addresses like a@b.com out
+    let handle_regex = Regex::new(r"(?:^|[^\w.@/-])@([A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?)").unwrap();
+    let pr_regex = Regex::new(r"(?:^|[^\w&])#(\d+)\b").unwrap();
+    
+    let mut pull_requests: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut unlinked: HashMap<String, usize> = HashMap::new();
+    
+    for release in releases {
+        let Some(body) = &release.body else { continue };
+        
+        for line in body.lines() {
+            let prs: Vec<&str> = pr_regex
+                .captures_iter(line)
+                .map(|captures| captures.get(1).unwrap().as_str())
+                .collect();
+            
+            for captures in handle_regex.captures_iter(line) {
+                let handle = captures.get(1).unwrap().as_str().to_string();
+                if prs.is_empty() {
+                    *unlinked.entry(handle).or_default() += 1;
+                } else {
+                    pull_requests.entry(handle).or_default().extend(prs.iter().map(|pr| pr.to_string()));
+                }
+            }
+        }
+    }
+    
+    let handles: HashSet<&String> = pull_requests.keys().chain(unlinked.keys()).collect();
+    let mut contributors: Vec<Contributor> = handles
+        .into_iter()
+        .map(|handle| Contributor {
+            handle: handle.clone(),
+            contributions: pull_requests.get(handle).map_or(0, |prs| prs.len())
+                + unlinked.get(handle).copied().unwrap_or(0),
+        })
+        .collect();
+    
+    // Most active contributors first, then alphabetically
+    contributors.sort_by(|a, b| {
+        b.contributions
+            .cmp(&a.contributions)
+            .then_with(|| a.handle.to_lowercase().cmp(&b.handle.to_lowercase()))
+    });
+    
+    debug!("Found {} contributors", contributors.len());
+    contributors
+}
+
+// One line per release, newest first: `- v1.2.0 (2023-03-01): Spring Release`, falling back to the
+// first line of the notes when the release has no name of its own
+fn generate_summary(releases: &[Release]) -> String {
+    debug!("Generating release summary");
+    let mut releases: Vec<&Release> = releases.iter().collect();
+    releases.sort_by_key(|release| std::cmp::Reverse(release.published().ok()));
+    let multiple_repos = has_multiple_repos(releases.iter().map(|release| release.repo.as_str()));
+    
+    let mut markdown = String::from("# Release Summary\n\n");
+    for release in releases {
+        let label = source_label(release.label.as_deref().unwrap_or(&release.repo), &release.tag_name, multiple_repos);
+        let date = release.published().map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        let summary = distinct_release_name(release)
+            .or_else(|| release.body.as_deref().and_then(first_content_line))
+            .unwrap_or_else(|| "No release notes provided".to_string());
+        markdown.push_str(&format!("- {} ({}): {}\n", label, date, summary));
+    }
+    
+    info!("Generated summary output: {} bytes", markdown.len());
+    markdown
+}
+
+// First line of a release body that says something, skipping headings and without its list marker
+fn first_content_line(body: &str) -> Option<String> {
+    body.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| strip_list_marker(line).unwrap_or(line).to_string())
+}
+
+fn generate_contributors_markdown(contributors: &[Contributor]) -> String {
+    if contributors.is_empty() {
+        return String::new();
+    }
+    
+    let mut markdown = String::from("## Contributors\n\n");
+    for contributor in contributors {
+        let noun = if contributor.contributions == 1 { "contribution" } else { "contributions" };
+        markdown.push_str(&format!("- @{} ({} {})\n", contributor.handle, contributor.contributions, noun));
+    }
+    markdown.push('\n');
+    markdown
+}
+
+/// Record of which releases an aggregation covered, written by `--manifest`
+#[derive(Debug, Serialize)]
+struct ReleaseManifest {
+    releases: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    id: u64,
+    tag_name: String,
+    published_at: String,
+    /// `owner/repo` the release belongs to
+    repo: String,
+    /// Whether any of the release's notes survived section filtering
+    contributed: bool,
+}
+
+impl ReleaseManifest {
+    fn new(releases: &[Release], options: &MergeOptions) -> Self {
+        let releases = releases
+            .iter()
+            .map(|release| ManifestEntry {
+                id: release.id,
+                tag_name: release.tag_name.clone(),
+                published_at: release.published_at.clone(),
+                repo: release.repo.clone(),
+                contributed: release.body.is_some() && !parse_sections_for_merge(release, options).0.is_empty(),
+            })
+            .collect();
+        ReleaseManifest { releases }
+    }
+}
+
+// Counts describing the aggregated output
+#[derive(Debug, PartialEq)]
+struct SummaryStats {
+    releases: usize,
+    first_date: Option<NaiveDate>,
+    last_date: Option<NaiveDate>,
+    items: usize,
+    sections: Vec<(String, usize)>,
+}
+
+impl SummaryStats {
+    fn from_merged_sections(merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>, uncategorized: &str) -> Self {
+        let all_items = || merged_sections.values().flatten();
+        let releases: HashSet<(&str, &str)> = all_items()
+            .map(|item| (item.repo.as_str(), item.version.as_str()))
+            .collect();
+        
+        SummaryStats {
+            releases: releases.len(),
+            first_date: all_items().map(|item| item.date).min(),
+            last_date: all_items().map(|item| item.date).max(),
+            items: all_items().count(),
+            sections: section_counts(merged_sections, uncategorized),
+        }
+    }
+    
+    // Heading-merged items don't carry dates, so those come from the releases they cite
+    fn from_merged_headings(merged_sections: &HashMap<String, Vec<MergedHeadingItem>>, releases: &[Release], uncategorized: &str) -> Self {
+        let cited: HashSet<(&str, &str)> = merged_sections
+            .values()
+            .flatten()
+            .flat_map(|item| item.sources.iter().map(|source| (item.repo.as_str(), source.as_str())))
+            .collect();
+        let dates: Vec<NaiveDate> = releases
+            .iter()
+            .filter(|release| cited.contains(&(release.repo.as_str(), release.tag_name.as_str())))
+            .filter_map(|release| release.published().ok())
+            .map(|date| date.date())
+            .collect();
+        
+        SummaryStats {
+            releases: cited.len(),
+            first_date: dates.iter().min().copied(),
+            last_date: dates.iter().max().copied(),
+            items: merged_sections.values().map(Vec::len).sum(),
+            sections: section_counts(merged_sections, uncategorized),
+        }
+    }
+}
+
+fn section_counts<T>(merged_sections: &HashMap<String, Vec<T>>, uncategorized: &str) -> Vec<(String, usize)> {
+    sort_section_names(merged_sections, &SectionOrder::new(Vec::new(), uncategorized))
+        .into_iter()
+        .map(|name| (name.clone(), merged_sections[name].len()))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+fn generate_stats_markdown(stats: &SummaryStats) -> String {
+    let date_range = match (stats.first_date, stats.last_date) {
+        (Some(first), Some(last)) => format!("{} to {}", first.format("%Y-%m-%d"), last.format("%Y-%m-%d")),
+        _ => "n/a".to_string(),
+    };
+    
+    let mut markdown = String::from("## Summary\n\n| Metric | Value |\n| --- | --- |\n");
+    markdown.push_str(&format!("| Releases | {} |\n", stats.releases));
+    markdown.push_str(&format!("| Date range | {} |\n", date_range));
+    markdown.push_str(&format!("| Items | {} |\n\n", stats.items));
+    
+    markdown.push_str("| Section | Items |\n| --- | --- |\n");
+    for (section, count) in &stats.sections {
+        markdown.push_str(&format!("| {} | {} |\n", section.replace('|', "\\|"), count));
+    }
+    markdown.push('\n');
+    markdown
+}
+
+// Emit bullets repeated verbatim across a contiguous run of releases once, under the newest
+// release and annotated with the range, e.g. `- Known issue (v1.2.0–v1.4.0)`
+fn collapse_repeated_items(
+    merged_sections: &mut HashMap<String, Vec<ReleaseNoteItem>>,
+    merged_by_heading: &HashMap<String, Vec<MergedHeadingItem>>,
+    releases: &[Release],
+) {
+    for (heading_section, heading_items) in merged_by_heading {
+        let section_key = normalize_section_name(heading_section);
+        
+        // Items with nested lines are split up in version-separated output, so leave them be
+        for heading_item in heading_items
+            .iter()
+            .filter(|item| item.sources.len() > 1 && !item.content.contains('\n'))
+        {
+            // Adjacency is judged within the item's own repository, in processing order
+            let order: Vec<&str> = releases
+                .iter()
+                .filter(|release| release.repo == heading_item.repo)
+                .map(|release| release.tag_name.as_str())
+                .collect();
+            let mut positions: Vec<usize> = heading_item
+                .sources
+                .iter()
+                .filter_map(|source| order.iter().position(|tag| tag == source))
+                .collect();
+            positions.sort_unstable();
+            positions.dedup();
+            
+            for run in positions.chunk_by(|a, b| b - a == 1).filter(|run| run.len() > 1) {
+                let newest = order[run[0]];
+                let oldest = order[run[run.len() - 1]];
+                let run_versions: HashSet<&str> = run.iter().map(|&i| order[i]).collect();
+                debug!("Collapsing repeated item across {}..{}: {}", oldest, newest, heading_item.content);
+                
+                for (_, items) in merged_sections
+                    .iter_mut()
+                    .filter(|(name, _)| normalize_section_name(name) == section_key)
+                {
+                    items.retain_mut(|item| {
+                        let repeated = item.repo == heading_item.repo
+                            && item.content.trim_end() == heading_item.content
+                            && run_versions.contains(item.version.as_str());
+                        if repeated && item.version == newest {
+                            item.content = format!("{} ({}\u{2013}{})", heading_item.content, oldest, newest);
+                        }
+                        !repeated || item.version == newest
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Fold indented lines into the preceding item so nested bullets stay with their parent
+fn group_nested_items(items: &[String]) -> Vec<String> {
+    let mut grouped: Vec<String> = Vec::new();
+    
+    for item in items {
+        let is_nested = item.starts_with(' ') || item.starts_with('\t');
+        match grouped.last_mut() {
+            Some(parent) if is_nested => {
+                parent.push('\n');
+                parent.push_str(item.trim_end());
+            }
+            _ => grouped.push(item.trim_end().to_string()),
+        }
+    }
+    
+    grouped
+}
+
+fn generate_markdown(
+    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
+    options: &MarkdownOptions,
+) -> String {
+    debug!("Generating markdown output (version-based)");
+    let mut markdown = String::from("# Aggregated Release Notes\n\n");
+    
+    let section_names = sort_section_names(merged_sections, &options.section_order);
+    
+    let multiple_repos = has_multiple_repos(merged_sections.values().flatten().map(|item| item.repo.as_str()));
+    
+    if options.group_by == GroupBy::Release {
+        push_release_groups(&mut markdown, merged_sections, &section_names, multiple_repos, options);
+    } else {
+        for section_name in section_names {
+            debug!("Processing section: {}", section_name);
+            let items = &merged_sections[section_name];
+            
+            let level = if options.preserve_levels { section_level(items) } else { 2 };
+            markdown.push_str(&format!("{} {}\n\n", heading_marker(level), section_name));
+            
+            if matches!(options.group_by, GroupBy::Repo | GroupBy::Source) {
+                let mut repos: BTreeMap<&str, Vec<&ReleaseNoteItem>> = BTreeMap::new();
+                for item in items {
+                    let key = if options.group_by == GroupBy::Source { item.source() } else { &item.repo };
+                    repos.entry(key).or_default().push(item);
+                }
+                
+                for (repo, repo_items) in repos {
+                    markdown.push_str(&format!("{} {}\n\n", heading_marker(level + 1), repo));
+                    push_version_groups(&mut markdown, group_items_by_version(repo_items), &heading_marker(level + 2), false, options);
+                }
+            } else {
+                push_version_groups(&mut markdown, group_items_by_version(items), &heading_marker(level + 1), multiple_repos, options);
+            }
+        }
+    }
+    
+    if options.toc {
+        markdown = add_table_of_contents(&markdown);
+    }
+    
+    info!("Generated markdown output: {} bytes", markdown.len());
+    markdown
+}
+
+// Release-first layout: a `##` heading per release with its own sections nested beneath
+fn push_release_groups(
+    markdown: &mut String,
+    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
+    section_names: &[&String],
+    show_repo: bool,
+    options: &MarkdownOptions,
+) {
+    for release_items in group_items_by_version(merged_sections.values().flatten()) {
+        let first = release_items[0];
+        let label = source_label(first.source(), &first.version, show_repo);
+        debug!("Adding release: {} ({})", label, first.date);
+        markdown.push_str(&format!("## {}\n\n", version_heading(&label, first, options.relative_to, options.date_format.as_deref(), version_url(first, options))));
+        
+        for section_name in section_names {
+            let items: Vec<&ReleaseNoteItem> = merged_sections[*section_name]
+                .iter()
+                .filter(|item| item.repo == first.repo && item.version == first.version && item.date == first.date)
+                .collect();
+            if items.is_empty() {
+                continue;
+            }
+            
+            markdown.push_str(&format!("### {}\n\n", section_name));
+            push_items(markdown, &items, options);
+            markdown.push('\n');
+        }
+    }
+}
+
+// Shallowest source heading level among a section's items, `##` when none was recorded
+fn section_level(items: &[ReleaseNoteItem]) -> usize {
+    items.iter().filter_map(|item| item.level).min().unwrap_or(2)
+}
+
+// `#` repeated for a heading level, capped at Markdown's six levels
+fn heading_marker(level: usize) -> String {
+    "#".repeat(level.clamp(1, 6))
+}
+
+// Merge freshly generated notes into the document at `path`, skipping releases it already lists
+fn append_to_existing(
+    path: &Path,
+    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
+    options: &MarkdownOptions,
+) -> Result<String> {
+    // The table of contents is rebuilt once the documents are combined
+    let fresh = generate_markdown(merged_sections, &MarkdownOptions { toc: false, date_format: None, ..options.clone() });
+    
+    let combined = if path.exists() {
+        info!("Appending to existing document {:?}", path);
+        let existing = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read existing output file: {:?}", path))?;
+        merge_markdown_documents(&existing, &fresh, &options.section_order)
+    } else {
+        debug!("{:?} doesn't exist yet, nothing to append to", path);
+        fresh
+    };
+    
+    Ok(if options.toc { add_table_of_contents(&combined) } else { combined })
+}
+
+/// A `### <version> (<date>)` heading and the lines beneath it, as found in generated Markdown
+struct VersionBlock {
+    /// Heading text without the release name and date, which identifies the release
+    label: String,
+    date: Option<NaiveDate>,
+    lines: Vec<String>,
+}
+
+// Split a section's lines into the text before its first version heading and the version blocks
+fn split_version_blocks(lines: &[String]) -> (Vec<String>, Vec<VersionBlock>) {
+    let heading_regex = Regex::new(r"^###\s+(.+?)(?: \u{2014} .*?)?(?: \((\d{4}-\d{2}-\d{2})(?:, [^)]*)?\))?\s*$").unwrap();
+    let mut preamble = Vec::new();
+    let mut blocks: Vec<VersionBlock> = Vec::new();
+    
+    for line in lines {
+        if let Some(captures) = heading_regex.captures(line) {
+            blocks.push(VersionBlock {
+                label: captures[1].to_string(),
+                date: captures
+                    .get(2)
+                    .and_then(|date| NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d").ok()),
+                lines: vec![line.clone()],
+            });
+        } else if let Some(block) = blocks.last_mut() {
+            block.lines.push(line.clone());
+        } else {
+            preamble.push(line.clone());
+        }
+    }
+    
+    (preamble, blocks)
+}
+
+// Combine two documents in the default layout: releases already present in `existing` are kept as they are,
+// the others are added from `fresh`, and every section is re-sorted newest first
+fn merge_markdown_documents(existing: &str, fresh: &str, section_order: &SectionOrder) -> String {
+    let title = fresh.lines().next().unwrap_or("# Aggregated Release Notes").to_string();
+    let mut combined: HashMap<String, (Vec<String>, Vec<VersionBlock>)> = HashMap::new();
+    
+    for document in [existing, fresh] {
+        // Everything above the first section (title, table of contents) is regenerated
+        let body = match document.find("\n## ") {
+            Some(start) => &document[start + 1..],
+            None => "",
+        };
+        
+        for (section_name, lines) in helpers::extract_sections(body, DEFAULT_MAX_HEADING_DEPTH) {
+            let (preamble, blocks) = split_version_blocks(&lines);
+            let (kept_preamble, kept_blocks) = combined.entry(section_name.clone()).or_default();
+            if kept_preamble.iter().all(|line| line.trim().is_empty()) {
+                *kept_preamble = preamble;
+            }
+            for block in blocks {
+                if kept_blocks.iter().any(|kept| kept.label == block.label) {
+                    debug!("{} / {} is already in the document", section_name, block.label);
+                    continue;
+                }
+                kept_blocks.push(block);
+            }
+        }
+    }
+    
+    let mut markdown = format!("{}\n\n", title);
+    let section_names: Vec<String> = sort_section_names(&combined, section_order).into_iter().cloned().collect();
+    for section_name in section_names {
+        let (preamble, blocks) = combined.get_mut(&section_name).unwrap();
+        markdown.push_str(&format!("## {}\n\n", section_name));
+        
+        let preamble = preamble.join("\n");
+        if !preamble.trim().is_empty() {
+            markdown.push_str(&format!("{}\n\n", preamble.trim()));
+        }
+        
+        // Stable, so releases sharing a date keep their order
+        blocks.sort_by_key(|block| std::cmp::Reverse(block.date));
+        for block in blocks.iter() {
+            markdown.push_str(&format!("{}\n\n", block.lines.join("\n").trim_end()));
+        }
+    }
+    
+    markdown
+}
+
+// Write a heading per release followed by its items
+fn push_version_groups(
+    markdown: &mut String,
+    groups: Vec<Vec<&ReleaseNoteItem>>,
+    heading: &str,
+    show_repo: bool,
+    options: &MarkdownOptions,
+) {
+    for version_items in groups {
+        let first = version_items[0];
+        let label = source_label(first.source(), &first.version, show_repo);
+        debug!("Adding version: {} ({})", label, first.date);
+        markdown.push_str(&format!("{} {}\n\n", heading, version_heading(&label, first, options.relative_to, options.date_format.as_deref(), version_url(first, options))));
+        
+        push_items(markdown, &version_items, options);
+        
+        markdown.push('\n');
+    }
+}
+
+// Write one release's items in the requested order, cutting them off after the configured
+// maximum with a count of what was left out
+fn push_items(markdown: &mut String, items: &[&ReleaseNoteItem], options: &MarkdownOptions) {
+    let items = sort_items(items, options.sort_items);
+    let shown = options.max_items_per_section.map_or(items.len(), |max| max.min(items.len()));
+    for item in &items[..shown] {
+        markdown.push_str(&format!("{}\n", item.content));
+    }
+    
+    if shown < items.len() {
+        debug!("Truncated {} items", items.len() - shown);
+        markdown.push_str(&format!("- …and {} more\n", items.len() - shown));
+    }
+}
+
+// Stable sort, so items that compare equal keep their parse order
+fn sort_items<'a>(items: &[&'a ReleaseNoteItem], order: ItemOrder) -> Vec<&'a ReleaseNoteItem> {
+    let mut sorted = items.to_vec();
+    match order {
+        ItemOrder::AsIs => {}
+        ItemOrder::Alphabetical => sorted.sort_by_cached_key(|item| item_text(&item.content).to_lowercase()),
+        ItemOrder::Length => sorted.sort_by_key(|item| item_text(&item.content).chars().count()),
+    }
+    sorted
+}
+
+// Item content without its leading `-`, `*` or `+` bullet marker
+fn item_text(content: &str) -> &str {
+    let trimmed = content.trim_start();
+    trimmed
+        .strip_prefix(['-', '*', '+'])
+        .map_or(trimmed, str::trim_start)
+}
+
+// New function to generate markdown with merged headings
+fn generate_markdown_merged_headings(
+    merged_sections: &HashMap<String, Vec<MergedHeadingItem>>,
+    options: &MarkdownOptions,
+) -> String {
+    debug!("Generating markdown output (heading-based)");
+    let mut markdown = String::from("# Aggregated Release Notes (Merged by Heading)\n\n");
+    
+    let section_names = sort_section_names(merged_sections, &options.section_order);
+    let multiple_repos = has_multiple_repos(merged_sections.values().flatten().map(|item| item.repo.as_str()));
+    
+    for section_name in section_names {
+        debug!("Processing section: {}", section_name);
+        markdown.push_str(&format!("## {}\n\n", section_name));
+        
+        let items = &merged_sections[section_name];
+        
+        if matches!(options.group_by, GroupBy::Repo | GroupBy::Source) {
+            let mut repos: BTreeMap<&str, Vec<&MergedHeadingItem>> = BTreeMap::new();
+            for item in items {
+                let key = if options.group_by == GroupBy::Source { item.source() } else { &item.repo };
+                repos.entry(key).or_default().push(item);
+            }
+            
+            for (repo, repo_items) in repos {
+                markdown.push_str(&format!("### {}\n\n", repo));
+                push_merged_items(&mut markdown, &repo_items, false, options.hide_source_annotations);
+            }
+        } else {
+            let items: Vec<&MergedHeadingItem> = items.iter().collect();
+            push_merged_items(&mut markdown, &items, multiple_repos, options.hide_source_annotations);
+        }
+        
+        markdown.push('\n');
+    }
+    
+    if options.toc {
+        markdown = add_table_of_contents(&markdown);
+    }
+    
+    info!("Generated markdown output: {} bytes", markdown.len());
+    markdown
+}
+
+// Write merged items, each followed by the versions it appeared in unless those are hidden
+fn push_merged_items(markdown: &mut String, items: &[&MergedHeadingItem], show_repo: bool, hide_sources: bool) {
+    for item in items {
+        // Add the content
+        markdown.push_str(&format!("{}\n", item.content));
+        
+        // Without annotations the items read as one continuous list
+        if hide_sources {
+            continue;
+        }
+        
+        // Add source versions if there are multiple
+        if item.sources.len() > 1 {
+            let sorted_sources = {
+                let mut sources = item.sources.clone();
+                sources.sort();
+                sources
+            };
+            
+            let sources_list = sorted_sources
+                .iter()
+                .map(|source| source_label(item.source(), source, show_repo))
+                .collect::<Vec<_>>()
+                .join(", ");
+            debug!("Item appears in multiple versions: {}", sources_list);
+            markdown.push_str(&format!("*(Present in versions: {})*\n\n", sources_list));
+        } else if !item.sources.is_empty() {
+            let source = source_label(item.source(), &item.sources[0], show_repo);
+            debug!("Item appears in single version: {}", source);
+            markdown.push_str(&format!("*(From version: {})*\n\n", source));
+        } else {
+            markdown.push('\n');
+        }
+    }
+}
+
+// Insert a list of links to every `##` section right after the document title
+fn add_table_of_contents(markdown: &str) -> String {
+    let heading_regex = Regex::new(r"^(#{1,6})\s+(.+)$").unwrap();
+    let mut slugger = GitHubSlugger::default();
+    let mut toc = String::new();
+    
+    // Slug every heading in document order so duplicate suffixes match GitHub's
+    for line in markdown.lines() {
+        if let Some(captures) = heading_regex.captures(line) {
+            let text = captures.get(2).unwrap().as_str().trim();
+            let slug = slugger.slug(text);
+            if captures.get(1).unwrap().as_str().len() == 2 {
+                toc.push_str(&format!("- [{}](#{})\n", text, slug));
+            }
+        }
+    }
+    
+    if toc.is_empty() {
+        return markdown.to_string();
+    }
+    
+    debug!("Adding table of contents");
+    match markdown.split_once("\n\n") {
+        Some((title, rest)) => format!("{}\n\n{}\n{}", title, toc, rest),
+        None => format!("{}\n{}", toc, markdown),
+    }
+}
+
+// Generates heading anchors the way GitHub does, including `-1`, `-2` suffixes for duplicates
+#[derive(Default)]
+struct GitHubSlugger {
+    occurrences: HashMap<String, usize>,
+}
+
+impl GitHubSlugger {
+    fn slug(&mut self, heading: &str) -> String {
+        let mut original = String::new();
+        for c in heading.to_lowercase().chars() {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                original.push(c);
+            } else if c == ' ' {
+                original.push('-');
+            }
+        }
+        
+        let mut slug = original.clone();
+        while self.occurrences.contains_key(&slug) {
+            let count = self.occurrences.get_mut(&original).unwrap();
+            *count += 1;
+            slug = format!("{}-{}", original, count);
+        }
+        self.occurrences.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+// Serialize merged sections as pretty-printed JSON, with sections in a stable (sorted) order
+fn generate_json<T: Serialize>(merged_sections: &HashMap<String, Vec<T>>) -> Result<String> {
+    debug!("Generating JSON output");
+    let ordered: BTreeMap<&String, &Vec<T>> = merged_sections.iter().collect();
+    let json = serde_json::to_string_pretty(&ordered).context("Failed to serialize release notes to JSON")?;
+
+    info!("Generated JSON output: {} bytes", json.len());
+    Ok(json)
+}
+
+// Serialize merged sections as YAML, using the JSON schema and section order
+fn generate_yaml<T: Serialize>(merged_sections: &HashMap<String, Vec<T>>) -> Result<String> {
+    debug!("Generating YAML output");
+    let ordered: BTreeMap<&String, &Vec<T>> = merged_sections.iter().collect();
+    let yaml = serde_yaml::to_string(&ordered).context("Failed to serialize release notes to YAML")?;
+
+    info!("Generated YAML output: {} bytes", yaml.len());
+    Ok(yaml)
+}
+
+// Serialize merged sections as TOML, each section becoming an array of tables
+fn generate_toml<T: Serialize>(merged_sections: &HashMap<String, Vec<T>>) -> Result<String> {
+    debug!("Generating TOML output");
+    let ordered: BTreeMap<&String, &Vec<T>> = merged_sections.iter().collect();
+    let toml = toml::to_string_pretty(&ordered).context("Failed to serialize release notes to TOML")?;
+
+    info!("Generated TOML output: {} bytes", toml.len());
+    Ok(toml)
+}
+
+// Flatten merged sections into `section,version,date,content` rows, sections in display order
+fn generate_csv(merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>, section_order: &SectionOrder) -> Result<String> {
+    debug!("Generating CSV output");
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["section", "version", "date", "content"])?;
+    
+    for section_name in sort_section_names(merged_sections, section_order) {
+        for item in &merged_sections[section_name] {
+            let date = item.date.format("%Y-%m-%d").to_string();
+            writer.write_record([section_name.as_str(), &item.version, &date, &item.content])?;
+        }
+    }
+    
+    let csv = String::from_utf8(writer.into_inner().context("Failed to write CSV output")?)?;
+    info!("Generated CSV output: {} bytes", csv.len());
+    Ok(csv)
+}
+
+// Generate a standalone HTML document, one anchored <h2> per section and <h3> per version
+fn generate_html(merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>, section_order: &SectionOrder, date_format: Option<&str>) -> String {
+    debug!("Generating HTML output (version-based)");
+    let section_names = sort_section_names(merged_sections, section_order);
+    let multiple_repos = has_multiple_repos(merged_sections.values().flatten().map(|item| item.repo.as_str()));
+    let mut anchors = HtmlAnchors::default();
+    let mut nav = Vec::new();
+    let mut body = String::new();
+    
+    for section_name in section_names {
+        debug!("Processing section: {}", section_name);
+        let section_id = anchors.unique(section_name);
+        nav.push((section_id.clone(), section_name.clone()));
+        body.push_str(&format!("<h2 id=\"{}\">{}</h2>\n", section_id, escape_html(section_name)));
+        
+        for version_items in group_items_by_version(&merged_sections[section_name]) {
+            let first = version_items[0];
+            let label = source_label(first.source(), &first.version, multiple_repos);
+            let heading = version_heading(&label, first, None, date_format, None);
+            let version_id = anchors.unique(&format!("{} {}", section_name, label));
+            body.push_str(&format!("<h3 id=\"{}\">{}</h3>\n", version_id, escape_html(&heading)));
+            
+            let lines: Vec<&str> = version_items.iter().map(|item| item.content.as_str()).collect();
+            body.push_str(&render_html_lines(&lines));
+        }
+    }
+    
+    let html = html_document("Aggregated Release Notes", &nav, &body);
+    info!("Generated HTML output: {} bytes", html.len());
+    html
+}
+
+// Generate a standalone HTML document for notes merged by heading
+fn generate_html_merged_headings(merged_sections: &HashMap<String, Vec<MergedHeadingItem>>, section_order: &SectionOrder) -> String {
+    debug!("Generating HTML output (heading-based)");
+    let section_names = sort_section_names(merged_sections, section_order);
+    let multiple_repos = has_multiple_repos(merged_sections.values().flatten().map(|item| item.repo.as_str()));
+    let mut anchors = HtmlAnchors::default();
+    let mut nav = Vec::new();
+    let mut body = String::new();
+    
+    for section_name in section_names {
+        debug!("Processing section: {}", section_name);
+        let section_id = anchors.unique(section_name);
+        nav.push((section_id.clone(), section_name.clone()));
+        body.push_str(&format!("<h2 id=\"{}\">{}</h2>\n<ul>\n", section_id, escape_html(section_name)));
+        
+        for item in &merged_sections[section_name] {
+            let mut sources: Vec<String> = item
+                .sources
+                .iter()
+                .map(|source| source_label(item.source(), source, multiple_repos))
+                .collect();
+            sources.sort();
+            body.push_str(&format!(
+                "<li>{} <em>({})</em></li>\n",
+                render_html_inline(strip_list_marker(&item.content).unwrap_or(&item.content)),
+                escape_html(&sources.join(", "))
+            ));
+        }
+        
+        body.push_str("</ul>\n");
+    }
+    
+    let html = html_document("Aggregated Release Notes (Merged by Heading)", &nav, &body);
+    info!("Generated HTML output: {} bytes", html.len());
+    html
+}
+
+// Wrap rendered sections in a full HTML page with a <nav> table of contents
+fn html_document(title: &str, nav: &[(String, String)], body: &str) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n</head>\n<body>\n", escape_html(title)));
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+    
+    html.push_str("<nav>\n<ul>\n");
+    for (id, name) in nav {
+        html.push_str(&format!("<li><a href=\"#{}\">{}</a></li>\n", id, escape_html(name)));
+    }
+    html.push_str("</ul>\n</nav>\n");
+    
+    html.push_str(body);
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+// Render Markdown lines, grouping consecutive list items into a single <ul>
+fn render_html_lines(lines: &[&str]) -> String {
+    let mut html = String::new();
+    let mut in_list = false;
+    
+    for line in lines {
+        match strip_list_marker(line) {
+            Some(item) => {
+                if !in_list {
+                    html.push_str("<ul>\n");
+                    in_list = true;
+                }
+                html.push_str(&format!("<li>{}</li>\n", render_html_inline(item)));
+            }
+            None => {
+                if in_list {
+                    html.push_str("</ul>\n");
+                    in_list = false;
+                }
+                html.push_str(&format!("<p>{}</p>\n", render_html_inline(line.trim())));
+            }
+        }
+    }
+    
+    if in_list {
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+// Return the text of a Markdown list item without its bullet marker
+fn strip_list_marker(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+        .map(str::trim)
+}
+
+static BOLD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*\*(.+?)\*\*").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)").unwrap());
+
+// Convert inline `code`, **bold** and [links](url) to HTML, leaving code spans untouched
+fn render_html_inline(text: &str) -> String {
+    let mut html = String::new();
+    
+    // Odd segments are inside backticks
+    for (i, segment) in text.split('`').enumerate() {
+        let escaped = escape_html(segment);
+        if i % 2 == 1 {
+            html.push_str(&format!("<code>{}</code>", escaped));
+        } else {
+            let bolded = BOLD.replace_all(&escaped, "<strong>$1</strong>");
+            let linked = LINK.replace_all(&bolded, |captures: &regex::Captures| {
+                if is_safe_link(&captures[2]) {
+                    format!("<a href=\"{}\">{}</a>", &captures[2], &captures[1])
+                } else {
+                    captures[1].to_string()
+                }
+            });
+            html.push_str(&linked);
+        }
+    }
+    html
+}
+
+// Release bodies are untrusted, so only web, mail and relative links become clickable; `javascript:` and
+// other schemes are dropped
+fn is_safe_link(url: &str) -> bool {
+    let scheme_end = url.find(':');
+    let path_start = url.find(['/', '?', '#']);
+    match scheme_end {
+        // A colon after the path starts, as in `docs/a:b`, doesn't end a scheme
+        Some(end) if path_start.is_none_or(|start| end < start) => {
+            matches!(url[..end].to_ascii_lowercase().as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Hands out unique id attributes derived from heading text
+#[derive(Default)]
+struct HtmlAnchors {
+    used: HashSet<String>,
+}
+
+impl HtmlAnchors {
+    fn unique(&mut self, text: &str) -> String {
+        let mut base = String::new();
+        for c in text.to_lowercase().chars() {
+            if c.is_alphanumeric() {
+                base.push(c);
+            } else if !base.is_empty() && !base.ends_with('-') {
+                base.push('-');
+            }
+        }
+        let base = base.trim_end_matches('-').to_string();
+        let base = if base.is_empty() { "section".to_string() } else { base };
+        
+        let mut id = base.clone();
+        let mut suffix = 1;
+        while !self.used.insert(id.clone()) {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+}
+
+/// Keep a Changelog categories, in the order they are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum ChangelogCategory {
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+    Fixed,
+    Security,
+    /// Catch-all for sections with no mapping
+    Other,
+}
+
+impl ChangelogCategory {
+    fn title(self) -> &'static str {
+        match self {
+            ChangelogCategory::Added => "Added",
+            ChangelogCategory::Changed => "Changed",
+            ChangelogCategory::Deprecated => "Deprecated",
+            ChangelogCategory::Removed => "Removed",
+            ChangelogCategory::Fixed => "Fixed",
+            ChangelogCategory::Security => "Security",
+            ChangelogCategory::Other => "Other",
+        }
+    }
+    
+    fn from_name(name: &str) -> Option<Self> {
+        match normalize_section_name(name).as_str() {
+            "added" => Some(ChangelogCategory::Added),
+            "changed" => Some(ChangelogCategory::Changed),
+            "deprecated" => Some(ChangelogCategory::Deprecated),
+            "removed" => Some(ChangelogCategory::Removed),
+            "fixed" => Some(ChangelogCategory::Fixed),
+            "security" => Some(ChangelogCategory::Security),
+            "other" => Some(ChangelogCategory::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Maps normalized release-note section names onto Keep a Changelog categories
+#[derive(Debug, Clone)]
+struct ChangelogMapping {
+    categories: HashMap<String, ChangelogCategory>,
+}
+
+impl Default for ChangelogMapping {
+    fn default() -> Self {
+        let defaults: [(ChangelogCategory, &[&str]); 6] = [
+            (ChangelogCategory::Added, &["added", "features", "feature", "new features", "new"]),
+            (ChangelogCategory::Changed, &["changed", "changes", "improvements", "enhancements", "performance", "breaking changes", "what's changed"]),
+            (ChangelogCategory::Deprecated, &["deprecated", "deprecations"]),
+            (ChangelogCategory::Removed, &["removed", "removals"]),
+            (ChangelogCategory::Fixed, &["fixed", "fixes", "bug fixes", "bugfixes", "bug fix"]),
+            (ChangelogCategory::Security, &["security", "security fixes"]),
+        ];
+        
+        let mut categories = HashMap::new();
+        for (category, aliases) in defaults {
+            for alias in aliases {
+                categories.insert(alias.to_string(), category);
+            }
+        }
+        ChangelogMapping { categories }
+    }
+}
+
+impl ChangelogMapping {
+    fn category_for(&self, section_name: &str) -> ChangelogCategory {
+        self.categories
+            .get(&normalize_section_name(section_name))
+            .copied()
+            .unwrap_or(ChangelogCategory::Other)
+    }
+    
+    // Add or replace mappings from a comma-separated list of `Section=Category` pairs
+    fn apply_overrides(&mut self, overrides: &str) -> Result<()> {
+        for pair in overrides.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (section, category) = pair
+                .split_once('=')
+                .with_context(|| format!("Invalid changelog mapping '{}', expected Section=Category", pair))?;
+            let category = ChangelogCategory::from_name(category).with_context(|| {
+                format!(
+                    "Unknown changelog category '{}', expected one of Added, Changed, Deprecated, Removed, Fixed, Security, Other",
+                    category.trim()
+                )
+            })?;
+            self.categories.insert(normalize_section_name(section), category);
+        }
+        Ok(())
+    }
+}
+
+// Generate a Keep a Changelog document with one `## [version] - date` block per release
+fn generate_keep_a_changelog(
+    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
+    mapping: &ChangelogMapping,
+    uncategorized: &str,
+) -> String {
+    debug!("Generating Keep a Changelog output");
+    let mut releases: HashMap<(String, NaiveDate), BTreeMap<ChangelogCategory, Vec<&str>>> = HashMap::new();
+    let multiple_repos = has_multiple_repos(merged_sections.values().flatten().map(|item| item.repo.as_str()));
+    
+    for section_name in sort_section_names(merged_sections, &SectionOrder::new(Vec::new(), uncategorized)) {
+        let category = mapping.category_for(section_name);
+        for item in &merged_sections[section_name] {
+            let version = extract_version(&item.version);
+            releases
+                .entry((source_label(item.source(), &version, multiple_repos), item.date))
+                .or_default()
+                .entry(category)
+                .or_default()
+                .push(&item.content);
+        }
+    }
+    
+    // Newest release first
+    let mut release_entries: Vec<_> = releases.into_iter().collect();
+    release_entries.sort_by(|a, b| b.0.1.cmp(&a.0.1).then_with(|| compare_semver(&b.0.0, &a.0.0)));
+    
+    let mut markdown = String::from("# Changelog\n\nAll notable changes to this project are documented in this file.\n\n");
+    
+    for ((version, date), categories) in release_entries {
+        markdown.push_str(&format!(
+            "## [{}] - {}\n\n",
+            version,
+            date.format("%Y-%m-%d")
+        ));
+        
+        for (category, items) in categories {
+            markdown.push_str(&format!("### {}\n\n", category.title()));
+            for item in items {
+                markdown.push_str(&format!("{}\n", item));
+            }
+            markdown.push('\n');
+        }
+    }
+    
+    info!("Generated Keep a Changelog output: {} bytes", markdown.len());
+    markdown
+}
+
+// ANSI escape sequences used by --format terminal
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_CYAN: &str = "\x1b[36m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+// Restyle generated Markdown for a terminal: heading markers are dropped, headings are bold,
+// version tags colored and dates and source notes dimmed. Without color only the markup goes
+fn render_terminal(markdown: &str, color: bool) -> String {
+    let heading_regex = Regex::new(r"^#+\s+(.+?)\s*$").unwrap();
+    let version_regex = Regex::new(r"^(.+?)((?: \u{2014} .*?)?) (\(\d{4}-\d{2}-\d{2}(?:, [^)]*)?\))$").unwrap();
+    let source_regex = Regex::new(r"^\*(\((?:Present in versions|From version): .*\))\*$").unwrap();
+    let style = |codes: &str, text: &str| if color { format!("{}{}{}", codes, text, ANSI_RESET) } else { text.to_string() };
+    
+    let mut output = String::new();
+    for line in markdown.lines() {
+        if let Some(captures) = heading_regex.captures(line) {
+            let heading = &captures[1];
+            match version_regex.captures(heading) {
+                Some(version) => output.push_str(&format!(
+                    "{}{} {}",
+                    style(&format!("{}{}", ANSI_BOLD, ANSI_CYAN), &version[1]),
+                    &version[2],
+                    style(ANSI_DIM, &version[3])
+                )),
+                None => output.push_str(&style(ANSI_BOLD, heading)),
+            }
+        } else if let Some(captures) = source_regex.captures(line) {
+            output.push_str(&style(ANSI_DIM, &captures[1]));
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// Atom 1.0 feed with an entry per release, built from the unmerged releases
+fn generate_atom_feed(releases: &[Release]) -> String {
+    debug!("Generating Atom feed for {} releases", releases.len());
+    
+    let mut repos: Vec<&str> = releases
+        .iter()
+        .map(|release| release.repo.as_str())
+        .filter(|repo| !repo.is_empty())
+        .collect();
+    repos.sort();
+    repos.dedup();
+    let feed_name = if repos.is_empty() { "releases".to_string() } else { repos.join(", ") };
+    
+    // The feed was last updated when its newest release was published
+    let updated = releases
+        .iter()
+        .filter_map(|release| chrono::DateTime::parse_from_rfc3339(&release.published_at).ok())
+        .max()
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+    
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    feed.push_str("  <title>Aggregated Release Notes</title>\n");
+    feed.push_str(&format!("  <id>urn:ghnotes:{}</id>\n", escape_html(&repos.join(","))));
+    feed.push_str(&format!("  <updated>{}</updated>\n", updated));
+    feed.push_str(&format!("  <author><name>{}</name></author>\n", escape_html(&feed_name)));
+    
+    for release in releases {
+        let title = release
+            .name
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(&release.tag_name);
+        let date = release.published_at.get(..10).unwrap_or(&release.published_at);
+        let content = render_release_html(release.body.as_deref().unwrap_or(""));
+        
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!("    <title>{}</title>\n", escape_html(title)));
+        // The release page is a stable, unique IRI; without one, an RFC 4151 tag URI needs a real domain as its authority
+        let id = match &release.html_url {
+            Some(url) => url.clone(),
+            None => format!("tag:github.com,{}:{}/{}", date, release.repo, release.tag_name),
+        };
+        feed.push_str(&format!("    <id>{}</id>\n", escape_html(&id)));
+        feed.push_str(&format!("    <updated>{}</updated>\n", release.published_at));
+        feed.push_str(&format!("    <content type=\"html\">{}</content>\n", escape_html(&content)));
+        feed.push_str("  </entry>\n");
+    }
+    
+    feed.push_str("</feed>\n");
+    info!("Generated Atom feed: {} bytes", feed.len());
+    feed
+}
+
+// Render a whole release body as HTML, keeping its headings in place
+fn render_release_html(body: &str) -> String {
+    let heading_regex = Regex::new(r"^(#{1,6})\s+(.+)$").unwrap();
+    let mut html = String::new();
+    let mut block: Vec<&str> = Vec::new();
+    
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        if let Some(captures) = heading_regex.captures(line) {
+            html.push_str(&render_html_lines(&block));
+            block.clear();
+            let level = captures.get(1).unwrap().as_str().len();
+            html.push_str(&format!(
+                "<h{}>{}</h{}>\n",
+                level,
+                render_html_inline(captures.get(2).unwrap().as_str().trim()),
+                level
+            ));
+        } else {
+            block.push(line);
+        }
+    }
+    
+    html.push_str(&render_html_lines(&block));
+    html
+}
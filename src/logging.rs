@@ -0,0 +1,70 @@
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// How log lines are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// env_logger's human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line with timestamp, level, target, message and repo
+    Json,
+}
+
+// Repository currently being processed, attached to JSON log lines
+static CURRENT_REPO: Mutex<Option<String>> = Mutex::new(None);
+
+/// Tag subsequent log lines with the `owner/repo` being processed
+pub fn set_current_repo(repo: Option<&str>) {
+    if let Ok(mut current) = CURRENT_REPO.lock() {
+        *current = repo.map(str::to_string);
+    }
+}
+
+/// Filter used when `RUST_LOG` is unset: `debug` with `--verbose`, `error` with `--quiet`, `info` otherwise
+pub fn default_filter(verbose: bool, quiet: bool) -> &'static str {
+    match (verbose, quiet) {
+        (true, _) => "debug",
+        (false, true) => "error",
+        (false, false) => "info",
+    }
+}
+
+/// Install the global logger with the level picked by [`default_filter`]
+pub fn init(verbose: bool, quiet: bool, format: LogFormat) {
+    let mut builder = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(default_filter(verbose, quiet)),
+    );
+
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let repo = CURRENT_REPO.lock().ok().and_then(|current| current.clone());
+            let line = json_log_line(
+                &Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                record.level(),
+                record.target(),
+                &record.args().to_string(),
+                repo.as_deref(),
+            );
+            writeln!(buf, "{}", line)
+        });
+    }
+
+    builder.init();
+}
+
+/// Render one structured log line; `repo` is left out when no repository is being processed
+pub fn json_log_line(timestamp: &str, level: log::Level, target: &str, message: &str, repo: Option<&str>) -> String {
+    let mut line = serde_json::json!({
+        "timestamp": timestamp,
+        "level": level.as_str(),
+        "target": target,
+        "message": message,
+    });
+    if let Some(repo) = repo {
+        line["repo"] = serde_json::Value::from(repo);
+    }
+    line.to_string()
+}
@@ -1,16 +1,15 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use chrono::NaiveDate;
-use clap::Parser;
-use regex::Regex;
-use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, Parser};
+use std::ffi::OsString;
 use std::io::Write;
-use std::path::PathBuf;
-// Added for logging
-use log::{debug, info, warn, error};
-use env_logger;
+use std::process::ExitCode;
+use std::path::{Path, PathBuf};
+use log::{debug, info};
+
+use ghnotes::logging::{self, LogFormat};
+use ghnotes::{aggregate, check_rate_limit, ApiError, NoReleases, TagNotFound, gzip, parse_date_format, parse_window, publish_gist, resolve_token, write_output_atomic, AggregatorConfig, ColorChoice, GroupBy, ItemOrder, OutputFormat, Provider, ReleaseOrder, SplitBy, DEFAULT_MAX_HEADING_DEPTH, MAX_MISSING_TAG_RETRIES, DEFAULT_UNCATEGORIZED_LABEL, SPLIT_INDEX_FILE};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -20,13 +19,17 @@ use env_logger;
     author
 )]
 struct Cli {
-    /// GitHub repository owner (user or organization)
-    #[arg(short, long)]
-    owner: String,
+    /// GitHub repository owner (user or organization), given once or once per --repo
+    #[arg(short, long, required_unless_present_any = ["check_rate_limit", "source"])]
+    owner: Vec<String>,
 
-    /// GitHub repository name
-    #[arg(short, long)]
-    repo: String,
+    /// GitHub repository name (repeat to aggregate several repositories)
+    #[arg(short, long, required_unless_present_any = ["check_rate_limit", "source"])]
+    repo: Vec<String>,
+
+    /// Extra repositories as owner/repo or owner/repo:Label, with their notes attributed to the label
+    #[arg(long, value_name = "OWNER/REPO[:LABEL]")]
+    source: Vec<String>,
 
     /// Start tag (older version)
     #[arg(short, long)]
@@ -36,585 +39,651 @@ struct Cli {
     #[arg(short, long)]
     end_tag: Option<String>,
 
-    /// GitHub personal access token (for higher rate limits)
+    /// GitHub personal access token (for higher rate limits), falls back to GITHUB_TOKEN
     #[arg(short, long)]
     token: Option<String>,
 
-    /// Output markdown file path
+    /// Output markdown file path ("-" for stdout)
     #[arg(long, default_value = "aggregated_release_notes.md")]
     output: PathBuf,
 
+    /// Print the aggregated notes to stdout instead of writing a file
+    #[arg(long, default_value = "false")]
+    stdout: bool,
+
+    /// Share the output as a secret GitHub Gist and print its URL instead of writing a file (needs a token with the gist scope)
+    #[arg(long, conflicts_with_all = ["stdout", "append", "output_dir"])]
+    gist: bool,
+
+    /// Compress the output file with gzip, adding .gz to --output unless it already ends with it
+    #[arg(long, conflicts_with_all = ["stdout", "append", "output_dir", "gist"])]
+    gzip: bool,
+
+    /// Hosting service to fetch releases from
+    #[arg(long, value_enum, default_value_t = Provider::Github)]
+    provider: Provider,
+
+    /// Base URL of the provider API (e.g. GitHub Enterprise, a self-hosted GitLab or a Gitea instance)
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// Maximum retries for transient API failures (5xx gateway errors, connection resets, rate limits)
+    #[arg(long, default_value_t = 3)]
+    max_retries: u32,
+
+    /// Fetch the releases again, up to N times with a growing delay, while a tag named by --versions, --start-tag or --end-tag is missing (for just-published releases)
+    #[arg(long, value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=MAX_MISSING_TAG_RETRIES as i64))]
+    retry_missing_tags: u32,
+
+    /// Cache GitHub release lists here and revalidate them with ETags on later runs
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Read releases from a JSON file (GitHub API format) instead of calling the API
+    #[arg(long)]
+    from_file: Option<PathBuf>,
+
+    /// Aggregate annotated tag messages instead of GitHub Releases, for repositories that only push tags
+    #[arg(long, conflicts_with = "from_file")]
+    from_tags: bool,
+
+    /// Have GitHub generate the notes for --start-tag..--end-tag from merged pull requests, for repositories without per-release notes
+    #[arg(long, requires = "end_tag", conflicts_with_all = ["from_file", "from_tags", "versions", "versions_file", "latest"])]
+    generate: bool,
+
+    /// Fetch releases through the GitHub GraphQL API, 100 at a time with full bodies, instead of the REST list; needs a token
+    #[arg(long, conflicts_with_all = ["from_file", "from_tags", "generate"])]
+    use_graphql: bool,
+
+    /// Refetch release bodies that the list endpoint omitted or truncated
+    #[arg(long, default_value = "false")]
+    fetch_full_bodies: bool,
+
+    /// Maximum number of concurrent requests when fetching full bodies
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+
     /// Include pre-releases
     #[arg(long, default_value = "false")]
     include_prereleases: bool,
 
+    /// Fetch pre-releases so range tags can name them, but leave them out of the output
+    #[arg(long)]
+    exclude_prereleases_from_output: bool,
+
+    /// Include draft releases, dated by when they were created
+    #[arg(long)]
+    include_drafts: bool,
+
     /// Arbitrary versions to merge (comma-separated list of tag names)
     #[arg(short = 'v', long)]
     versions: Option<String>,
 
+    /// Read tag names to merge from a file, one per line (blank lines and # comments are ignored), in addition to --versions
+    #[arg(long, value_name = "PATH")]
+    versions_file: Option<PathBuf>,
+
+    /// Only consider releases whose tag matches this shell-style glob (e.g. "backend-v*")
+    #[arg(long, value_name = "GLOB")]
+    tag_pattern: Option<String>,
+
+    /// Only consider releases whose tag starts with this prefix (e.g. "frontend-"), showing their versions without it
+    #[arg(long, value_name = "PREFIX")]
+    tag_prefix: Option<String>,
+
+    /// Only aggregate releases whose semver is at least this version (non-semver tags are skipped)
+    #[arg(long, value_name = "SEMVER")]
+    min_version: Option<String>,
+
+    /// Exit with an error instead of a warning when no releases match the selection (for CI checks)
+    #[arg(long)]
+    fail_on_empty: bool,
+
+    /// Check every release parses cleanly and exit with an error listing the ones that don't, without writing any output
+    #[arg(long, conflicts_with_all = ["stdout", "gist", "append"])]
+    validate: bool,
+
+    /// Deepest heading level that starts a section; deeper headings are kept as content (and reported by --validate)
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        default_value_t = DEFAULT_MAX_HEADING_DEPTH,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=6)
+    )]
+    max_heading_depth: usize,
+
+    /// Only aggregate the N most recent releases (by publish date)
+    #[arg(long, value_name = "N", conflicts_with_all = ["start_tag", "end_tag", "versions", "versions_file"])]
+    latest: Option<usize>,
+
+    /// List a bullet repeated across consecutive releases once, annotated with the version range
+    #[arg(long)]
+    collapse_repeated: bool,
+
+    /// Treat bullets differing only in case, trailing punctuation or version numbers as duplicates (with --merge-headings)
+    #[arg(long)]
+    fuzzy_dedup: bool,
+
     /// Merge by heading (combine content under common headings instead of keeping versions separate)
     #[arg(short = 'm', long, default_value = "false")]
     merge_headings: bool,
-    
-    /// Enable verbose logging
+
+    /// Emit a one-line-per-release digest (name or first line of the notes) instead of sections
+    #[arg(long, conflicts_with_all = ["merge_headings", "append", "template", "split_by"])]
+    summarize: bool,
+
+    /// Leave out the version annotations under each item merged by heading
+    #[arg(long)]
+    no_source_annotations: bool,
+
+    /// Ordering used to resolve --start-tag/--end-tag ranges
+    #[arg(long, value_enum, default_value_t = ReleaseOrder::Date)]
+    order_by: ReleaseOrder,
+
+    /// Fail when --start-tag is newer than --end-tag instead of swapping them
+    #[arg(long)]
+    strict_range: bool,
+
+    /// Leave the start tag itself out of the range, for everything released after it
+    #[arg(long, requires = "start_tag")]
+    exclusive_start: bool,
+
+    /// Leave the end tag itself out of the range, for everything released up to it
+    #[arg(long, requires = "end_tag")]
+    exclusive_end: bool,
+
+    /// Only include releases published on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<NaiveDate>,
+
+    /// Only include releases published on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    until: Option<NaiveDate>,
+
+    /// Only include releases from the last N days or weeks (e.g. 30d, 2w), counted back from today
+    #[arg(long, value_name = "DURATION", value_parser = parse_window, conflicts_with = "since")]
+    window: Option<chrono::Duration>,
+
+    /// Comma-separated section names to leave out (case-insensitive)
+    #[arg(long)]
+    exclude_sections: Option<String>,
+
+    /// Comma-separated section names to keep, dropping all others (--exclude-sections wins on overlap)
+    #[arg(long)]
+    include_sections: Option<String>,
+
+    /// TOML or JSON file mapping canonical section names to lists of aliases, on top of the built-in synonyms
+    #[arg(long, value_name = "PATH")]
+    section_aliases: Option<PathBuf>,
+
+    /// Turn `#123` and `owner/repo#123` issue/PR references into GitHub links
+    #[arg(long)]
+    link_refs: bool,
+
+    /// Group heading-less notes into sections by their conventional-commit prefix (feat:, fix:, ...)
+    #[arg(long)]
+    parse_conventional: bool,
+
+    /// File the notes of a release without any headings under a section named after the release (its name, or else its tag) instead of Uncategorized
+    #[arg(long, conflicts_with = "parse_conventional")]
+    name_as_section: bool,
+
+    /// Join an indented line that continues a wrapped bullet onto that bullet, so it stays one item
+    #[arg(long)]
+    join_wrapped_bullets: bool,
+
+    /// Replace GitHub emoji shortcodes such as :rocket: with the emoji itself, in section names and notes
+    #[arg(long)]
+    render_emoji: bool,
+
+    /// Leave releases without any notes out of version-separated output
+    #[arg(long)]
+    skip_empty: bool,
+
+    /// Collect the "Full Changelog" compare links into a "Changelog Links" section instead of dropping them
+    #[arg(long)]
+    keep_compare_links: bool,
+
+    /// List each release's downloadable assets with their size in an Assets section
+    #[arg(long)]
+    list_assets: bool,
+
+    /// Comma-separated section names to list first, in order; the rest follow alphabetically
+    #[arg(long, value_name = "SECTIONS")]
+    section_order: Option<String>,
+
+    /// Name of the section collecting notes that appear before any heading; it is always listed last
+    #[arg(long, value_name = "NAME", default_value = DEFAULT_UNCATEGORIZED_LABEL)]
+    uncategorized_label: String,
+
+    /// Keep at most N items per section for each release, noting how many were left out (Markdown output)
+    #[arg(long, value_name = "N")]
+    max_items_per_section: Option<usize>,
+
+    /// Order of the items listed under each version: as-is, alphabetical or length (Markdown output)
+    #[arg(long, value_enum, default_value_t = ItemOrder::AsIs)]
+    sort_items: ItemOrder,
+
+    /// Keep each section at the heading level it had in the release notes (Markdown output)
+    #[arg(long)]
+    preserve_levels: bool,
+
+    /// Annotate version headings with how long ago each release was published (Markdown output)
+    #[arg(long)]
+    relative_dates: bool,
+
+    /// strftime format for the dates in version headings (default: %Y-%m-%d)
+    #[arg(long, value_name = "FORMAT", value_parser = parse_date_format)]
+    date_format: Option<String>,
+
+    /// Link each version heading to its release page (version-separated Markdown output)
+    #[arg(long)]
+    link_versions: bool,
+
+    /// Push every Markdown heading N levels deeper (capped at h6), for embedding in another document
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    shift_headings: usize,
+
+    /// How to group Markdown output: by section, by repository within sections, or by release
+    #[arg(long, value_enum, default_value_t = GroupBy::Section)]
+    group_by: GroupBy,
+
+    /// Prepend a table of contents linking to each section (Markdown output)
     #[arg(long, default_value = "false")]
-    verbose: bool,
-}
+    toc: bool,
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Release {
-    id: u64,
-    tag_name: String,
-    name: Option<String>,
-    body: Option<String>,
-    published_at: String,
-    prerelease: bool,
-}
+    /// Render Markdown output through a Tera template file instead of the built-in layout ("default" selects the shipped template)
+    #[arg(long, value_name = "PATH")]
+    template: Option<PathBuf>,
 
-#[derive(Debug)]
-struct ReleaseNoteItem {
-    content: String,
-    version: String,
-    date: NaiveDate,
-}
+    /// Merge into the existing output file instead of replacing it, skipping releases it already lists
+    #[arg(long)]
+    append: bool,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    
-    // Initialize logger
-    if cli.verbose {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
-    } else {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-    }
-    
-    info!("Fetching release notes for {}/{}", cli.owner, cli.repo);
+    /// Only emit items not already present in this previously generated Markdown file
+    #[arg(long, value_name = "FILE")]
+    diff_against: Option<PathBuf>,
 
-    // Get all releases first
-    let all_releases = fetch_all_releases(&cli).await?;
-    info!("Found {} releases total", all_releases.len());
+    /// Compare exactly two selected releases and only emit the items the newer one has that the older one doesn't
+    #[arg(long, conflicts_with_all = ["diff_against", "summarize", "append"])]
+    changed_only: bool,
 
-    if all_releases.is_empty() {
-        warn!("No releases found. Exiting.");
-        return Ok(());
-    }
+    /// Append a summary table of release, item and per-section counts (Markdown output)
+    #[arg(long)]
+    stats: bool,
 
-    // Determine which releases to process based on CLI flags
-    let releases_to_process = if let Some(versions) = &cli.versions {
-        // Process arbitrary versions
-        let version_tags: Vec<&str> = versions.split(',').map(|s| s.trim()).collect();
-        debug!("Processing specific versions: {:?}", version_tags);
-        filter_releases_by_tags(&all_releases, &version_tags)?
-    } else if cli.start_tag.is_some() || cli.end_tag.is_some() {
-        // Process range of versions
-        debug!("Processing range: start={:?}, end={:?}", cli.start_tag, cli.end_tag);
-        filter_releases_by_range(&all_releases, cli.start_tag.as_deref(), cli.end_tag.as_deref())?
-    } else {
-        // Process all releases
-        debug!("Processing all releases");
-        all_releases
-    };
+    /// Append a Contributors section listing every @mentioned handle (Markdown output)
+    #[arg(long, default_value = "false")]
+    contributors: bool,
 
-    info!("Processing {} releases", releases_to_process.len());
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Markdown)]
+    format: OutputFormat,
 
-    let markdown = if cli.merge_headings {
-        // Merge content under common headings
-        debug!("Merging release notes by heading");
-        let merged_by_heading = merge_release_notes_by_heading(&releases_to_process);
-        generate_markdown_merged_headings(&merged_by_heading)
-    } else {
-        // Traditional merge - keep versions separate under each heading
-        debug!("Merging release notes by version");
-        let merged_sections = merge_release_notes(&releases_to_process);
-        generate_markdown(&merged_sections)
-    };
+    /// Whether --format terminal uses ANSI colors (auto: only when printing to a terminal)
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 
-    // Write to file
-    debug!("Writing output to {:?}", cli.output);
-    let mut file = File::create(&cli.output)
-        .with_context(|| format!("Failed to create output file: {:?}", cli.output))?;
-    file.write_all(markdown.as_bytes())
-        .with_context(|| format!("Failed to write to output file: {:?}", cli.output))?;
+    /// Extra section-to-category mappings for keepachangelog output (e.g. "Improvements=Changed,Breaking=Removed")
+    #[arg(long)]
+    changelog_map: Option<String>,
 
-    info!("Successfully wrote aggregated release notes to {:?}", cli.output);
-    Ok(())
-}
+    /// Also write a JSON manifest of the processed releases and whether each contributed content
+    #[arg(long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
 
-async fn fetch_all_releases(cli: &Cli) -> Result<Vec<Release>> {
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static("github-release-notes-aggregator"));
-    
-    if let Some(token) = &cli.token {
-        debug!("Using GitHub personal access token for authentication");
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            HeaderValue::from_str(&format!("token {}", token))?,
-        );
-    } else {
-        debug!("No GitHub token provided, using unauthenticated requests");
-    }
+    /// Write each section to its own Markdown file in --output-dir, with an index.md linking to them
+    #[arg(long, value_enum, requires = "output_dir", conflicts_with_all = ["append", "template"])]
+    split_by: Option<SplitBy>,
 
-    let url = format!(
-        "https://api.github.com/repos/{}/{}/releases?per_page=100",
-        cli.owner, cli.repo
-    );
-    
-    info!("Making API request to: {}", url);
-    
-    // Log request details before sending
-    debug!("API Request: GET {}", url);
-    debug!("Headers: {:?}", headers);
-    
-    let response = client
-        .get(&url)
-        .headers(headers)
-        .send()
-        .await
-        .context("Failed to send request to GitHub API")?;
-    
-    // Log response details
-    debug!("API Response: Status: {}", response.status());
-    debug!("Response headers: {:?}", response.headers());
+    /// Directory for the files written by --split-by
+    #[arg(long, value_name = "DIR", requires = "split_by")]
+    output_dir: Option<PathBuf>,
     
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_else(|_| "Unable to read response body".to_string());
-        error!("GitHub API error: Status={}, Body={}", status, body);
-        return Err(anyhow::anyhow!(
-            "GitHub API returned error status: {}, Body: {}",
-            status, body
-        ));
-    }
-    
-    // Clone the response for logging the body if needed
-    let response_text = response.text().await.context("Failed to get response text")?;
-    debug!("Response body length: {} bytes", response_text.len());
-    
-    if cli.verbose {
-        debug!("First 500 characters of response: {}", 
-            if response_text.len() > 500 {
-                &response_text[..500]
-            } else {
-                &response_text
-            }
-        );
+    /// Enable verbose logging
+    #[arg(long, default_value = "false")]
+    verbose: bool,
+
+    /// Only log errors, for scripting
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print the remaining GitHub API quota and exit without aggregating
+    #[arg(long)]
+    check_rate_limit: bool,
+
+    /// TOML file of option defaults keyed by field name (e.g. exclude_sections = "Chores"); command-line flags take precedence
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Log line format on stderr
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Open HTML output in the default browser for a quick preview
+    #[cfg(feature = "open")]
+    #[arg(long)]
+    open: bool,
+}
+
+impl Cli {
+    fn into_config(self) -> AggregatorConfig {
+        AggregatorConfig {
+            owner: self.owner,
+            repo: self.repo,
+            source: self.source,
+            start_tag: self.start_tag,
+            end_tag: self.end_tag,
+            token: self.token,
+            provider: self.provider,
+            base_url: self.base_url,
+            max_retries: self.max_retries,
+            retry_missing_tags: self.retry_missing_tags,
+            cache_dir: self.cache_dir,
+            from_file: self.from_file,
+            from_tags: self.from_tags,
+            generate: self.generate,
+            use_graphql: self.use_graphql,
+            fetch_full_bodies: self.fetch_full_bodies,
+            concurrency: self.concurrency,
+            include_prereleases: self.include_prereleases,
+            exclude_prereleases_from_output: self.exclude_prereleases_from_output,
+            include_drafts: self.include_drafts,
+            versions: self.versions,
+            versions_file: self.versions_file,
+            tag_pattern: self.tag_pattern,
+            tag_prefix: self.tag_prefix,
+            min_version: self.min_version,
+            fail_on_empty: self.fail_on_empty,
+            validate: self.validate,
+            max_heading_depth: self.max_heading_depth,
+            latest: self.latest,
+            collapse_repeated: self.collapse_repeated,
+            fuzzy_dedup: self.fuzzy_dedup,
+            merge_headings: self.merge_headings,
+            summarize: self.summarize,
+            no_source_annotations: self.no_source_annotations,
+            order_by: self.order_by,
+            strict_range: self.strict_range,
+            exclusive_start: self.exclusive_start,
+            exclusive_end: self.exclusive_end,
+            since: self.since,
+            until: self.until,
+            window: self.window,
+            exclude_sections: self.exclude_sections,
+            include_sections: self.include_sections,
+            section_aliases: self.section_aliases,
+            link_refs: self.link_refs,
+            parse_conventional: self.parse_conventional,
+            name_as_section: self.name_as_section,
+            join_wrapped_bullets: self.join_wrapped_bullets,
+            render_emoji: self.render_emoji,
+            skip_empty: self.skip_empty,
+            keep_compare_links: self.keep_compare_links,
+            list_assets: self.list_assets,
+            section_order: self.section_order,
+            uncategorized_label: self.uncategorized_label,
+            max_items_per_section: self.max_items_per_section,
+            sort_items: self.sort_items,
+            preserve_levels: self.preserve_levels,
+            relative_dates: self.relative_dates,
+            date_format: self.date_format,
+            link_versions: self.link_versions,
+            shift_headings: self.shift_headings,
+            group_by: self.group_by,
+            toc: self.toc,
+            template: self.template,
+            append_to: self.append.then(|| self.output.clone()),
+            diff_against: self.diff_against,
+            changed_only: self.changed_only,
+            stats: self.stats,
+            contributors: self.contributors,
+            format: self.format,
+            // Files never get colors unless asked for explicitly
+            color: match self.color {
+                ColorChoice::Auto if !(self.stdout || self.output.as_os_str() == "-") => ColorChoice::Never,
+                color => color,
+            },
+            changelog_map: self.changelog_map,
+            manifest: self.manifest,
+            split_by: self.split_by,
+            output_dir: self.output_dir,
+            verbose: self.verbose,
+        }
     }
+}
+
+// Parse the command line, filling in any options it leaves unset from the --config file
+fn parse_cli() -> Result<Cli> {
+    let args: Vec<OsString> = std::env::args_os().collect();
     
-    // Parse the JSON response
-    let releases: Vec<Release> = serde_json::from_str(&response_text)
-        .context("Failed to parse GitHub API response")?;
-    
-    debug!("Parsed {} releases from API response", releases.len());
-
-    // Filter out prereleases if not included
-    let filtered_releases = if !cli.include_prereleases {
-        let prerelease_count = releases.iter().filter(|r| r.prerelease).count();
-        let filtered = releases.into_iter().filter(|r| !r.prerelease).collect::<Vec<_>>();
-        debug!("Filtered out {} prereleases", prerelease_count);
-        filtered
-    } else {
-        releases
+    // A lenient first pass finds the config file and which options were given explicitly,
+    // without failing on required options the file may still provide
+    let matches = Cli::command().ignore_errors(true).get_matches_from(&args);
+    let Some(path) = matches.get_one::<PathBuf>("config") else {
+        return Ok(parse_args(args));
     };
-
-    // Sort by published date (newest first)
-    let mut sorted_releases = filtered_releases;
-    sorted_releases.sort_by(|a, b| {
-        let date_a = chrono::DateTime::parse_from_rfc3339(&a.published_at)
-            .unwrap()
-            .naive_utc();
-        let date_b = chrono::DateTime::parse_from_rfc3339(&b.published_at)
-            .unwrap()
-            .naive_utc();
-        date_b.cmp(&date_a)
-    });
     
-    debug!("Releases sorted by date (newest first)");
+    let file_args = config_file_args(path, &matches)?;
+    debug!("Loaded {} arguments from config file {:?}", file_args.len(), path);
+    Ok(parse_args(args.into_iter().chain(file_args)))
+}
 
-    Ok(sorted_releases)
+// Clap exits with 2 on a usage error, but that code means no releases were found
+fn parse_args(args: impl IntoIterator<Item = OsString>) -> Cli {
+    Cli::try_parse_from(args).unwrap_or_else(|err| {
+        // --help and --version
+        if !err.use_stderr() {
+            err.exit();
+        }
+        let _ = err.print();
+        std::process::exit(EXIT_FAILURE.into())
+    })
 }
 
-fn filter_releases_by_range(
-    releases: &[Release], 
-    start_tag: Option<&str>,
-    end_tag: Option<&str>
-) -> Result<Vec<Release>> {
-    let mut filtered = releases.to_vec();
-    
-    if let (Some(start_tag), Some(end_tag)) = (start_tag, end_tag) {
-        debug!("Filtering releases between tags '{}' and '{}'", start_tag, end_tag);
-        let start_index = releases
-            .iter()
-            .position(|r| r.tag_name == start_tag)
-            .context(format!("Start tag '{}' not found", start_tag))?;
+// Translate a TOML config file into flags for every option the command line didn't set
+fn config_file_args(path: &Path, matches: &ArgMatches) -> Result<Vec<OsString>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {:?}", path))?;
+    let table: toml::Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {:?}", path))?;
+    
+    let command = Cli::command();
+    let mut args = Vec::new();
+    
+    for (key, value) in table {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key.as_str() && arg.get_long().is_some())
+            .filter(|arg| key != "config" && !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version))
+            .ok_or_else(|| anyhow!("Unknown option '{}' in config file {:?}", key, path))?;
         
-        let end_index = releases
-            .iter()
-            .position(|r| r.tag_name == end_tag)
-            .context(format!("End tag '{}' not found", end_tag))?;
-
-        // Ensure we get releases between the two tags (inclusive)
-        let (lower_index, higher_index) = if start_index <= end_index {
-            (start_index, end_index)
-        } else {
-            (end_index, start_index)
-        };
+        if matches.value_source(&key) == Some(ValueSource::CommandLine) {
+            debug!("Command line overrides '{}' from the config file", key);
+            continue;
+        }
         
-        debug!("Tag indices: start={}, end={}", lower_index, higher_index);
-
-        filtered = releases.iter().enumerate()
-            .filter(|(i, _)| *i >= lower_index && *i <= higher_index)
-            .map(|(_, r)| r.clone())
-            .collect();
-    } else if let Some(start_tag) = start_tag {
-        // Only start tag specified - get from that tag to the latest
-        debug!("Filtering releases from tag '{}' to latest", start_tag);
-        let start_index = releases
-            .iter()
-            .position(|r| r.tag_name == start_tag)
-            .context(format!("Start tag '{}' not found", start_tag))?;
-            
-        filtered = releases.iter().enumerate()
-            .filter(|(i, _)| *i >= start_index)
-            .map(|(_, r)| r.clone())
-            .collect();
-    } else if let Some(end_tag) = end_tag {
-        // Only end tag specified - get from the earliest to that tag
-        debug!("Filtering releases from earliest to tag '{}'", end_tag);
-        let end_index = releases
-            .iter()
-            .position(|r| r.tag_name == end_tag)
-            .context(format!("End tag '{}' not found", end_tag))?;
-            
-        filtered = releases.iter().enumerate()
-            .filter(|(i, _)| *i <= end_index)
-            .map(|(_, r)| r.clone())
-            .collect();
+        let flag = OsString::from(format!("--{}", arg.get_long().unwrap()));
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, toml::Value::Boolean(enabled)) => {
+                if enabled {
+                    args.push(flag);
+                }
+            }
+            (ArgAction::SetTrue, _) => {
+                return Err(anyhow!("Option '{}' in config file {:?} must be true or false", key, path));
+            }
+            // Repeatable options take one flag per element, others a comma-separated list
+            (ArgAction::Append, toml::Value::Array(values)) => {
+                for value in values {
+                    args.push(flag.clone());
+                    args.push(config_value(&key, value)?.into());
+                }
+            }
+            (_, toml::Value::Array(values)) => {
+                let values = values
+                    .into_iter()
+                    .map(|value| config_value(&key, value))
+                    .collect::<Result<Vec<_>>>()?;
+                args.push(flag);
+                args.push(values.join(",").into());
+            }
+            (_, value) => {
+                args.push(flag);
+                args.push(config_value(&key, value)?.into());
+            }
+        }
     }
     
-    info!("Filtered to {} releases", filtered.len());
-    Ok(filtered)
+    Ok(args)
 }
 
-fn filter_releases_by_tags(releases: &[Release], tags: &[&str]) -> Result<Vec<Release>> {
-    debug!("Filtering releases by specific tags: {:?}", tags);
-    let mut filtered_releases = Vec::new();
-    let mut missing_tags = Vec::new();
-    
-    for tag in tags {
-        let release = releases.iter().find(|r| &r.tag_name == tag);
-        
-        match release {
-            Some(release) => filtered_releases.push(release.clone()),
-            None => missing_tags.push(*tag),
+// Render a scalar config value the way it would be written on the command line
+fn config_value(key: &str, value: toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        toml::Value::Datetime(value) => Ok(value.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) => {
+            Err(anyhow!("Option '{}' in the config file must be a single value or a list of values", key))
         }
     }
-    
-    if !missing_tags.is_empty() {
-        error!("Missing tags: {:?}", missing_tags);
-        return Err(anyhow::anyhow!(
-            "Could not find the following tags: {}",
-            missing_tags.join(", ")
-        ));
-    }
-    
-    // Sort by published date (newest first)
-    filtered_releases.sort_by(|a, b| {
-        let date_a = chrono::DateTime::parse_from_rfc3339(&a.published_at)
-            .unwrap()
-            .naive_utc();
-        let date_b = chrono::DateTime::parse_from_rfc3339(&b.published_at)
-            .unwrap()
-            .naive_utc();
-        date_b.cmp(&date_a)
-    });
-    
-    info!("Filtered to {} releases", filtered_releases.len());
-    Ok(filtered_releases)
 }
 
-fn parse_release_notes(body: &str) -> HashMap<String, Vec<String>> {
-    let mut sections: HashMap<String, Vec<String>> = HashMap::new();
-    let mut current_section = "Uncategorized".to_string();
-    
-    // Initialize with uncategorized section
-    sections.insert(current_section.clone(), Vec::new());
-    
-    // Define a regex for Markdown headings
-    let heading_regex = Regex::new(r"^(#{1,6})\s+(.+)$").unwrap();
-    
-    for line in body.lines() {
-        if let Some(captures) = heading_regex.captures(line) {
-            current_section = captures.get(2).unwrap().as_str().trim().to_string();
-            if !sections.contains_key(&current_section) {
-                sections.insert(current_section.clone(), Vec::new());
-            }
-        } else if !line.trim().is_empty() {
-            // Add non-empty lines to the current section
-            sections.get_mut(&current_section).unwrap().push(line.to_string());
+// Exit codes scripts can branch on; see the README
+const EXIT_FAILURE: u8 = 1;
+const EXIT_NO_RELEASES: u8 = 2;
+const EXIT_TAG_NOT_FOUND: u8 = 3;
+const EXIT_AUTH: u8 = 4;
+const EXIT_NETWORK: u8 = 5;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(exit_code(&err))
         }
     }
-    
-    // Remove sections with no content
-    sections.retain(|_, lines| !lines.is_empty());
-    
-    debug!("Parsed {} sections from release notes", sections.len());
-    sections
 }
 
-fn merge_release_notes(releases: &[Release]) -> HashMap<String, Vec<ReleaseNoteItem>> {
-    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
-    let mut known_sections: HashSet<String> = HashSet::new();
-    
-    // First pass - collect all possible sections
-    for release in releases {
-        if let Some(body) = &release.body {
-            let sections = parse_release_notes(body);
-            for section_name in sections.keys() {
-                known_sections.insert(section_name.clone());
-            }
+// The exit code for the first error in the chain that has one of its own
+fn exit_code(err: &anyhow::Error) -> u8 {
+    for cause in err.chain() {
+        if cause.is::<NoReleases>() {
+            return EXIT_NO_RELEASES;
+        }
+        if cause.is::<TagNotFound>() {
+            return EXIT_TAG_NOT_FOUND;
+        }
+        if cause.downcast_ref::<ApiError>().is_some_and(ApiError::is_auth_failure) {
+            return EXIT_AUTH;
+        }
+        // Connection failures and timeouts; error statuses are ApiErrors
+        if cause.is::<reqwest::Error>() {
+            return EXIT_NETWORK;
         }
     }
+    EXIT_FAILURE
+}
+
+async fn run() -> Result<()> {
+    let mut cli = parse_cli()?;
     
-    debug!("Found {} unique section names across all releases", known_sections.len());
+    // Initialize logger
+    logging::init(cli.verbose, cli.quiet, cli.log_format);
     
-    // Initialize merged sections
-    for section in known_sections {
-        merged_sections.insert(section, Vec::new());
+    // Fall back to the environment so the token doesn't need to be on the command line
+    cli.token = resolve_token(cli.token.take(), std::env::var("GITHUB_TOKEN").ok());
+
+    if cli.check_rate_limit {
+        print!("{}", check_rate_limit(&cli.into_config()).await?);
+        return Ok(());
     }
-    
-    // Second pass - populate sections with items
-    for release in releases {
-        if let Some(body) = &release.body {
-            let version = release.tag_name.clone();
-            let date = chrono::DateTime::parse_from_rfc3339(&release.published_at)
-                .unwrap()
-                .naive_utc()
-                .date();
-            
-            debug!("Processing release {} ({})", version, date);
-            let sections = parse_release_notes(body);
-            
-            for (section_name, items) in sections {
-                for item in items {
-                    let note_item = ReleaseNoteItem {
-                        content: item,
-                        version: version.clone(),
-                        date,
-                    };
-                    
-                    merged_sections.get_mut(&section_name).unwrap().push(note_item);
-                }
-            }
-        } else {
-            debug!("Release {} has no body content", release.tag_name);
-        }
+
+    let to_stdout = cli.stdout || cli.output.as_os_str() == "-";
+    // Split output leaves the index next to the section files it links to
+    let mut output = match &cli.output_dir {
+        Some(dir) => dir.join(SPLIT_INDEX_FILE),
+        None => cli.output.clone(),
+    };
+    // An --output ending in .gz is compressed without asking
+    let compress = cli.gzip || (!to_stdout && output.extension().is_some_and(|ext| ext == "gz"));
+    if compress && cli.append {
+        return Err(anyhow!("--append can't merge into a compressed file"));
+    }
+    if cli.gzip && output.extension().is_none_or(|ext| ext != "gz") {
+        output.as_mut_os_string().push(".gz");
+    }
+    // Fail before fetching anything rather than after
+    if cli.gist && cli.token.is_none() {
+        return Err(anyhow!("--gist needs a GitHub token with the gist scope (--token or GITHUB_TOKEN)"));
     }
-    
-    debug!("Merged all release notes into sections");
-    merged_sections
-}
 
-// New function for merging content under common headings
-#[derive(Debug)]
-struct MergedHeadingItem {
-    content: String,
-    sources: Vec<String>, // List of versions this item came from
-}
+    let gist = cli.gist;
+    #[cfg(feature = "open")]
+    let (open, format) = (cli.open, cli.format);
+    let config = cli.into_config();
+    let markdown = aggregate(config.clone()).await?;
 
-fn merge_release_notes_by_heading(releases: &[Release]) -> HashMap<String, Vec<MergedHeadingItem>> {
-    let mut merged_sections: HashMap<String, Vec<MergedHeadingItem>> = HashMap::new();
-    let mut known_sections: HashSet<String> = HashSet::new();
-    
-    // First pass - collect all possible sections
-    for release in releases {
-        if let Some(body) = &release.body {
-            let sections = parse_release_notes(body);
-            for section_name in sections.keys() {
-                known_sections.insert(section_name.clone());
-            }
-        }
+    // Validation passed and has already been reported; there's no document to write
+    if config.validate {
+        return Ok(());
     }
-    
-    debug!("Found {} unique section names across all releases", known_sections.len());
-    
-    // Initialize merged sections
-    for section in known_sections {
-        merged_sections.insert(section, Vec::new());
+
+    #[cfg(feature = "open")]
+    if open {
+        open_preview(&markdown, format);
     }
-    
-    // Second pass - collect all content items by section
-    let mut content_map: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
-    
-    for release in releases {
-        if let Some(body) = &release.body {
-            let version = release.tag_name.clone();
-            debug!("Processing release {} for heading merge", version);
-            let sections = parse_release_notes(body);
-            
-            for (section_name, items) in sections {
-                if !content_map.contains_key(&section_name) {
-                    content_map.insert(section_name.clone(), HashMap::new());
-                }
-                
-                let section_content = content_map.get_mut(&section_name).unwrap();
-                
-                for item in items {
-                    // Normalize the content by trimming whitespace
-                    let normalized_content = item.trim().to_string();
-                    
-                    if !section_content.contains_key(&normalized_content) {
-                        section_content.insert(normalized_content.clone(), Vec::new());
-                    }
-                    
-                    section_content.get_mut(&normalized_content).unwrap().push(version.clone());
-                }
-            }
-        }
+
+    if gist {
+        println!("{}", publish_gist(&config, &markdown).await?);
+        return Ok(());
     }
-    
-    // Third pass - create merged items
-    for (section_name, content_items) in content_map {
-        let mut merged_items = Vec::new();
-        
-        for (content, versions) in content_items {
-            let merged_item = MergedHeadingItem {
-                content,
-                sources: versions,
-            };
-            
-            merged_items.push(merged_item);
-        }
-        
-        // Sort items by how many versions they appear in (most common first)
-        merged_items.sort_by(|a, b| {
-            // First by number of sources (descending)
-            let source_cmp = b.sources.len().cmp(&a.sources.len());
-            
-            // Then alphabetically by content if tied
-            if source_cmp == std::cmp::Ordering::Equal {
-                a.content.cmp(&b.content)
-            } else {
-                source_cmp
-            }
-        });
-        
-        merged_sections.insert(section_name, merged_items);
+
+    // Logging goes to stderr, so stdout only ever carries the aggregated notes
+    if to_stdout {
+        debug!("Writing output to stdout");
+        std::io::stdout()
+            .write_all(markdown.as_bytes())
+            .context("Failed to write to stdout")?;
+        return Ok(());
     }
-    
-    debug!("Merged release notes by heading");
-    merged_sections
-}
 
-fn generate_markdown(
-    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
-) -> String {
-    debug!("Generating markdown output (version-based)");
-    let mut markdown = String::from("# Aggregated Release Notes\n\n");
-    
-    // Sort sections alphabetically, but put "Uncategorized" at the end
-    let mut section_names: Vec<&String> = merged_sections.keys().collect();
-    section_names.sort_by(|a, b| {
-        if *a == "Uncategorized" {
-            std::cmp::Ordering::Greater
-        } else if *b == "Uncategorized" {
-            std::cmp::Ordering::Less
-        } else {
-            a.cmp(b)
-        }
-    });
-    
-    for section_name in section_names {
-        debug!("Processing section: {}", section_name);
-        markdown.push_str(&format!("## {}\n\n", section_name));
-        
-        let items = &merged_sections[section_name];
-        
-        // Group items by version
-        let mut versions = HashMap::new();
-        for item in items {
-            versions
-                .entry((item.version.clone(), item.date))
-                .or_insert_with(Vec::new)
-                .push(item);
-        }
-        
-        // Sort versions by date (newest first)
-        let mut version_entries: Vec<_> = versions.into_iter().collect();
-        version_entries.sort_by(|a, b| b.0.1.cmp(&a.0.1));
-        
-        for ((version, date), version_items) in version_entries {
-            debug!("Adding version: {} ({})", version, date);
-            markdown.push_str(&format!(
-                "### {} ({})\n\n",
-                version,
-                date.format("%Y-%m-%d")
-            ));
-            
-            for item in version_items {
-                markdown.push_str(&format!("{}\n", item.content));
-            }
-            
-            markdown.push_str("\n");
-        }
+    // Write to file
+    debug!("Writing output to {:?}", output);
+    if compress {
+        write_output_atomic(&output, &gzip(markdown.as_bytes())?)?;
+    } else {
+        write_output_atomic(&output, markdown.as_bytes())?;
     }
-    
-    info!("Generated markdown output: {} bytes", markdown.len());
-    markdown
+
+    info!("Successfully wrote aggregated release notes to {:?}", output);
+    Ok(())
 }
 
-// New function to generate markdown with merged headings
-fn generate_markdown_merged_headings(
-    merged_sections: &HashMap<String, Vec<MergedHeadingItem>>,
-) -> String {
-    debug!("Generating markdown output (heading-based)");
-    let mut markdown = String::from("# Aggregated Release Notes (Merged by Heading)\n\n");
+// Write HTML output to a temporary file and hand it to the default browser
+#[cfg(feature = "open")]
+fn open_preview(html: &str, format: OutputFormat) {
+    if format != OutputFormat::Html {
+        log::warn!("--open only previews HTML output, ignoring");
+        return;
+    }
+    if std::env::var_os("CI").is_some() {
+        log::warn!("Not opening a browser in a CI environment, ignoring --open");
+        return;
+    }
     
-    // Sort sections alphabetically, but put "Uncategorized" at the end
-    let mut section_names: Vec<&String> = merged_sections.keys().collect();
-    section_names.sort_by(|a, b| {
-        if *a == "Uncategorized" {
-            std::cmp::Ordering::Greater
-        } else if *b == "Uncategorized" {
-            std::cmp::Ordering::Less
-        } else {
-            a.cmp(b)
-        }
-    });
+    let path = std::env::temp_dir().join(format!("ghnotes-preview-{}.html", std::process::id()));
+    let result = std::fs::write(&path, html)
+        .with_context(|| format!("Failed to write preview file: {:?}", path))
+        .and_then(|()| opener::open(&path).context("Failed to open the default browser"));
     
-    for section_name in section_names {
-        debug!("Processing section: {}", section_name);
-        markdown.push_str(&format!("## {}\n\n", section_name));
-        
-        let items = &merged_sections[section_name];
-        
-        for item in items {
-            // Add the content
-            markdown.push_str(&format!("{}\n", item.content));
-            
-            // Add source versions if there are multiple
-            if item.sources.len() > 1 {
-                let sorted_sources = {
-                    let mut sources = item.sources.clone();
-                    sources.sort();
-                    sources
-                };
-                
-                let sources_list = sorted_sources.join(", ");
-                debug!("Item appears in multiple versions: {}", sources_list);
-                markdown.push_str(&format!("*(Present in versions: {})*\n\n", sources_list));
-            } else if !item.sources.is_empty() {
-                debug!("Item appears in single version: {}", item.sources[0]);
-                markdown.push_str(&format!("*(From version: {})*\n\n", item.sources[0]));
-            } else {
-                markdown.push_str("\n");
-            }
-        }
-        
-        markdown.push_str("\n");
+    match result {
+        Ok(()) => info!("Opened preview of {:?} in the browser", path),
+        Err(err) => log::warn!("Could not open preview: {:#}", err),
     }
-    
-    info!("Generated markdown output: {} bytes", markdown.len());
-    markdown
-}
\ No newline at end of file
+}
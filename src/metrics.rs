@@ -0,0 +1,101 @@
+use log::info;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of API requests sent, shared between every clone so all sources count towards one run
+#[derive(Debug, Clone, Default)]
+pub struct RequestCounter(Arc<AtomicUsize>);
+
+impl RequestCounter {
+    /// Count one more request, including retries of a failed one
+    pub fn record(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Stages of a run that are timed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Requesting and decoding releases
+    Fetching,
+    /// Selecting releases and splitting their notes into sections
+    Parsing,
+    /// Rendering the output document
+    Generating,
+}
+
+/// Wall time, time per phase and API requests of a single run
+#[derive(Debug)]
+pub struct Metrics {
+    started: Instant,
+    requests: RequestCounter,
+    fetching: Duration,
+    parsing: Duration,
+    generating: Duration,
+    // Phase being timed and when it began
+    current: Option<(Phase, Instant)>,
+}
+
+impl Metrics {
+    pub fn start() -> Self {
+        Metrics {
+            started: Instant::now(),
+            requests: RequestCounter::default(),
+            fetching: Duration::ZERO,
+            parsing: Duration::ZERO,
+            generating: Duration::ZERO,
+            current: None,
+        }
+    }
+
+    /// Counter to hand to the sources so their requests are included
+    pub fn requests(&self) -> &RequestCounter {
+        &self.requests
+    }
+
+    /// Stop timing the current phase, if any, and start timing `phase`
+    pub fn begin(&mut self, phase: Phase) {
+        self.finish();
+        self.current = Some((phase, Instant::now()));
+    }
+
+    /// Stop timing the current phase
+    pub fn finish(&mut self) {
+        if let Some((phase, began)) = self.current.take() {
+            self.add(phase, began.elapsed());
+        }
+    }
+
+    /// Add time spent in `phase`; a phase entered more than once accumulates
+    pub fn add(&mut self, phase: Phase, elapsed: Duration) {
+        match phase {
+            Phase::Fetching => self.fetching += elapsed,
+            Phase::Parsing => self.parsing += elapsed,
+            Phase::Generating => self.generating += elapsed,
+        }
+    }
+
+    /// One-line summary of the run so far
+    pub fn summary(&self) -> String {
+        format!(
+            "Finished in {:.2?} with {} API request{} (fetching {:.2?}, parsing {:.2?}, generating {:.2?})",
+            self.started.elapsed(),
+            self.requests.count(),
+            if self.requests.count() == 1 { "" } else { "s" },
+            self.fetching,
+            self.parsing,
+            self.generating
+        )
+    }
+
+    /// Finish the current phase and log the summary
+    pub fn log_summary(&mut self) {
+        self.finish();
+        info!("{}", self.summary());
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(feature = "progress")]
+use std::io::IsTerminal;
+#[cfg(feature = "progress")]
+use std::time::Duration;
+
+/// Progress of a long fetch, drawn on stderr with the `progress` feature; otherwise every call does nothing
+#[derive(Clone, Default)]
+pub struct Progress {
+    #[cfg(feature = "progress")]
+    bar: Option<indicatif::ProgressBar>,
+}
+
+impl Progress {
+    /// A spinner on stderr if `enabled` and stderr is a terminal, so piped and CI runs stay plain
+    #[cfg(feature = "progress")]
+    pub fn new(enabled: bool) -> Self {
+        let bar = (enabled && std::io::stderr().is_terminal()).then(|| {
+            let bar = indicatif::ProgressBar::new_spinner();
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar
+        });
+        Progress { bar }
+    }
+
+    #[cfg(not(feature = "progress"))]
+    pub fn new(_enabled: bool) -> Self {
+        Progress::default()
+    }
+
+    /// Whether anything is drawn
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "progress")]
+        return self.bar.is_some();
+        #[cfg(not(feature = "progress"))]
+        false
+    }
+
+    /// A page of the release list arrived, bringing the total to `releases`
+    pub fn fetched_page(&self, page: usize, releases: usize) {
+        self.set_message(|| format!("Fetched {} page{} ({} releases)", page, if page == 1 { "" } else { "s" }, releases));
+    }
+
+    /// Another truncated body was refetched, `done` of `total`
+    pub fn fetched_body(&self, done: usize, total: usize) {
+        self.set_message(|| format!("Fetched {}/{} full release bodies", done, total));
+    }
+
+    /// Remove the spinner once fetching is over
+    pub fn finish(&self) {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+
+    // The message is only formatted when there's a bar to show it
+    fn set_message(&self, message: impl FnOnce() -> String) {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = &self.bar {
+            bar.set_message(message());
+        }
+        #[cfg(not(feature = "progress"))]
+        let _ = message;
+    }
+}
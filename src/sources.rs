@@ -0,0 +1,1324 @@
+use anyhow::{Context, Result};
+use futures::stream::{self, FuturesUnordered, StreamExt, TryStreamExt};
+use log::{debug, error, info, warn};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_NONE_MATCH, LINK, USER_AGENT};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::helpers::{parse_rate_limit, parse_retry_after, tag_matches, GitHubError, RateLimit};
+use crate::metrics::RequestCounter;
+use crate::progress::Progress;
+use crate::{AggregatorConfig, Release};
+
+/// Default base URL of the GitHub REST API
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+// Most releases the GitHub list endpoint returns per page
+const GITHUB_PAGE_SIZE: usize = 100;
+
+/// A place releases can be fetched from, normalized into the GitHub-shaped `Release`
+pub trait ReleaseSource {
+    async fn fetch(&self) -> Result<Vec<Release>>;
+}
+
+/// Releases from the GitHub REST API
+pub struct GitHubSource {
+    pub api_url: String,
+    pub owner: String,
+    pub repo: String,
+    pub token: Option<String>,
+    pub fetch_full_bodies: bool,
+    pub concurrency: usize,
+    pub verbose: bool,
+    pub retry: RetryPolicy,
+    pub cache_dir: Option<PathBuf>,
+    /// Tags the selection names; further pages are fetched until they have all been seen
+    pub wanted_tags: Vec<String>,
+    /// `--tag-prefix` the releases' tags carry and `wanted_tags` are named without
+    pub tag_prefix: Option<String>,
+    /// Build releases from the repository's tags and their annotation messages instead of its releases
+    pub from_tags: bool,
+    /// Have GitHub generate notes for a tag range instead of fetching published releases
+    pub generate: Option<NotesRange>,
+    /// Page through releases with the GraphQL API, which returns full bodies, instead of the REST list
+    pub use_graphql: bool,
+    pub requests: RequestCounter,
+    pub progress: Progress,
+}
+
+/// Tags bounding the notes `POST /releases/generate-notes` writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotesRange {
+    /// Release the notes are for; the tag doesn't have to exist yet
+    pub tag: String,
+    /// Tag to compare against, or GitHub's choice of the previous release if unset
+    pub previous_tag: Option<String>,
+}
+
+impl GitHubSource {
+    pub fn from_config(config: &AggregatorConfig, owner: &str, repo: &str, requests: &RequestCounter) -> Self {
+        GitHubSource {
+            api_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| GITHUB_API_URL.to_string()),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            token: config.token.clone(),
+            fetch_full_bodies: config.fetch_full_bodies,
+            concurrency: config.concurrency,
+            verbose: config.verbose,
+            retry: RetryPolicy {
+                max_retries: config.max_retries,
+                ..RetryPolicy::default()
+            },
+            cache_dir: config.cache_dir.clone(),
+            wanted_tags: wanted_tags(config),
+            tag_prefix: config.tag_prefix.clone(),
+            from_tags: config.from_tags,
+            generate: config
+                .end_tag
+                .clone()
+                .filter(|_| config.generate)
+                .map(|tag| NotesRange { tag, previous_tag: config.start_tag.clone() }),
+            use_graphql: config.use_graphql,
+            requests: requests.clone(),
+            progress: Progress::default(),
+        }
+    }
+
+    fn repo_url(&self) -> String {
+        format!(
+            "{}/repos/{}/{}",
+            self.api_url.trim_end_matches('/'),
+            self.owner,
+            self.repo
+        )
+    }
+
+    // Refetch truncated bodies through `GET /releases/{id}`, at most `concurrency` requests at a time
+    async fn fetch_full_bodies(
+        &self,
+        client: &reqwest::Client,
+        headers: &HeaderMap,
+        releases: &mut [Release],
+    ) -> Result<()> {
+        let mut queue = releases
+            .iter()
+            .filter(|r| is_body_truncated(r))
+            .map(|r| r.id)
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        info!("Fetching full bodies for {} releases", queue.len());
+        let concurrency = self.concurrency.max(1);
+        let total = queue.len();
+        let mut done = 0;
+
+        let mut pending = FuturesUnordered::new();
+        for id in queue.by_ref().take(concurrency) {
+            pending.push(self.fetch_release_by_id(client, headers, id));
+        }
+
+        while let Some(result) = pending.next().await {
+            let full_release = result?;
+            done += 1;
+            self.progress.fetched_body(done, total);
+            if let Some(release) = releases.iter_mut().find(|r| r.id == full_release.id) {
+                debug!("Fetched full body for release {}", release.tag_name);
+                release.body = full_release.body;
+            }
+
+            if let Some(id) = queue.next() {
+                pending.push(self.fetch_release_by_id(client, headers, id));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_release_by_id(
+        &self,
+        client: &reqwest::Client,
+        headers: &HeaderMap,
+        id: u64,
+    ) -> Result<Release> {
+        let url = format!("{}/releases/{}", self.repo_url(), id);
+
+        let response_text = github_get(client, &url, headers, &self.retry, &self.requests).await?;
+        serde_json::from_str(&response_text)
+            .with_context(|| format!("Failed to parse GitHub API response for release {}", id))
+    }
+
+    // Ask GitHub to write notes for the range, returned as a single release for the rest of the pipeline
+    async fn generate_notes(&self, client: &reqwest::Client, headers: &HeaderMap, range: &NotesRange) -> Result<Vec<Release>> {
+        let url = format!("{}/releases/generate-notes", self.repo_url());
+        let request = GenerateNotesRequest {
+            tag_name: &range.tag,
+            previous_tag_name: range.previous_tag.as_deref(),
+        };
+        info!("Making API request to: {}", url);
+        let response_text = github_post(client, &url, headers, &request, &self.retry, &self.requests).await?;
+        let notes: GeneratedNotes = serde_json::from_str(&response_text).context("Failed to parse generated release notes")?;
+        
+        Ok(vec![Release {
+            id: 1,
+            tag_name: range.tag.clone(),
+            name: Some(notes.name),
+            body: Some(notes.body),
+            published_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            prerelease: false,
+            draft: false,
+            created_at: None,
+            assets: Vec::new(),
+            repo: String::new(),
+            label: None,
+            html_url: None,
+        }])
+    }
+
+    // GitHub Enterprise serves GraphQL at /api/graphql next to the REST /api/v3
+    fn graphql_url(&self) -> String {
+        let api_url = self.api_url.trim_end_matches('/');
+        match api_url.strip_suffix("/v3") {
+            Some(base) => format!("{}/graphql", base),
+            None => format!("{}/graphql", api_url),
+        }
+    }
+
+    // Page through the releases newest first, stopping like the REST list once the wanted tags are in
+    async fn fetch_graphql(&self, client: &reqwest::Client, headers: &HeaderMap) -> Result<Vec<Release>> {
+        if self.token.is_none() {
+            return Err(anyhow::anyhow!("--use-graphql needs a GitHub token (--token or GITHUB_TOKEN)"));
+        }
+        
+        let url = self.graphql_url();
+        let mut releases: Vec<Release> = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut page = 0;
+        loop {
+            page += 1;
+            let request = GraphQlRequest {
+                query: GRAPHQL_RELEASES_QUERY,
+                variables: GraphQlVariables {
+                    owner: &self.owner,
+                    name: &self.repo,
+                    cursor: cursor.as_deref(),
+                },
+            };
+            info!("Making GraphQL request to: {} (page {})", url, page);
+            let response_text = github_post(client, &url, headers, &request, &self.retry, &self.requests).await?;
+            let connection = parse_graphql_releases(&response_text)
+                .with_context(|| format!("Failed to parse GitHub GraphQL response for page {}", page))?;
+            releases.extend(connection.nodes.into_iter().map(GraphQlRelease::into_release));
+            self.progress.fetched_page(page, releases.len());
+            
+            let missing = !missing_tags(&self.wanted_tags, self.tag_prefix.as_deref(), &releases).is_empty();
+            match connection.page_info.end_cursor {
+                Some(end_cursor) if connection.page_info.has_next_page && missing => cursor = Some(end_cursor),
+                _ => break,
+            }
+        }
+        
+        debug!("Fetched {} releases in {} GraphQL requests", releases.len(), page);
+        Ok(releases)
+    }
+
+    // List every tag ref, then look up each tag's annotation (or, for lightweight tags, its commit date)
+    async fn fetch_tags(&self, client: &reqwest::Client, headers: &HeaderMap) -> Result<Vec<Release>> {
+        let url = format!("{}/git/matching-refs/tags", self.repo_url());
+        info!("Making API request to: {}", url);
+        let response_text = github_get(client, &url, headers, &self.retry, &self.requests).await?;
+        let refs: Vec<GitRef> = serde_json::from_str(&response_text).context("Failed to parse GitHub tag refs")?;
+        debug!("Found {} tags", refs.len());
+
+        // `buffered` keeps the tags in ref order while still overlapping the lookups
+        let releases: Vec<Release> = stream::iter(refs)
+            .map(|tag_ref| self.fetch_tag(client, headers, tag_ref))
+            .buffered(self.concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(releases
+            .into_iter()
+            .enumerate()
+            .map(|(i, release)| Release { id: i as u64 + 1, ..release })
+            .collect())
+    }
+
+    async fn fetch_tag(&self, client: &reqwest::Client, headers: &HeaderMap, tag_ref: GitRef) -> Result<Release> {
+        let tag_name = tag_ref.name.trim_start_matches("refs/tags/").to_string();
+        let (body, date) = match tag_ref.object.kind.as_str() {
+            "tag" => {
+                let url = format!("{}/git/tags/{}", self.repo_url(), tag_ref.object.sha);
+                let response_text = github_get(client, &url, headers, &self.retry, &self.requests).await?;
+                let tag: GitTag = serde_json::from_str(&response_text)
+                    .with_context(|| format!("Failed to parse GitHub tag object for {}", tag_name))?;
+                (Some(tag_message(&tag.message)), tag.tagger.date)
+            }
+            _ => {
+                debug!("{} is a lightweight tag without a message", tag_name);
+                let url = format!("{}/git/commits/{}", self.repo_url(), tag_ref.object.sha);
+                let response_text = github_get(client, &url, headers, &self.retry, &self.requests).await?;
+                let commit: GitCommit = serde_json::from_str(&response_text)
+                    .with_context(|| format!("Failed to parse GitHub commit for {}", tag_name))?;
+                (None, commit.committer.date)
+            }
+        };
+
+        Ok(Release {
+            id: 0,
+            tag_name,
+            name: None,
+            body,
+            published_at: date,
+            prerelease: false,
+            draft: false,
+            created_at: None,
+            assets: Vec::new(),
+            repo: String::new(),
+            label: None,
+            html_url: None,
+        })
+    }
+}
+
+/// A ref as returned by `GET /repos/{owner}/{repo}/git/matching-refs/tags`
+#[derive(Debug, Deserialize)]
+pub struct GitRef {
+    #[serde(rename = "ref")]
+    pub name: String,
+    pub object: GitObject,
+}
+
+/// What a ref points at: a tag object for annotated tags, a commit for lightweight ones
+#[derive(Debug, Deserialize)]
+pub struct GitObject {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub sha: String,
+}
+
+/// Annotated tag object from `GET /repos/{owner}/{repo}/git/tags/{sha}`
+#[derive(Debug, Deserialize)]
+pub struct GitTag {
+    pub message: String,
+    pub tagger: GitSignature,
+}
+
+/// Commit from `GET /repos/{owner}/{repo}/git/commits/{sha}`
+#[derive(Debug, Deserialize)]
+pub struct GitCommit {
+    pub committer: GitSignature,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitSignature {
+    pub date: String,
+}
+
+/// Body of `POST /repos/{owner}/{repo}/releases/generate-notes`
+#[derive(Debug, Serialize)]
+struct GenerateNotesRequest<'a> {
+    tag_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_tag_name: Option<&'a str>,
+}
+
+/// Notes GitHub wrote for a tag range
+#[derive(Debug, Deserialize)]
+pub struct GeneratedNotes {
+    pub name: String,
+    pub body: String,
+}
+
+// Releases newest first, as the REST list returns them. `description` is the full body,
+// so there's nothing to refetch
+const GRAPHQL_RELEASES_QUERY: &str = "query($owner: String!, $name: String!, $cursor: String) {
+  repository(owner: $owner, name: $name) {
+    releases(first: 100, after: $cursor, orderBy: {field: CREATED_AT, direction: DESC}) {
+      pageInfo { hasNextPage endCursor }
+      nodes { databaseId tagName name description publishedAt createdAt isPrerelease isDraft url }
+    }
+  }
+}";
+
+/// Body of `POST /graphql`
+#[derive(Debug, Serialize)]
+struct GraphQlRequest<'a> {
+    query: &'a str,
+    variables: GraphQlVariables<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct GraphQlVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+    cursor: Option<&'a str>,
+}
+
+/// GraphQL answers 200 even when the query failed, with the reasons in `errors`
+#[derive(Debug, Deserialize)]
+pub struct GraphQlResponse {
+    pub data: Option<GraphQlData>,
+    #[serde(default)]
+    pub errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQlError {
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQlData {
+    pub repository: Option<GraphQlRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQlRepository {
+    pub releases: GraphQlReleaseConnection,
+}
+
+/// One page of releases
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlReleaseConnection {
+    pub page_info: GraphQlPageInfo,
+    pub nodes: Vec<GraphQlRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlPageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+/// Release as returned by the GitHub GraphQL API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlRelease {
+    pub database_id: Option<u64>,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub published_at: Option<String>,
+    pub created_at: Option<String>,
+    pub is_prerelease: bool,
+    pub is_draft: bool,
+    pub url: Option<String>,
+}
+
+impl GraphQlRelease {
+    pub fn into_release(self) -> Release {
+        Release {
+            id: self.database_id.unwrap_or_default(),
+            tag_name: self.tag_name,
+            name: self.name,
+            body: self.description,
+            // Drafts aren't published yet, as with the REST API
+            published_at: self.published_at.unwrap_or_default(),
+            prerelease: self.is_prerelease,
+            draft: self.is_draft,
+            created_at: self.created_at,
+            assets: Vec::new(),
+            repo: String::new(),
+            label: None,
+            html_url: self.url,
+        }
+    }
+}
+
+// The page of releases in a GraphQL response, or the errors GitHub reported instead
+pub fn parse_graphql_releases(body: &str) -> Result<GraphQlReleaseConnection> {
+    let response: GraphQlResponse = serde_json::from_str(body)?;
+    if !response.errors.is_empty() {
+        let messages: Vec<&str> = response.errors.iter().map(|err| err.message.as_str()).collect();
+        return Err(anyhow::anyhow!("GitHub GraphQL API returned errors: {}", messages.join("; ")));
+    }
+    
+    response
+        .data
+        .and_then(|data| data.repository)
+        .map(|repository| repository.releases)
+        .context("GitHub GraphQL API returned no repository")
+}
+
+// A tag message without the signature git appends to signed tags
+fn tag_message(message: &str) -> String {
+    let end = ["-----BEGIN PGP SIGNATURE-----", "-----BEGIN SSH SIGNATURE-----"]
+        .iter()
+        .filter_map(|marker| message.find(marker))
+        .min()
+        .unwrap_or(message.len());
+    message[..end].trim().to_string()
+}
+
+impl ReleaseSource for GitHubSource {
+    async fn fetch(&self) -> Result<Vec<Release>> {
+        let client = reqwest::Client::new();
+        let headers = github_headers(self.token.as_deref())?;
+
+        if self.from_tags {
+            return self.fetch_tags(&client, &headers).await;
+        }
+
+        if let Some(range) = &self.generate {
+            return self.generate_notes(&client, &headers, range).await;
+        }
+
+        if self.use_graphql {
+            return self.fetch_graphql(&client, &headers).await;
+        }
+
+        let url = format!("{}/releases?per_page={}", self.repo_url(), GITHUB_PAGE_SIZE);
+
+        // With a cache, ask the API to skip the body if nothing changed since the cached ETag
+        let cache = self.cache_dir.as_deref().map(|dir| ResponseCache::new(dir, &self.owner, &self.repo));
+        let cached = cache.as_ref().and_then(ResponseCache::load);
+        let mut request_headers = headers.clone();
+        if let Some(cached) = &cached {
+            debug!("Sending If-None-Match: {}", cached.etag);
+            request_headers.insert(IF_NONE_MATCH, HeaderValue::from_str(&cached.etag)?);
+        }
+
+        info!("Making API request to: {}", url);
+        let response = github_get_response(&client, &url, &request_headers, &self.retry, &self.requests).await?;
+        let response_text = match (response.body, cached) {
+            (Some(body), _) => {
+                if let (Some(cache), Some(etag)) = (&cache, response.etag) {
+                    cache.store(&etag, &body);
+                }
+                body
+            }
+            (None, Some(cached)) => {
+                info!("Releases for {}/{} unchanged, using cached response", self.owner, self.repo);
+                cached.body
+            }
+            (None, None) => return Err(anyhow::anyhow!("GitHub API returned 304 Not Modified without a cached response")),
+        };
+
+        if self.verbose {
+            debug!("First 500 characters of response: {}",
+                if response_text.len() > 500 {
+                    &response_text[..500]
+                } else {
+                    &response_text
+                }
+            );
+        }
+
+        // Parse the JSON response
+        let mut releases: Vec<Release> = serde_json::from_str(&response_text)
+            .context("Failed to parse GitHub API response")?;
+
+        debug!("Parsed {} releases from API response", releases.len());
+        self.progress.fetched_page(1, releases.len());
+
+        // The first page holds the newest releases; older tags the selection names need more pages
+        let mut page = 1;
+        let mut page_len = releases.len();
+        while page_len == GITHUB_PAGE_SIZE {
+            let missing = missing_tags(&self.wanted_tags, self.tag_prefix.as_deref(), &releases);
+            if missing.is_empty() {
+                break;
+            }
+            
+            page += 1;
+            // The progress bar already counts pages
+            let level = if self.progress.is_enabled() { log::Level::Debug } else { log::Level::Info };
+            log::log!(level, "Tags {:?} are older than the {} releases fetched so far, fetching page {}", missing, releases.len(), page);
+            let url = format!("{}/releases?per_page={}&page={}", self.repo_url(), GITHUB_PAGE_SIZE, page);
+            let response_text = github_get(&client, &url, &headers, &self.retry, &self.requests).await?;
+            let older: Vec<Release> = serde_json::from_str(&response_text)
+                .with_context(|| format!("Failed to parse GitHub API response for page {}", page))?;
+            page_len = older.len();
+            releases.extend(older);
+            self.progress.fetched_page(page, releases.len());
+        }
+
+        if self.fetch_full_bodies {
+            self.fetch_full_bodies(&client, &headers, &mut releases).await?;
+        }
+
+        Ok(releases)
+    }
+}
+
+pub fn github_headers(token: Option<&str>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("github-release-notes-aggregator"));
+
+    if let Some(token) = token {
+        debug!("Using GitHub personal access token for authentication");
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("token {}", token))?,
+        );
+    } else {
+        debug!("No GitHub token provided, using unauthenticated requests");
+    }
+
+    Ok(headers)
+}
+
+/// How many times, and how patiently, transient request failures are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff (base, 2x base, 4x base, ...) plus up to one base delay of jitter
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let backoff = self.base_delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let base_nanos = self.base_delay.as_nanos().max(1) as u64;
+        let jitter = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.subsec_nanos() as u64 % base_nanos);
+        backoff + Duration::from_nanos(jitter)
+    }
+}
+
+// Outcome of a single failed request attempt
+enum RequestError {
+    /// Worth another try, after the server-requested delay if there was one
+    Retryable(anyhow::Error, Option<Duration>),
+    Fatal(anyhow::Error),
+}
+
+/// A successful API response; `body` is `None` when the server answered 304 Not Modified
+pub struct ApiResponse {
+    pub body: Option<String>,
+    pub etag: Option<String>,
+    /// URL of the next page of a paginated list, if there is one
+    pub next_page: Option<String>,
+}
+
+// Tags the selection names, which decide how many pages of releases are fetched; `aggregate` has already stripped any `--tag-prefix`
+fn wanted_tags(config: &AggregatorConfig) -> Vec<String> {
+    config
+        .start_tag
+        .iter()
+        .chain(&config.end_tag)
+        .cloned()
+        .chain(config.versions.iter().flat_map(|versions| versions.split(',').map(|tag| tag.trim().to_string())))
+        .collect()
+}
+
+// Wanted tags none of the releases fetched so far match, compared the way the selection resolves them
+fn missing_tags<'a>(wanted_tags: &'a [String], tag_prefix: Option<&str>, releases: &[Release]) -> Vec<&'a String> {
+    // Only tags with the prefix are selectable, and they're matched without it
+    let versions: Vec<&str> = releases
+        .iter()
+        .filter_map(|release| match tag_prefix {
+            Some(prefix) => release.tag_name.strip_prefix(prefix),
+            None => Some(release.tag_name.as_str()),
+        })
+        .collect();
+    wanted_tags
+        .iter()
+        .filter(|tag| !versions.iter().any(|version| tag_matches(version, tag)))
+        .collect()
+}
+
+// The next page from a `Link: <...>; rel="next"` header (GitHub, Gitea), or GitLab's `X-Next-Page` number
+fn next_page_url(headers: &HeaderMap, url: &str) -> Option<String> {
+    let link = headers.get(LINK).and_then(|value| value.to_str().ok()).and_then(|link| {
+        link.split(',').find_map(|part| {
+            let (target, params) = part.split_once(';')?;
+            params
+                .split(';')
+                .any(|param| param.trim() == "rel=\"next\"")
+                .then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+        })
+    });
+    // The target may be relative to the request
+    if let Some(link) = link {
+        return reqwest::Url::parse(url).and_then(|base| base.join(link)).ok().map(String::from);
+    }
+    
+    let page = headers.get("x-next-page")?.to_str().ok()?.trim();
+    if page.is_empty() {
+        return None;
+    }
+    let mut next = reqwest::Url::parse(url).ok()?;
+    let query: Vec<(String, String)> = next
+        .query_pairs()
+        .filter(|(name, _)| name != "page")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    next.query_pairs_mut().clear().extend_pairs(query).append_pair("page", page);
+    Some(next.to_string())
+}
+
+// Send a GET request to the GitHub API and return the response body, retrying transient failures
+pub async fn github_get(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    retry: &RetryPolicy,
+    requests: &RequestCounter,
+) -> Result<String> {
+    github_get_response(client, url, headers, retry, requests)
+        .await?
+        .body
+        .context("GitHub API returned 304 Not Modified for an unconditional request")
+}
+
+// Like `github_get`, but keeping the ETag and passing 304 Not Modified through
+pub async fn github_get_response(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    retry: &RetryPolicy,
+    requests: &RequestCounter,
+) -> Result<ApiResponse> {
+    send_with_retries(url, retry, requests, || github_send_once(client.get(url), "GET", url, headers)).await
+}
+
+// POST `body` as JSON, retrying like `github_get`
+pub async fn github_post<T: Serialize>(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    body: &T,
+    retry: &RetryPolicy,
+    requests: &RequestCounter,
+) -> Result<String> {
+    send_with_retries(url, retry, requests, || github_send_once(client.post(url).json(body), "POST", url, headers))
+        .await?
+        .body
+        .context("GitHub API returned 304 Not Modified for a POST request")
+}
+
+// Send a request until it succeeds, a fatal error comes back or the retries run out
+async fn send_with_retries<F, Fut>(url: &str, retry: &RetryPolicy, requests: &RequestCounter, send: F) -> Result<ApiResponse>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = std::result::Result<ApiResponse, RequestError>>,
+{
+    let mut attempt = 0;
+    loop {
+        requests.record();
+        match send().await {
+            Ok(response) => return Ok(response),
+            Err(RequestError::Retryable(err, retry_after)) if attempt < retry.max_retries => {
+                attempt += 1;
+                let delay = retry_after.unwrap_or_else(|| retry.delay_for(attempt));
+                warn!(
+                    "Request to {} failed ({}), retry {}/{} in {:?}",
+                    url, err, attempt, retry.max_retries, delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(RequestError::Retryable(err, _)) | Err(RequestError::Fatal(err)) => return Err(err),
+        }
+    }
+}
+
+async fn github_send_once(
+    request: reqwest::RequestBuilder,
+    method: &str,
+    url: &str,
+    headers: &HeaderMap,
+) -> std::result::Result<ApiResponse, RequestError> {
+    // Log request details before sending
+    debug!("API Request: {} {}", method, url);
+    debug!("Headers: {:?}", headers);
+
+    // Transport errors (connection resets, timeouts) are always worth another try
+    let response = request
+        .headers(headers.clone())
+        .send()
+        .await
+        .context("Failed to send request to GitHub API")
+        .map_err(|err| RequestError::Retryable(err, None))?;
+
+    // Log response details
+    debug!("API Response: Status: {}", response.status());
+    debug!("Response headers: {:?}", response.headers());
+
+    let rate_limit = parse_rate_limit(response.headers());
+    if let Some(rate_limit) = &rate_limit {
+        debug!("Rate limit: {}/{} remaining, resets at {}", rate_limit.remaining, rate_limit.limit, rate_limit.reset);
+        if rate_limit.remaining == 0 {
+            warn!("GitHub API rate limit exhausted, resets at unix time {}", rate_limit.reset);
+        }
+    }
+
+    let retry_after = parse_retry_after(response.headers())
+        .map(|retry_after| retry_after.delay_from(chrono::Utc::now()));
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let next_page = next_page_url(response.headers(), url);
+
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        debug!("Resource not modified since ETag");
+        return Ok(ApiResponse { body: None, etag, next_page: None });
+    }
+
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_else(|_| "Unable to read response body".to_string());
+        error!("GitHub API error: Status={}, Body={}", status, body);
+        let err = github_error(status, &body, headers.contains_key(AUTHORIZATION));
+
+        // Secondary (abuse) rate limits answer 403/429 with a Retry-After header
+        let retry_after = retry_after.filter(|_| {
+            matches!(status, StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS)
+        });
+        if let Some(delay) = retry_after {
+            warn!("GitHub API asked to retry after {:?}", delay);
+        }
+
+        let rate_limited = status == StatusCode::FORBIDDEN
+            && rate_limit.is_some_and(|rate_limit| rate_limit.remaining == 0);
+        let retryable = matches!(
+            status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        ) || rate_limited
+            || retry_after.is_some();
+
+        return Err(if retryable { RequestError::Retryable(err, retry_after) } else { RequestError::Fatal(err) });
+    }
+
+    let response_text = response
+        .text()
+        .await
+        .context("Failed to get response text")
+        .map_err(|err| RequestError::Retryable(err, None))?;
+    debug!("Response body length: {} bytes", response_text.len());
+    Ok(ApiResponse { body: Some(response_text), etag, next_page })
+}
+
+// GET a page from another forge's API (`forge` names it in messages), retrying transient failures like `github_get`
+async fn forge_get(
+    client: &reqwest::Client,
+    forge: &str,
+    url: &str,
+    headers: &HeaderMap,
+    retry: &RetryPolicy,
+    requests: &RequestCounter,
+) -> Result<ApiResponse> {
+    send_with_retries(url, retry, requests, || forge_send_once(client.get(url), forge, url, headers)).await
+}
+
+async fn forge_send_once(
+    request: reqwest::RequestBuilder,
+    forge: &str,
+    url: &str,
+    headers: &HeaderMap,
+) -> std::result::Result<ApiResponse, RequestError> {
+    debug!("API Request: GET {}", url);
+
+    let response = request
+        .headers(headers.clone())
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {} API", forge))
+        .map_err(|err| RequestError::Retryable(err, None))?;
+
+    debug!("API Response: Status: {}", response.status());
+    let retry_after = parse_retry_after(response.headers())
+        .map(|retry_after| retry_after.delay_from(chrono::Utc::now()));
+    let next_page = next_page_url(response.headers(), url);
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_else(|_| "Unable to read response body".to_string());
+        error!("{} API error: Status={}, Body={}", forge, status, body);
+        let err = ApiError::new(status, format!("{} API returned error status: {}, Body: {}", forge, status, body)).into();
+
+        // Both answer rate limiting with 429, and may say when to come back
+        let retryable = matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        );
+        return Err(if retryable { RequestError::Retryable(err, retry_after) } else { RequestError::Fatal(err) });
+    }
+
+    let response_text = response
+        .text()
+        .await
+        .context("Failed to get response text")
+        .map_err(|err| RequestError::Retryable(err, None))?;
+    Ok(ApiResponse { body: Some(response_text), etag: None, next_page })
+}
+
+/// A release host answered a request with an error status
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    message: String,
+    /// A 404 that most likely means the token can't see a private repository
+    access_denied: bool,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: String) -> Self {
+        ApiError { status, message, access_denied: false }
+    }
+
+    /// The credentials were missing, rejected or lacked access, or the rate limit ran out
+    pub fn is_auth_failure(&self) -> bool {
+        self.access_denied
+            || matches!(
+                self.status,
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+            )
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+// Explain a failed request. GitHub hides private repositories a token can't see behind a
+// plain 404, so an authenticated "Not Found" most likely means missing access, not a typo
+fn github_error(status: StatusCode, body: &str, authenticated: bool) -> anyhow::Error {
+    let not_found = serde_json::from_str::<GitHubError>(body).is_ok_and(|err| err.message == "Not Found");
+    if status == StatusCode::NOT_FOUND && authenticated && not_found {
+        return ApiError {
+            access_denied: true,
+            ..ApiError::new(
+                status,
+                "GitHub API returned 404 Not Found. If the repository is private, the token may not have access to it: \
+                 a fine-grained token must be granted this repository, a classic token needs the repo scope, \
+                 and organizations using SAML SSO require the token to be authorized for SSO"
+                    .to_string(),
+            )
+        }
+        .into();
+    }
+    
+    ApiError::new(status, format!("GitHub API returned error status: {}, Body: {}", status, body)).into()
+}
+
+/// Body of `GET /rate_limit`
+#[derive(Debug, Deserialize)]
+pub struct RateLimitResponse {
+    pub resources: RateLimitResources,
+}
+
+/// Quotas per API; releases are fetched through the core REST quota
+#[derive(Debug, Deserialize)]
+pub struct RateLimitResources {
+    pub core: RateLimit,
+}
+
+// Parse the core REST quota out of a `/rate_limit` response body
+pub fn parse_rate_limit_response(body: &str) -> Result<RateLimit> {
+    let response: RateLimitResponse =
+        serde_json::from_str(body).context("Failed to parse GitHub rate limit response")?;
+    Ok(response.resources.core)
+}
+
+// Ask the GitHub API for the current quota; the request itself doesn't count against it
+pub async fn fetch_rate_limit(api_url: &str, token: Option<&str>, retry: &RetryPolicy, requests: &RequestCounter) -> Result<RateLimit> {
+    let client = reqwest::Client::new();
+    let headers = github_headers(token)?;
+    let url = format!("{}/rate_limit", api_url.trim_end_matches('/'));
+    
+    let body = github_get(&client, &url, &headers, retry, requests).await?;
+    parse_rate_limit_response(&body)
+}
+
+/// Body of `POST /gists`
+#[derive(Debug, Serialize)]
+struct GistRequest<'a> {
+    description: &'a str,
+    public: bool,
+    files: HashMap<&'a str, GistFile<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct GistFile<'a> {
+    content: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct GistResponse {
+    html_url: String,
+}
+
+// Create a secret gist holding `content` as `file_name` and return its URL. Not retried, since
+// repeating a POST that timed out after reaching GitHub would create a second gist
+pub async fn create_gist(api_url: &str, token: &str, file_name: &str, description: &str, content: &str) -> Result<String> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/gists", api_url.trim_end_matches('/'));
+    let request = GistRequest {
+        description,
+        public: false,
+        files: HashMap::from([(file_name, GistFile { content })]),
+    };
+    
+    debug!("API Request: POST {}", url);
+    let response = client
+        .post(&url)
+        .headers(github_headers(Some(token))?)
+        .json(&request)
+        .send()
+        .await
+        .context("Failed to send gist to GitHub API")?;
+    
+    let rate_limit = parse_rate_limit(response.headers());
+    let quota = rate_limit
+        .as_ref()
+        .map(|rate_limit| format!(" (rate limit: {}/{} remaining)", rate_limit.remaining, rate_limit.limit))
+        .unwrap_or_default();
+    debug!("Gist response: Status: {}{}", response.status(), quota);
+    
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<GitHubError>(&body).map_or(body, |err| err.message);
+        // GitHub answers 404 rather than 403 when the token lacks the gist scope
+        let hint = if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND) {
+            "; check that the token has the gist scope"
+        } else {
+            ""
+        };
+        return Err(anyhow::anyhow!("Failed to create gist: GitHub API returned {}: {}{}{}", status, message, quota, hint));
+    }
+    
+    let body = response.text().await.context("Failed to read gist response")?;
+    let gist: GistResponse = serde_json::from_str(&body).context("Failed to parse gist response")?;
+    info!("Created gist {}{}", gist.html_url, quota);
+    Ok(gist.html_url)
+}
+
+/// Release list response saved alongside the ETag it was served with
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+/// On-disk cache of release list responses, one file per repository
+pub struct ResponseCache {
+    path: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: &Path, owner: &str, repo: &str) -> Self {
+        ResponseCache {
+            path: dir.join(format!("{}__{}.json", owner.replace('/', "__"), repo)),
+        }
+    }
+
+    // A missing or unreadable cache entry just means a full request
+    pub fn load(&self) -> Option<CachedResponse> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(cached) => Some(cached),
+            Err(err) => {
+                warn!("Ignoring corrupt cache file {}: {}", self.path.display(), err);
+                None
+            }
+        }
+    }
+
+    // Failing to write the cache only costs a full request next time, so it isn't fatal
+    pub fn store(&self, etag: &str, body: &str) {
+        let cached = CachedResponse {
+            etag: etag.to_string(),
+            body: body.to_string(),
+        };
+        let result = (|| -> Result<()> {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&self.path, serde_json::to_string(&cached)?)?;
+            Ok(())
+        })();
+
+        match result {
+            Ok(()) => debug!("Cached response in {}", self.path.display()),
+            Err(err) => warn!("Failed to write cache file {}: {}", self.path.display(), err),
+        }
+    }
+}
+
+// GitHub caps release bodies at 125,000 characters
+pub const LIST_BODY_LIMIT: usize = 125_000;
+
+// The list endpoint can omit or cut off long bodies; anything missing or at the size cap is refetched
+pub fn is_body_truncated(release: &Release) -> bool {
+    match &release.body {
+        Some(body) => body.chars().count() >= LIST_BODY_LIMIT,
+        None => true,
+    }
+}
+
+/// Releases read from a JSON file in the GitHub API format
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: &Path) -> Self {
+        FileSource {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl ReleaseSource for FileSource {
+    async fn fetch(&self) -> Result<Vec<Release>> {
+        info!("Reading releases from file: {:?}", self.path);
+
+        let contents = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read releases file: {:?}", self.path))?;
+
+        let releases: Vec<Release> = serde_json::from_str(&contents)
+            .with_context(|| format!("Releases file {:?} is not a JSON array of GitHub releases", self.path))?;
+
+        debug!("Parsed {} releases from file", releases.len());
+        Ok(releases)
+    }
+}
+
+/// Releases from the GitLab `/projects/:id/releases` API
+pub struct GitLabSource {
+    base_url: String,
+    project: String,
+    token: Option<String>,
+    retry: RetryPolicy,
+    /// Tags the selection names; further pages are fetched until they have all been seen
+    wanted_tags: Vec<String>,
+    /// `--tag-prefix` the releases' tags carry and `wanted_tags` are named without
+    tag_prefix: Option<String>,
+    requests: RequestCounter,
+}
+
+/// Release as returned by the GitLab API
+#[derive(Debug, Deserialize)]
+pub struct GitLabRelease {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub released_at: String,
+    #[serde(default)]
+    pub upcoming_release: bool,
+}
+
+impl GitLabRelease {
+    // GitLab releases have no numeric id, so the caller supplies a stable one
+    pub fn into_release(self, id: u64) -> Release {
+        Release {
+            id,
+            tag_name: self.tag_name,
+            name: self.name,
+            body: self.description,
+            published_at: self.released_at,
+            // Upcoming releases are scheduled for the future, the closest analogue to a prerelease
+            prerelease: self.upcoming_release,
+            draft: false,
+            created_at: None,
+            assets: Vec::new(),
+            repo: String::new(),
+            label: None,
+            html_url: None,
+        }
+    }
+}
+
+impl GitLabSource {
+    pub fn from_config(config: &AggregatorConfig, owner: &str, repo: &str, requests: &RequestCounter) -> Self {
+        GitLabSource {
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "https://gitlab.com".to_string()),
+            project: format!("{}/{}", owner, repo),
+            token: config.token.clone(),
+            retry: RetryPolicy {
+                max_retries: config.max_retries,
+                ..RetryPolicy::default()
+            },
+            wanted_tags: wanted_tags(config),
+            tag_prefix: config.tag_prefix.clone(),
+            requests: requests.clone(),
+        }
+    }
+
+    fn releases_url(&self) -> String {
+        // The project path is passed as a single URL-encoded id
+        format!(
+            "{}/api/v4/projects/{}/releases?per_page=100",
+            self.base_url.trim_end_matches('/'),
+            self.project.replace('/', "%2F")
+        )
+    }
+}
+
+impl ReleaseSource for GitLabSource {
+    async fn fetch(&self) -> Result<Vec<Release>> {
+        let client = reqwest::Client::new();
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("github-release-notes-aggregator"));
+
+        if let Some(token) = &self.token {
+            debug!("Using GitLab access token for authentication");
+            headers.insert("PRIVATE-TOKEN", HeaderValue::from_str(token)?);
+        }
+
+        // Newest first, like GitHub; older pages are only needed for older tags the selection names
+        let mut url = self.releases_url();
+        let mut releases: Vec<Release> = Vec::new();
+        loop {
+            info!("Making API request to: {}", url);
+            let response = forge_get(&client, "GitLab", &url, &headers, &self.retry, &self.requests).await?;
+            let page: Vec<GitLabRelease> = serde_json::from_str(response.body.as_deref().unwrap_or_default())
+                .context("Failed to parse GitLab API response")?;
+            debug!("Parsed {} releases from GitLab API response", page.len());
+            // Ids number the releases across pages, newest first
+            let fetched = releases.len() as u64;
+            releases.extend(page.into_iter().zip(fetched + 1..).map(|(release, id)| release.into_release(id)));
+
+            match response.next_page {
+                Some(next) if !missing_tags(&self.wanted_tags, self.tag_prefix.as_deref(), &releases).is_empty() => url = next,
+                _ => break,
+            }
+        }
+
+        Ok(releases)
+    }
+}
+
+/// Releases from the Gitea (and Forgejo) `/repos/{owner}/{repo}/releases` API
+pub struct GiteaSource {
+    base_url: String,
+    owner: String,
+    repo: String,
+    token: Option<String>,
+    retry: RetryPolicy,
+    /// Tags the selection names; further pages are fetched until they have all been seen
+    wanted_tags: Vec<String>,
+    /// `--tag-prefix` the releases' tags carry and `wanted_tags` are named without
+    tag_prefix: Option<String>,
+    requests: RequestCounter,
+}
+
+/// Release as returned by the Gitea API
+#[derive(Debug, Deserialize)]
+pub struct GiteaRelease {
+    pub id: u64,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub published_at: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    pub created_at: Option<String>,
+    pub html_url: Option<String>,
+}
+
+impl GiteaRelease {
+    pub fn into_release(self) -> Release {
+        Release {
+            id: self.id,
+            tag_name: self.tag_name,
+            // Gitea sends an empty name rather than null when none was given
+            name: self.name.filter(|name| !name.is_empty()),
+            body: self.body,
+            published_at: self.published_at,
+            prerelease: self.prerelease,
+            draft: self.draft,
+            created_at: self.created_at,
+            assets: Vec::new(),
+            repo: String::new(),
+            label: None,
+            html_url: self.html_url,
+        }
+    }
+}
+
+impl GiteaSource {
+    // There is no public default instance, so the base URL is required
+    pub fn from_config(config: &AggregatorConfig, owner: &str, repo: &str, requests: &RequestCounter) -> Result<Self> {
+        let base_url = config
+            .base_url
+            .clone()
+            .context("--provider gitea needs --base-url pointing at the Gitea instance")?;
+        Ok(GiteaSource {
+            base_url,
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            token: config.token.clone(),
+            retry: RetryPolicy {
+                max_retries: config.max_retries,
+                ..RetryPolicy::default()
+            },
+            wanted_tags: wanted_tags(config),
+            tag_prefix: config.tag_prefix.clone(),
+            requests: requests.clone(),
+        })
+    }
+
+    fn releases_url(&self) -> String {
+        format!(
+            "{}/api/v1/repos/{}/{}/releases?limit=50",
+            self.base_url.trim_end_matches('/'),
+            self.owner,
+            self.repo
+        )
+    }
+}
+
+impl ReleaseSource for GiteaSource {
+    async fn fetch(&self) -> Result<Vec<Release>> {
+        let client = reqwest::Client::new();
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("github-release-notes-aggregator"));
+
+        if let Some(token) = &self.token {
+            debug!("Using Gitea access token for authentication");
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("token {}", token))?);
+        }
+
+        // Newest first, following the `Link` header while an older tag the selection names is missing
+        let mut url = self.releases_url();
+        let mut releases: Vec<Release> = Vec::new();
+        loop {
+            info!("Making API request to: {}", url);
+            let response = forge_get(&client, "Gitea", &url, &headers, &self.retry, &self.requests).await?;
+            let page: Vec<GiteaRelease> = serde_json::from_str(response.body.as_deref().unwrap_or_default())
+                .context("Failed to parse Gitea API response")?;
+            debug!("Parsed {} releases from Gitea API response", page.len());
+            releases.extend(page.into_iter().map(GiteaRelease::into_release));
+
+            match response.next_page {
+                Some(next) if !missing_tags(&self.wanted_tags, self.tag_prefix.as_deref(), &releases).is_empty() => url = next,
+                _ => break,
+            }
+        }
+
+        Ok(releases)
+    }
+}
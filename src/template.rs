@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::Tera;
+
+use crate::{group_items_by_version, has_multiple_repos, sort_section_names, source_label, ReleaseNoteItem, SectionOrder};
+
+/// Template reproducing the built-in Markdown layout, selected with `--template default`
+pub const DEFAULT_TEMPLATE: &str = include_str!("../templates/default.md.tera");
+
+/// Variables available to `--template` files
+#[derive(Debug, Serialize)]
+pub struct TemplateContext {
+    /// Sections in output order (`--section-order` first, then alphabetical, `--uncategorized-label` last)
+    pub sections: Vec<TemplateSection>,
+}
+
+/// One `##` section of the aggregated notes
+#[derive(Debug, Serialize)]
+pub struct TemplateSection {
+    /// Section heading as written in the release notes
+    pub name: String,
+    /// Releases contributing to the section, newest first
+    pub versions: Vec<TemplateVersion>,
+}
+
+/// The items a single release contributed to a section
+#[derive(Debug, Serialize)]
+pub struct TemplateVersion {
+    /// Tag name of the release
+    pub version: String,
+    /// `owner/repo` the release came from
+    pub repo: String,
+    /// Tag name, prefixed with the repository when several are aggregated
+    pub label: String,
+    /// Release name when it differs from the tag, otherwise null
+    pub name: Option<String>,
+    /// Publication date as `YYYY-MM-DD`
+    pub date: String,
+    /// Raw Markdown lines, in release order
+    pub items: Vec<String>,
+}
+
+impl TemplateContext {
+    pub fn from_merged_sections(merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>, section_order: &SectionOrder) -> Self {
+        let multiple_repos = has_multiple_repos(merged_sections.values().flatten().map(|item| item.repo.as_str()));
+        
+        let sections = sort_section_names(merged_sections, section_order)
+            .into_iter()
+            .map(|name| TemplateSection {
+                name: name.clone(),
+                versions: group_items_by_version(&merged_sections[name])
+                    .into_iter()
+                    .map(|items| {
+                        let first = items[0];
+                        TemplateVersion {
+                            version: first.version.clone(),
+                            repo: first.repo.clone(),
+                            label: source_label(&first.repo, &first.version, multiple_repos),
+                            name: first.name.clone(),
+                            date: first.date.format("%Y-%m-%d").to_string(),
+                            items: items.iter().map(|item| item.content.clone()).collect(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        
+        TemplateContext { sections }
+    }
+}
+
+/// Render merged sections through a Tera template
+pub fn render_template(
+    template: &str,
+    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
+    section_order: &SectionOrder,
+) -> Result<String> {
+    let context = TemplateContext::from_merged_sections(merged_sections, section_order);
+    debug!("Rendering template with {} sections", context.sections.len());
+    
+    let context = tera::Context::from_serialize(&context).context("Failed to build template context")?;
+    // Output is Markdown, so nothing is HTML-escaped
+    let rendered = Tera::one_off(template, &context, false).context("Failed to render template")?;
+    
+    info!("Rendered template output: {} bytes", rendered.len());
+    Ok(rendered)
+}
+
+/// Load a template file and render merged sections through it
+///
+/// A path of `default` that doesn't name an existing file selects the shipped [`DEFAULT_TEMPLATE`].
+pub fn render_template_file(
+    path: &Path,
+    merged_sections: &HashMap<String, Vec<ReleaseNoteItem>>,
+    section_order: &SectionOrder,
+) -> Result<String> {
+    if path == Path::new("default") && !path.exists() {
+        return render_template(DEFAULT_TEMPLATE, merged_sections, section_order);
+    }
+    let template = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read template {}", path.display()))?;
+    render_template(&template, merged_sections, section_order)
+}
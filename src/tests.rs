@@ -1,12 +1,10 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::NaiveDate;
-    use std::collections::HashMap;
+use super::*;
+use chrono::NaiveDate;
+use std::collections::HashMap;
 
-    #[test]
-    fn test_parse_release_notes() {
-        let example_release_notes = r#"# Features
+#[test]
+fn test_parse_release_notes() {
+    let example_release_notes = r#"# Features
 
 - Added new feature 1
 - Added new feature 2
@@ -20,144 +18,3558 @@ mod tests {
 
 - Updated docs"#;
 
-        let sections = parse_release_notes(example_release_notes);
-        
-        assert_eq!(sections.len(), 3);
-        assert!(sections.contains_key("Features"));
-        assert!(sections.contains_key("Bug Fixes"));
-        assert!(sections.contains_key("Documentation"));
-        
-        assert_eq!(sections["Features"].len(), 2);
-        assert_eq!(sections["Bug Fixes"].len(), 2);
-        assert_eq!(sections["Documentation"].len(), 1);
-        
-        assert_eq!(sections["Features"][0], "- Added new feature 1");
-        assert_eq!(sections["Features"][1], "- Added new feature 2");
-        assert_eq!(sections["Bug Fixes"][0], "- Fixed bug 1");
-        assert_eq!(sections["Bug Fixes"][1], "- Fixed bug 2");
-        assert_eq!(sections["Documentation"][0], "- Updated docs");
-    }
-
-    #[test]
-    fn test_merge_release_notes() {
-        // Create mock releases
-        let releases = vec![
-            Release {
-                id: 1,
-                tag_name: "v1.0.0".to_string(),
-                name: Some("Version 1.0.0".to_string()),
-                body: Some(r#"# Features
+    let (sections, _) = parse_release_notes(example_release_notes, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+    
+    assert_eq!(sections.len(), 3);
+    assert!(sections.contains_key("Features"));
+    assert!(sections.contains_key("Bug Fixes"));
+    assert!(sections.contains_key("Documentation"));
+    
+    assert_eq!(sections["Features"].len(), 2);
+    assert_eq!(sections["Bug Fixes"].len(), 2);
+    assert_eq!(sections["Documentation"].len(), 1);
+    
+    assert_eq!(sections["Features"][0], "- Added new feature 1");
+    assert_eq!(sections["Features"][1], "- Added new feature 2");
+    assert_eq!(sections["Bug Fixes"][0], "- Fixed bug 1");
+    assert_eq!(sections["Bug Fixes"][1], "- Fixed bug 2");
+    assert_eq!(sections["Documentation"][0], "- Updated docs");
+}
+
+#[test]
+fn test_merge_release_notes() {
+    // Create mock releases
+    let releases = vec![
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            name: Some("Version 1.0.0".to_string()),
+            body: Some(r#"# Features
 - Feature A v1
 - Feature B v1
 
 # Bug Fixes
 - Bug Fix A v1"#.to_string()),
-                published_at: "2023-01-01T00:00:00Z".to_string(),
-                prerelease: false,
-            },
-            Release {
-                id: 2,
-                tag_name: "v2.0.0".to_string(),
-                name: Some("Version 2.0.0".to_string()),
-                body: Some(r#"# Features
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+        Release {
+            id: 2,
+            tag_name: "v2.0.0".to_string(),
+            name: Some("Version 2.0.0".to_string()),
+            body: Some(r#"# Features
 - Feature A v2
 - Feature C v2
 
 # Performance
 - Performance improvement v2"#.to_string()),
-                published_at: "2023-02-01T00:00:00Z".to_string(),
-                prerelease: false,
-            },
-        ];
-
-        let merged_sections = merge_release_notes(&releases);
-        
-        // Check that we have all expected sections
-        assert_eq!(merged_sections.len(), 3);
-        assert!(merged_sections.contains_key("Features"));
-        assert!(merged_sections.contains_key("Bug Fixes"));
-        assert!(merged_sections.contains_key("Performance"));
-        
-        // Check that the Features section has entries from both releases
-        assert_eq!(merged_sections["Features"].len(), 4);
-        
-        // Check that versions are correctly assigned
-        let v1_features = merged_sections["Features"]
-            .iter()
-            .filter(|item| item.version == "v1.0.0")
-            .count();
-        
-        let v2_features = merged_sections["Features"]
-            .iter()
-            .filter(|item| item.version == "v2.0.0")
-            .count();
-        
-        assert_eq!(v1_features, 2);
-        assert_eq!(v2_features, 2);
-        
-        // Check that dates are correctly parsed
-        let jan_1_2023 = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
-        let feb_1_2023 = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
-        
-        for item in &merged_sections["Features"] {
-            if item.version == "v1.0.0" {
-                assert_eq!(item.date, jan_1_2023);
-            } else if item.version == "v2.0.0" {
-                assert_eq!(item.date, feb_1_2023);
-            }
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+    ];
+
+    let merged_sections = merge_release_notes(&releases, &MergeOptions::default());
+    
+    // Check that we have all expected sections
+    assert_eq!(merged_sections.len(), 3);
+    assert!(merged_sections.contains_key("Features"));
+    assert!(merged_sections.contains_key("Bug Fixes"));
+    assert!(merged_sections.contains_key("Performance"));
+    
+    // Check that the Features section has entries from both releases
+    assert_eq!(merged_sections["Features"].len(), 4);
+    
+    // Check that versions are correctly assigned
+    let v1_features = merged_sections["Features"]
+        .iter()
+        .filter(|item| item.version == "v1.0.0")
+        .count();
+    
+    let v2_features = merged_sections["Features"]
+        .iter()
+        .filter(|item| item.version == "v2.0.0")
+        .count();
+    
+    assert_eq!(v1_features, 2);
+    assert_eq!(v2_features, 2);
+    
+    // Check that dates are correctly parsed
+    let jan_1_2023 = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+    let feb_1_2023 = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
+    
+    for item in &merged_sections["Features"] {
+        if item.version == "v1.0.0" {
+            assert_eq!(item.date, jan_1_2023);
+        } else if item.version == "v2.0.0" {
+            assert_eq!(item.date, feb_1_2023);
         }
     }
+}
+
+#[test]
+fn test_generate_markdown() {
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    
+    // Add some test data
+    let date1 = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+    let date2 = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
+    
+    let features = vec![
+        ReleaseNoteItem {
+            content: "- Feature A v1".to_string(),
+            version: "v1.0.0".to_string(),
+            date: date1,
+            ..Default::default()
+        },
+        ReleaseNoteItem {
+            content: "- Feature B v1".to_string(),
+            version: "v1.0.0".to_string(),
+            date: date1,
+            ..Default::default()
+        },
+        ReleaseNoteItem {
+            content: "- Feature A v2".to_string(),
+            version: "v2.0.0".to_string(),
+            date: date2,
+            ..Default::default()
+        },
+    ];
+    
+    let bugs = vec![
+        ReleaseNoteItem {
+            content: "- Bug Fix A v1".to_string(),
+            version: "v1.0.0".to_string(),
+            date: date1,
+            ..Default::default()
+        },
+    ];
+    
+    merged_sections.insert("Features".to_string(), features);
+    merged_sections.insert("Bug Fixes".to_string(), bugs);
+    
+    let markdown = generate_markdown(&merged_sections, &MarkdownOptions::default());
+    
+    // Check that the markdown contains all expected sections and versions
+    assert!(markdown.contains("# Aggregated Release Notes"));
+    assert!(markdown.contains("## Bug Fixes"));
+    assert!(markdown.contains("## Features"));
+    assert!(markdown.contains("### v1.0.0 (2023-01-01)"));
+    assert!(markdown.contains("### v2.0.0 (2023-02-01)"));
+    
+    // Check that content items are included
+    assert!(markdown.contains("- Feature A v1"));
+    assert!(markdown.contains("- Feature B v1"));
+    assert!(markdown.contains("- Feature A v2"));
+    assert!(markdown.contains("- Bug Fix A v1"));
+}
+
+#[test]
+fn test_default_template_matches_generate_markdown() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            body: Some("# Features\n- Feature B\n# Bug Fixes\n- Fix B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("# Features\n- Feature A\n- Feature A2".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+
+    assert_eq!(
+        template::render_template(template::DEFAULT_TEMPLATE, &merged, &SectionOrder::default()).unwrap(),
+        generate_markdown(&merged, &MarkdownOptions::default())
+    );
+
+    // A minimal custom layout sees every section, version and item
+    let custom = "{% for section in sections %}{{ section.name }}:\
+                  {% for version in section.versions %} {{ version.version }}@{{ version.date }}\
+                  [{{ version.items | join(sep=\"; \") }}]{% endfor %}\n{% endfor %}";
+    assert_eq!(
+        template::render_template(custom, &merged, &SectionOrder::default()).unwrap(),
+        "Bug Fixes: v1.1.0@2023-02-01[- Fix B]\n\
+         Features: v1.1.0@2023-02-01[- Feature B] v1.0.0@2023-01-01[- Feature A; - Feature A2]\n"
+    );
+
+    assert!(template::render_template("{% for %}", &merged, &SectionOrder::default()).is_err());
+    
+    // `--template default` picks the shipped template without needing a copy on disk
+    assert_eq!(
+        template::render_template_file(Path::new("default"), &merged, &SectionOrder::default()).unwrap(),
+        generate_markdown(&merged, &MarkdownOptions::default())
+    );
+    assert!(template::render_template_file(Path::new("missing.tera"), &merged, &SectionOrder::default()).is_err());
+}
+
+#[test]
+fn test_section_order() {
+    let merged: HashMap<String, Vec<ReleaseNoteItem>> = ["Uncategorized", "Documentation", "Bug Fixes", "Chores", "Features"]
+        .iter()
+        .map(|name| (name.to_string(), Vec::new()))
+        .collect();
+
+    assert_eq!(
+        sort_section_names(&merged, &SectionOrder::default()),
+        vec!["Bug Fixes", "Chores", "Documentation", "Features", "Uncategorized"]
+    );
+
+    // Listed sections lead in the given order, matched case-insensitively; Uncategorized stays last
+    let order = parse_section_order("features, BUG FIXES, uncategorized, Missing");
+    assert_eq!(
+        sort_section_names(&merged, &SectionOrder::new(order, DEFAULT_UNCATEGORIZED_LABEL)),
+        vec!["Features", "Bug Fixes", "Chores", "Documentation", "Uncategorized"]
+    );
+}
+
+#[test]
+fn test_version_heading_includes_release_name() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            name: Some("Spring Release".to_string()),
+            body: Some("# Features\n- Feature B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            name: Some("v1.0.0".to_string()),
+            body: Some("# Features\n- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+
+    // A name that only repeats the tag is left out
+    let markdown = generate_markdown(&merged, &MarkdownOptions::default());
+    assert!(markdown.contains("### v1.1.0 \u{2014} \"Spring Release\" (2023-02-01)\n\n- Feature B\n"));
+    assert!(markdown.contains("### v1.0.0 (2023-01-01)\n\n- Feature A\n"));
+
+    assert_eq!(
+        template::render_template(template::DEFAULT_TEMPLATE, &merged, &SectionOrder::default()).unwrap(),
+        markdown
+    );
+}
+
+#[test]
+fn test_generate_markdown_grouped_by_release() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v2.0.0".to_string(),
+            body: Some("# Features\n- Feature B\n# Bug Fixes\n- Fix B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("# Features\n- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    let options = MarkdownOptions {
+        group_by: GroupBy::Release,
+        ..Default::default()
+    };
+
+    // Each release lists only the sections it contributed to
+    assert_eq!(
+        generate_markdown(&merge_release_notes(&releases, &MergeOptions::default()), &options),
+        "# Aggregated Release Notes\n\n\
+         ## v2.0.0 (2023-02-01)\n\n### Bug Fixes\n\n- Fix B\n\n### Features\n\n- Feature B\n\n\
+         ## v1.0.0 (2023-01-01)\n\n### Features\n\n- Feature A\n\n"
+    );
+}
+
+#[test]
+fn test_generate_json() {
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    merged_sections.insert(
+        "Features".to_string(),
+        vec![ReleaseNoteItem {
+            content: "- Feature A v1".to_string(),
+            version: "v1.0.0".to_string(),
+            date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+            ..Default::default()
+        }],
+    );
+
+    let json = generate_json(&merged_sections).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    let item = &value["Features"][0];
+    assert_eq!(item["content"], "- Feature A v1");
+    assert_eq!(item["version"], "v1.0.0");
+    assert_eq!(item["date"], "2023-01-01");
+}
+
+#[test]
+fn test_generate_yaml_and_toml() {
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    merged_sections.insert(
+        "Bug Fixes".to_string(),
+        vec![ReleaseNoteItem {
+            content: "- Fix \"quoted\": crash".to_string(),
+            version: "v1.1.0".to_string(),
+            date: NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            repo: "octo/example".to_string(),
+            ..Default::default()
+        }],
+    );
+
+    // Dates come back as plain strings in both formats
+    let yaml = generate_yaml(&merged_sections).unwrap();
+    let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+    let item = &value["Bug Fixes"][0];
+    assert_eq!(item["content"].as_str(), Some("- Fix \"quoted\": crash"));
+    assert_eq!(item["version"].as_str(), Some("v1.1.0"));
+    assert_eq!(item["date"].as_str(), Some("2023-02-01"));
+
+    let toml = generate_toml(&merged_sections).unwrap();
+    let value: toml::Value = toml::from_str(&toml).unwrap();
+    let item = &value["Bug Fixes"][0];
+    assert_eq!(item["content"].as_str(), Some("- Fix \"quoted\": crash"));
+    assert_eq!(item["repo"].as_str(), Some("octo/example"));
+    assert_eq!(item["date"].as_str(), Some("2023-02-01"));
+}
+
+#[test]
+fn test_generate_csv() {
+    let item = |content: &str, version: &str, day: u32| ReleaseNoteItem {
+        content: content.to_string(),
+        version: version.to_string(),
+        date: NaiveDate::from_ymd_opt(2023, 1, day).unwrap(),
+        ..Default::default()
+    };
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    merged_sections.insert(
+        "Features".to_string(),
+        vec![item("- Export to CSV, JSON and \"YAML\"", "v1.1.0", 2), item("- Feature A", "v1.0.0", 1)],
+    );
+    merged_sections.insert("Bug Fixes".to_string(), vec![item("- Fix B", "v1.1.0", 2)]);
+    
+    // Fields with commas or quotes are quoted, with embedded quotes doubled
+    let csv = generate_csv(&merged_sections, &SectionOrder::default()).unwrap();
+    assert_eq!(
+        csv,
+        "section,version,date,content\n\
+         Bug Fixes,v1.1.0,2023-01-02,- Fix B\n\
+         Features,v1.1.0,2023-01-02,\"- Export to CSV, JSON and \"\"YAML\"\"\"\n\
+         Features,v1.0.0,2023-01-01,- Feature A\n"
+    );
+}
+
+#[test]
+fn test_json_log_line() {
+    let line = logging::json_log_line(
+        "2023-01-01T00:00:00.000Z",
+        log::Level::Warn,
+        "ghnotes::sources",
+        "Request failed: \"timeout\"",
+        Some("octo/example"),
+    );
+    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(value["timestamp"], "2023-01-01T00:00:00.000Z");
+    assert_eq!(value["level"], "WARN");
+    assert_eq!(value["target"], "ghnotes::sources");
+    assert_eq!(value["message"], "Request failed: \"timeout\"");
+    assert_eq!(value["repo"], "octo/example");
+    assert!(!line.contains('\n'));
+
+    let line = logging::json_log_line("2023-01-01T00:00:00.000Z", log::Level::Info, "ghnotes", "Done", None);
+    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert!(value.get("repo").is_none());
+}
+
+#[test]
+fn test_default_log_filter() {
+    assert_eq!(logging::default_filter(false, false), "info");
+    assert_eq!(logging::default_filter(true, false), "debug");
+    assert_eq!(logging::default_filter(false, true), "error");
+    // clap rejects the combination, but verbose wins if it ever gets through
+    assert_eq!(logging::default_filter(true, true), "debug");
+}
+
+#[test]
+fn test_resolve_token() {
+    // Explicit flag wins over the environment
+    assert_eq!(
+        resolve_token(Some("flag".to_string()), Some("env".to_string())),
+        Some("flag".to_string())
+    );
+    // Environment is used when the flag is absent
+    assert_eq!(resolve_token(None, Some("env".to_string())), Some("env".to_string()));
+    // Empty environment values are ignored
+    assert_eq!(resolve_token(None, Some("  ".to_string())), None);
+    assert_eq!(resolve_token(None, None), None);
+}
+
+fn date_fixture_releases() -> Vec<Release> {
+    ["2023-03-15", "2023-02-15", "2023-01-15"]
+        .iter()
+        .enumerate()
+        .map(|(i, date)| Release {
+            id: i as u64,
+            tag_name: format!("v1.{}.0", 2 - i),
+            name: None,
+            body: None,
+            published_at: format!("{}T12:00:00Z", date),
+            prerelease: false,
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[test]
+fn test_filter_releases_by_date() {
+    let releases = date_fixture_releases();
+    let feb_1 = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
+    let feb_15 = NaiveDate::from_ymd_opt(2023, 2, 15).unwrap();
+
+    // Both bounds are inclusive
+    let filtered = filter_releases_by_date(&releases, Some(feb_1), Some(feb_15)).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0"]);
+
+    // Only a lower bound keeps everything from that date to the latest
+    let filtered = filter_releases_by_date(&releases, Some(feb_1), None).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.2.0", "v1.1.0"]);
+
+    // Only an upper bound keeps everything from the earliest to that date
+    let filtered = filter_releases_by_date(&releases, None, Some(feb_15)).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.0"]);
+
+    // Reversed bounds are rejected
+    assert!(filter_releases_by_date(&releases, Some(feb_15), Some(feb_1)).is_err());
+}
+
+#[test]
+fn test_take_latest_releases() {
+    let mut releases = date_fixture_releases();
+    releases.reverse();
+
+    let latest = take_latest_releases(releases.clone(), 2);
+    let tags: Vec<&str> = latest.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.2.0", "v1.1.0"]);
 
-    #[test]
-    fn test_generate_markdown() {
-        let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
-        
-        // Add some test data
-        let date1 = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
-        let date2 = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
-        
-        let features = vec![
+    // Asking for more than exist keeps everything
+    assert_eq!(take_latest_releases(releases, 10).len(), 3);
+}
+
+fn semver_fixture_releases() -> Vec<Release> {
+    // Newest first by publish date; v1.0.1 is a backported patch published after v2.0.0
+    [
+        ("v1.0.1", "2023-04-01"),
+        ("v2.0.0", "2023-03-01"),
+        ("v1.1.0", "2023-02-01"),
+        ("v1.0.0", "2023-01-01"),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, (tag, date))| Release {
+        id: i as u64,
+        tag_name: tag.to_string(),
+        name: None,
+        body: None,
+        published_at: format!("{}T00:00:00Z", date),
+        prerelease: false,
+        ..Default::default()
+    })
+    .collect()
+}
+
+#[test]
+fn test_filter_releases_by_tags_tolerates_v_prefix() {
+    let releases = semver_fixture_releases();
+
+    let filtered = filter_releases_by_tags(&releases, &["1.1.0", "V2.0.0"]).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v2.0.0", "v1.1.0"]);
+
+    let filtered = filter_releases_by_range(&releases, Some("1.0.0"), Some("1.1.0"), ReleaseOrder::Date, false, false, false).unwrap();
+    assert_eq!(filtered.len(), 2);
+}
+
+#[test]
+fn test_missing_tag_suggests_closest_match() {
+    let releases = semver_fixture_releases();
+
+    let err = filter_releases_by_tags(&releases, &["v1.1.9", "nightly"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not find the following tags: v1.1.9 (did you mean 'v1.1.0'?), nightly"
+    );
+
+    let err = filter_releases_by_range(&releases, Some("v2.0.9"), None, ReleaseOrder::Date, false, false, false).unwrap_err();
+    assert_eq!(err.to_string(), "Start tag 'v2.0.9' not found (did you mean 'v2.0.0'?)");
+
+    assert_eq!(helpers::edit_distance("kitten", "sitting"), 3);
+    assert_eq!(helpers::edit_distance("", "v1"), 2);
+}
+
+#[test]
+fn test_exclude_prereleases_after_range() {
+    let releases: Vec<Release> = [
+        ("v2.0.0", "2023-04-01", false),
+        ("v2.0.0-rc.1", "2023-03-01", true),
+        ("v1.1.0", "2023-02-01", false),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, (tag, date, prerelease))| Release {
+        id: i as u64,
+        tag_name: tag.to_string(),
+        body: Some(format!("# Features\n- Added in {}", tag)),
+        published_at: format!("{}T00:00:00Z", date),
+        prerelease: *prerelease,
+        ..Default::default()
+    })
+    .collect();
+
+    // The pre-release resolves as the range start, then drops out before rendering
+    let selected = filter_releases_by_range(&releases, Some("v2.0.0-rc.1"), Some("v2.0.0"), ReleaseOrder::Date, false, false, false).unwrap();
+    assert_eq!(selected.len(), 2);
+    let selected = exclude_prereleases(selected);
+
+    let markdown = generate_markdown(&merge_release_notes(&selected, &MergeOptions::default()), &MarkdownOptions::default());
+    assert!(markdown.contains("- Added in v2.0.0\n"));
+    assert!(!markdown.contains("rc.1"));
+}
+
+#[test]
+fn test_filter_releases_by_min_version() {
+    let releases: Vec<Release> = ["v2.1.0", "2.0.0", "v2.0.0-rc.1", "nightly", "v1.9.9"]
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| Release {
+            id: i as u64,
+            tag_name: tag.to_string(),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        })
+        .collect();
+
+    // The release candidate precedes 2.0.0, and non-semver tags are dropped
+    let filtered = filter_releases_by_min_version(&releases, "2.0.0").unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v2.1.0", "2.0.0"]);
+
+    let filtered = filter_releases_by_min_version(&releases, "v2.0.0-rc.1").unwrap();
+    assert_eq!(filtered.len(), 3);
+
+    assert!(filter_releases_by_min_version(&releases, "2.0").is_err());
+}
+
+#[test]
+fn test_compare_semver_prerelease_precedence() {
+    use std::cmp::Ordering;
+
+    // The precedence chain from the semver specification
+    let ordered = [
+        "1.0.0-alpha",
+        "1.0.0-alpha.1",
+        "1.0.0-alpha.beta",
+        "1.0.0-beta",
+        "1.0.0-beta.2",
+        "1.0.0-beta.11",
+        "1.0.0-rc.1",
+        "v1.0.0",
+        "1.0.1-alpha",
+    ];
+    for pair in ordered.windows(2) {
+        assert_eq!(compare_semver(pair[0], pair[1]), Ordering::Less, "{} < {}", pair[0], pair[1]);
+        assert_eq!(compare_semver(pair[1], pair[0]), Ordering::Greater, "{} > {}", pair[1], pair[0]);
+    }
+
+    // Build metadata is ignored
+    assert_eq!(compare_semver("1.0.0+build.5", "v1.0.0"), Ordering::Equal);
+    assert_eq!(compare_semver("1.0.0-rc.1+a", "1.0.0-rc.1+b"), Ordering::Equal);
+}
+
+#[test]
+fn test_filter_releases_by_range_date_order() {
+    let releases = semver_fixture_releases();
+
+    let filtered =
+        filter_releases_by_range(&releases, Some("v1.0.0"), Some("v1.1.0"), ReleaseOrder::Date, false, false, false).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.0"]);
+
+    // By date, v1.0.1 comes after v2.0.0 so the range v2.0.0..v1.0.1 spans both
+    let filtered =
+        filter_releases_by_range(&releases, Some("v2.0.0"), Some("v1.0.1"), ReleaseOrder::Date, false, false, false).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.0.1", "v2.0.0"]);
+}
+
+#[test]
+fn test_filter_releases_by_range_semver_order() {
+    let releases = semver_fixture_releases();
+
+    // By semver, the backported v1.0.1 sits between v1.0.0 and v1.1.0
+    let filtered =
+        filter_releases_by_range(&releases, Some("v1.0.0"), Some("v1.1.0"), ReleaseOrder::Semver, false, false, false).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.1", "v1.0.0"]);
+
+    let filtered =
+        filter_releases_by_range(&releases, Some("v1.1.0"), Some("v2.0.0"), ReleaseOrder::Semver, false, false, false).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v2.0.0", "v1.1.0"]);
+}
+
+#[test]
+fn test_filter_releases_by_range_semver_falls_back_to_date() {
+    let mut releases = semver_fixture_releases();
+    releases[0].tag_name = "nightly".to_string();
+
+    // A non-semver boundary falls back to publish-date ordering
+    let filtered =
+        filter_releases_by_range(&releases, Some("v2.0.0"), Some("nightly"), ReleaseOrder::Semver, false, false, false).unwrap();
+    let tags: Vec<&str> = filtered.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["nightly", "v2.0.0"]);
+}
+
+#[test]
+fn test_merge_release_notes_by_heading_normalizes_sections() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v2.0.0".to_string(),
+            name: None,
+            body: Some("# Features\n- Shared feature\n- Feature B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            name: None,
+            body: Some("# features \n- Shared feature\n- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+    ];
+
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+
+    // Both spellings collapse into one section, displayed with the first-seen name
+    assert_eq!(merged.len(), 1);
+    let features = &merged["Features"];
+    assert_eq!(features.len(), 3);
+    assert_eq!(features[0].content, "- Shared feature");
+    assert_eq!(features[0].sources, vec!["v2.0.0", "v1.0.0"]);
+}
+
+#[test]
+fn test_section_names_ignore_colon_and_count() {
+    assert_eq!(normalize_section_name("Bug Fixes:"), "bug fixes");
+    assert_eq!(normalize_section_name("Features (3)"), "features");
+    assert_eq!(normalize_section_name(" Features (12): "), "features");
+    assert_eq!(normalize_section_name("Support (macOS)"), "support (macos)");
+    assert_eq!(normalize_section_name("()"), "()");
+    
+    let release = |tag: &str, published_at: &str, body: &str| Release {
+        tag_name: tag.to_string(),
+        body: Some(body.to_string()),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("v1.2.0", "2023-03-01T00:00:00Z", "## Bug Fixes:\n- Fix C\n## Features (1)\n- Feature C"),
+        release("v1.1.0", "2023-02-01T00:00:00Z", "## Bug Fixes\n- Fix B\n## Features\n- Feature B\n- Feature B2"),
+        release("v1.0.0", "2023-01-01T00:00:00Z", "## Bug Fixes\n- Fix A\n## Features\n- Feature A"),
+    ];
+    
+    // Each section is shown the way most releases wrote it
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let mut names: Vec<&String> = merged.keys().collect();
+    names.sort();
+    assert_eq!(names, vec!["Bug Fixes", "Features"]);
+    assert_eq!(merged["Bug Fixes"].len(), 3);
+    assert_eq!(merged["Features"].len(), 4);
+    
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged["Bug Fixes"].len(), 3);
+    assert_eq!(merged["Features"].len(), 4);
+}
+
+#[test]
+fn test_generate_html() {
+    let date = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    merged_sections.insert(
+        "Bug Fixes".to_string(),
+        vec![
             ReleaseNoteItem {
-                content: "- Feature A v1".to_string(),
+                content: "- Fixed **crash** in `<parser>`".to_string(),
                 version: "v1.0.0".to_string(),
-                date: date1,
+                date,
+                ..Default::default()
             },
             ReleaseNoteItem {
-                content: "- Feature B v1".to_string(),
+                content: "- See [docs](https://example.com)".to_string(),
                 version: "v1.0.0".to_string(),
-                date: date1,
-            },
-            ReleaseNoteItem {
-                content: "- Feature A v2".to_string(),
-                version: "v2.0.0".to_string(),
-                date: date2,
+                date,
+                ..Default::default()
             },
-        ];
-        
-        let bugs = vec![
-            ReleaseNoteItem {
-                content: "- Bug Fix A v1".to_string(),
+        ],
+    );
+
+    let html = generate_html(&merged_sections, &SectionOrder::default(), None);
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<nav>\n<ul>\n<li><a href=\"#bug-fixes\">Bug Fixes</a></li>"));
+    assert!(html.contains("<h2 id=\"bug-fixes\">Bug Fixes</h2>"));
+    assert!(html.contains("<h3 id=\"bug-fixes-v1-0-0\">v1.0.0 (2023-01-01)</h3>"));
+    assert!(html.contains(
+        "<ul>\n<li>Fixed <strong>crash</strong> in <code>&lt;parser&gt;</code></li>\n<li>See <a href=\"https://example.com\">docs</a></li>\n</ul>"
+    ));
+}
+
+#[test]
+fn test_html_links_only_allow_safe_schemes() {
+    assert_eq!(render_html_inline("[docs](https://example.com)"), "<a href=\"https://example.com\">docs</a>");
+    assert_eq!(render_html_inline("[mail](mailto:octo@example.com)"), "<a href=\"mailto:octo@example.com\">mail</a>");
+    assert_eq!(render_html_inline("[notes](docs/a:b.md#top)"), "<a href=\"docs/a:b.md#top\">notes</a>");
+    assert_eq!(render_html_inline("[top](#features)"), "<a href=\"#features\">top</a>");
+    
+    // Anything else keeps its text but loses the link
+    assert_eq!(render_html_inline("[x](javascript:alert(1))"), "x)");
+    assert_eq!(render_html_inline("[x](JavaScript:void)"), "x");
+    assert_eq!(render_html_inline("[x](data:text/html,hi)"), "x");
+}
+
+#[test]
+fn test_github_slugger() {
+    let mut slugger = GitHubSlugger::default();
+
+    assert_eq!(slugger.slug("Bug Fixes"), "bug-fixes");
+    assert_eq!(slugger.slug("What's New?"), "whats-new");
+    assert_eq!(slugger.slug("Fixes & Improvements (v1.0)"), "fixes--improvements-v10");
+    assert_eq!(slugger.slug("snake_case-heading"), "snake_case-heading");
+
+    // Duplicates get numbered suffixes
+    assert_eq!(slugger.slug("Bug Fixes"), "bug-fixes-1");
+    assert_eq!(slugger.slug("Bug Fixes"), "bug-fixes-2");
+    assert_eq!(slugger.slug("bug-fixes-1"), "bug-fixes-1-1");
+}
+
+#[test]
+fn test_generate_markdown_with_toc() {
+    let date = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+    let mut merged_sections: HashMap<String, Vec<ReleaseNoteItem>> = HashMap::new();
+    for section in ["Features", "Bug Fixes!"] {
+        merged_sections.insert(
+            section.to_string(),
+            vec![ReleaseNoteItem {
+                content: "- Item".to_string(),
                 version: "v1.0.0".to_string(),
-                date: date1,
+                date,
+                ..Default::default()
+            }],
+        );
+    }
+
+    let options = MarkdownOptions { toc: true, ..Default::default() };
+    let markdown = generate_markdown(&merged_sections, &options);
+
+    assert!(markdown.starts_with(
+        "# Aggregated Release Notes\n\n- [Bug Fixes!](#bug-fixes)\n- [Features](#features)\n\n## Bug Fixes!"
+    ));
+}
+
+#[tokio::test]
+async fn test_load_releases_from_file() {
+    let path = std::env::temp_dir().join(format!("ghnotes-releases-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"[
+            {"id": 1, "tag_name": "v1.0.0", "name": null, "body": "- One", "published_at": "2023-01-01T00:00:00Z", "prerelease": false},
+            {"id": 3, "tag_name": "v2.0.0-rc.1", "name": null, "body": "- RC", "published_at": "2023-03-01T00:00:00Z", "prerelease": true},
+            {"id": 2, "tag_name": "v1.1.0", "name": null, "body": "- Two", "published_at": "2023-02-01T00:00:00Z", "prerelease": false}
+        ]"#,
+    )
+    .unwrap();
+
+    // Prereleases are dropped and the rest sorted newest first
+    let releases = collect_releases(&FileSource::new(&path), false, false).await.unwrap();
+    let tags: Vec<&str> = releases.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.0"]);
+
+    let releases = collect_releases(&FileSource::new(&path), true, false).await.unwrap();
+    assert_eq!(releases[0].tag_name, "v2.0.0-rc.1");
+
+    // JSON that doesn't match the release shape is a clear error
+    std::fs::write(&path, r#"{"message": "Not Found"}"#).unwrap();
+    let err = collect_releases(&FileSource::new(&path), false, false).await.unwrap_err();
+    assert!(err.to_string().contains("is not a JSON array of GitHub releases"));
+
+    std::fs::remove_file(&path).unwrap();
+    let err = collect_releases(&FileSource::new(&path), false, false).await.unwrap_err();
+    assert!(err.to_string().contains("Failed to read releases file"));
+}
+
+#[test]
+fn test_write_output_atomic() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-output-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let entries = || {
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    };
+
+    // Overwrites the destination and leaves nothing else behind
+    let path = dir.join("notes.md");
+    std::fs::write(&path, "old").unwrap();
+    write_output_atomic(&path, b"new").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+    assert_eq!(entries(), vec!["notes.md"]);
+
+    // A directory in the way makes the final rename fail; the temp file must not linger
+    let blocked = dir.join("blocked.md");
+    std::fs::create_dir(&blocked).unwrap();
+    assert!(write_output_atomic(&blocked, b"contents").is_err());
+    assert_eq!(entries(), vec!["blocked.md", "notes.md"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_is_body_truncated() {
+    let mut release = Release {
+        id: 1,
+        tag_name: "v1.0.0".to_string(),
+        name: None,
+        body: Some("- Short body".to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        prerelease: false,
+        ..Default::default()
+    };
+    assert!(!sources::is_body_truncated(&release));
+
+    release.body = None;
+    assert!(sources::is_body_truncated(&release));
+
+    release.body = Some("x".repeat(sources::LIST_BODY_LIMIT));
+    assert!(sources::is_body_truncated(&release));
+}
+
+#[test]
+fn test_merge_release_notes_dedupes_within_version() {
+    let releases = vec![Release {
+        id: 1,
+        tag_name: "v1.0.0".to_string(),
+        name: None,
+        body: Some("# Features\n- Feature A\n- Feature B\n- Feature A  \n- Feature C".to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        prerelease: false,
+        ..Default::default()
+    }];
+
+    let merged_sections = merge_release_notes(&releases, &MergeOptions::default());
+
+    let contents: Vec<&str> = merged_sections["Features"]
+        .iter()
+        .map(|item| item.content.as_str())
+        .collect();
+    assert_eq!(contents, vec!["- Feature A", "- Feature B", "- Feature C"]);
+}
+
+#[test]
+fn test_merge_release_notes_keeps_empty_releases() {
+    let releases = vec![
+        Release {
+            id: 3,
+            tag_name: "v1.2.0".to_string(),
+            body: Some("# Features\n- Feature C".to_string()),
+            published_at: "2023-03-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            body: None,
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("# Features\n- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    // The empty release still gets a version heading with a placeholder
+    let markdown = generate_markdown(&merge_release_notes(&releases, &MergeOptions::default()), &MarkdownOptions::default());
+    assert!(markdown.contains("## Uncategorized\n\n### v1.1.0 (2023-02-01)\n\n*No release notes provided.*\n"));
+    assert!(markdown.contains("### v1.2.0 (2023-03-01)\n\n- Feature C\n"));
+
+    let options = MergeOptions {
+        skip_empty: true,
+        ..Default::default()
+    };
+    let merged = merge_release_notes(&releases, &options);
+    assert!(!merged.contains_key("Uncategorized"));
+    assert_eq!(merged["Features"].len(), 2);
+}
+
+#[test]
+fn test_summary_stats() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            body: Some("# Features\n- Feature B\n- Feature B\n- Shared\n# Bug Fixes\n- Fix B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("# Features\n- Feature A\n- Shared".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    // The duplicate bullet in v1.1.0 is counted once
+    let stats = SummaryStats::from_merged_sections(&merge_release_notes(&releases, &MergeOptions::default()), DEFAULT_UNCATEGORIZED_LABEL);
+    assert_eq!(stats.releases, 2);
+    assert_eq!(stats.items, 5);
+    assert_eq!(
+        stats.sections,
+        vec![("Bug Fixes".to_string(), 1), ("Features".to_string(), 4)]
+    );
+    assert_eq!(
+        generate_stats_markdown(&stats),
+        "## Summary\n\n| Metric | Value |\n| --- | --- |\n\
+         | Releases | 2 |\n| Date range | 2023-01-01 to 2023-02-01 |\n| Items | 5 |\n\n\
+         | Section | Items |\n| --- | --- |\n| Bug Fixes | 1 |\n| Features | 4 |\n\n"
+    );
+
+    // Merging by heading folds the shared bullet into one item
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    let stats = SummaryStats::from_merged_headings(&merged, &releases, DEFAULT_UNCATEGORIZED_LABEL);
+    assert_eq!(stats.releases, 2);
+    assert_eq!(stats.items, 4);
+    assert_eq!(stats.first_date, NaiveDate::from_ymd_opt(2023, 1, 1));
+}
+
+#[test]
+fn test_collapse_repeated_items() {
+    let releases: Vec<Release> = [
+        ("v1.4.0", "2023-04-01", "# Known Issues\n- Slow startup\n- Memory leak"),
+        ("v1.3.0", "2023-03-01", "# Known Issues\n- Slow startup\n- Memory leak"),
+        ("v1.2.0", "2023-02-01", "# Known Issues\n- Slow startup"),
+        ("v1.1.0", "2023-01-01", "# Known Issues\n- Memory leak"),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, (tag, date, body))| Release {
+        id: i as u64,
+        tag_name: tag.to_string(),
+        body: Some(body.to_string()),
+        published_at: format!("{}T00:00:00Z", date),
+        ..Default::default()
+    })
+    .collect();
+
+    let options = MergeOptions::default();
+    let mut merged = merge_release_notes(&releases, &options);
+    collapse_repeated_items(&mut merged, &merge_release_notes_by_heading(&releases, &options), &releases);
+
+    // "Slow startup" spans three consecutive releases and is listed once under the newest;
+    // "Memory leak" skips v1.2.0, so only its v1.4.0-v1.3.0 run collapses
+    assert_eq!(
+        generate_markdown(&merged, &MarkdownOptions::default()),
+        "# Aggregated Release Notes\n\n## Known Issues\n\n\
+         ### v1.4.0 (2023-04-01)\n\n- Slow startup (v1.2.0\u{2013}v1.4.0)\n- Memory leak (v1.3.0\u{2013}v1.4.0)\n\n\
+         ### v1.1.0 (2023-01-01)\n\n- Memory leak\n\n"
+    );
+}
+
+#[test]
+fn test_collect_contributors() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            name: None,
+            body: Some(
+                "## What's Changed\n* feat: x by @alice in #123\n* fix: y by @bob in #124\n* docs: z by @alice in #125\n\nContact maintainers@example.com or @carol"
+                    .to_string(),
+            ),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            name: None,
+            // The same PR listed again in an older release doesn't count twice
+            body: Some("* feat: x by @alice in #123".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+    ];
+
+    let contributors = collect_contributors(&releases);
+
+    assert_eq!(
+        contributors,
+        vec![
+            Contributor { handle: "alice".to_string(), contributions: 2 },
+            Contributor { handle: "bob".to_string(), contributions: 1 },
+            Contributor { handle: "carol".to_string(), contributions: 1 },
+        ]
+    );
+
+    let markdown = generate_contributors_markdown(&contributors);
+    assert!(markdown.starts_with("## Contributors\n\n- @alice (2 contributions)\n- @bob (1 contribution)\n"));
+    assert!(!markdown.contains("example"));
+}
+
+#[test]
+fn test_changelog_mapping_aliases() {
+    let mut mapping = ChangelogMapping::default();
+
+    assert_eq!(mapping.category_for("Features"), ChangelogCategory::Added);
+    assert_eq!(mapping.category_for("Bug Fixes"), ChangelogCategory::Fixed);
+    assert_eq!(mapping.category_for(" fixes "), ChangelogCategory::Fixed);
+    assert_eq!(mapping.category_for("Deprecations"), ChangelogCategory::Deprecated);
+    assert_eq!(mapping.category_for("Security"), ChangelogCategory::Security);
+    assert_eq!(mapping.category_for("Miscellaneous"), ChangelogCategory::Other);
+
+    mapping.apply_overrides("Miscellaneous=changed, Cleanup=Removed").unwrap();
+    assert_eq!(mapping.category_for("miscellaneous"), ChangelogCategory::Changed);
+    assert_eq!(mapping.category_for("Cleanup"), ChangelogCategory::Removed);
+
+    assert!(mapping.apply_overrides("Cleanup").is_err());
+    assert!(mapping.apply_overrides("Cleanup=Tidied").is_err());
+}
+
+#[test]
+fn test_generate_keep_a_changelog() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            name: None,
+            body: Some("# Bug Fixes\n- Fix B\n# Features\n- Feature B\n# Chores\n- Bump deps".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            name: None,
+            body: Some("# Features\n- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+    ];
+
+    let merged_sections = merge_release_notes(&releases, &MergeOptions::default());
+    let changelog = generate_keep_a_changelog(&merged_sections, &ChangelogMapping::default(), DEFAULT_UNCATEGORIZED_LABEL);
+
+    assert!(changelog.starts_with("# Changelog\n"));
+    assert!(changelog.contains(
+        "## [1.1.0] - 2023-02-01\n\n### Added\n\n- Feature B\n\n### Fixed\n\n- Fix B\n\n### Other\n\n- Bump deps\n\n## [1.0.0] - 2023-01-01\n\n### Added\n\n- Feature A\n"
+    ));
+}
+
+#[test]
+fn test_generate_atom_feed() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            name: Some("Fixes & more".to_string()),
+            body: Some("## Bug Fixes\n- Fix <crash>".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            repo: "octo/example".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            repo: "octo/example".to_string(),
+            html_url: Some("https://github.com/octo/example/releases/tag/v1.0.0".to_string()),
+            ..Default::default()
+        },
+    ];
+
+    let feed = generate_atom_feed(&releases);
+
+    // Elements the Atom schema requires of the feed itself
+    assert!(feed.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    assert!(feed.contains("<title>Aggregated Release Notes</title>"));
+    assert!(feed.contains("<id>urn:ghnotes:octo/example</id>"));
+    assert!(feed.contains("<updated>2023-02-01T00:00:00+00:00</updated>"));
+    assert!(feed.contains("<author><name>octo/example</name></author>"));
+    assert!(feed.trim_end().ends_with("</feed>"));
+
+    // Every entry carries a title, id, updated and escaped HTML content
+    assert_eq!(feed.matches("<entry>").count(), 2);
+    assert_eq!(feed.matches("</entry>").count(), 2);
+    assert!(feed.contains("<title>Fixes &amp; more</title>"));
+    assert!(feed.contains("<title>v1.0.0</title>"));
+    assert!(feed.contains("<id>tag:github.com,2023-02-01:octo/example/v1.1.0</id>"));
+    assert!(feed.contains("<id>https://github.com/octo/example/releases/tag/v1.0.0</id>"));
+    assert!(feed.contains("<updated>2023-01-01T00:00:00Z</updated>"));
+    assert!(feed.contains(
+        "<content type=\"html\">&lt;h2&gt;Bug Fixes&lt;/h2&gt;\n&lt;ul&gt;\n&lt;li&gt;Fix &amp;lt;crash&amp;gt;&lt;/li&gt;\n&lt;/ul&gt;\n</content>"
+    ));
+}
+
+#[test]
+fn test_repeated_heading_keeps_all_content() {
+    let body = "# Notes\n- First\n# Features\n- Feature\n# Notes\n- Second";
+
+    let (sections, _) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+    assert_eq!(sections["Notes"], vec!["- First", "- Second"]);
+
+    let sections = helpers::extract_sections(body, DEFAULT_MAX_HEADING_DEPTH);
+    assert_eq!(sections["Notes"], vec!["- First", "- Second"]);
+    assert_eq!(sections["Features"], vec!["- Feature"]);
+}
+
+#[test]
+fn test_max_heading_depth() {
+    let body = "# Release\nIntro\n## Features\n- Feature A\n### Parser\n- Setext support\n#### Internals\n- Refactor";
+    let parse = |max_depth| parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, max_depth).0;
+    
+    // Depth 1: only the title splits; everything below stays together
+    let sections = parse(1);
+    assert_eq!(sections.len(), 1);
+    assert_eq!(
+        sections["Release"],
+        vec!["Intro", "## Features", "- Feature A", "### Parser", "- Setext support", "#### Internals", "- Refactor"]
+    );
+    
+    // Depth 2 (the default) keeps `###` sub-notes inside their section, as `extract_sections` does
+    let sections = parse(DEFAULT_MAX_HEADING_DEPTH);
+    assert_eq!(sections["Release"], vec!["Intro"]);
+    assert_eq!(sections["Features"], vec!["- Feature A", "### Parser", "- Setext support", "#### Internals", "- Refactor"]);
+    assert_eq!(helpers::extract_sections(body, DEFAULT_MAX_HEADING_DEPTH)["Features"], sections["Features"]);
+    
+    // Depth 3 makes `###` a section of its own
+    let sections = parse(3);
+    assert_eq!(sections["Features"], vec!["- Feature A"]);
+    assert_eq!(sections["Parser"], vec!["- Setext support", "#### Internals", "- Refactor"]);
+    assert!(!sections.contains_key("Internals"));
+    
+    // A `---` underline only makes an h2, so at depth 1 it's kept as content
+    let sections = parse_release_notes("Features\n---\n- Feature A", DEFAULT_UNCATEGORIZED_LABEL, None, 1).0;
+    assert_eq!(sections[DEFAULT_UNCATEGORIZED_LABEL], vec!["Features", "---", "- Feature A"]);
+}
+
+#[test]
+fn test_parse_release_notes_setext_headings() {
+    let body = "Intro line\n\nFeatures\n========\n\n- Feature 1\n\nBug Fixes\n---------\n- Fix 1";
+
+    let (sections, _) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+
+    assert_eq!(sections.len(), 3);
+    assert_eq!(sections["Uncategorized"], vec!["Intro line"]);
+    assert_eq!(sections["Features"], vec!["- Feature 1"]);
+    assert_eq!(sections["Bug Fixes"], vec!["- Fix 1"]);
+}
+
+#[test]
+fn test_parse_release_notes_horizontal_rule_is_not_a_heading() {
+    // A rule after a blank line or a list item is a thematic break, not an underline
+    let body = "# Features\n- Feature 1\n---\n- Feature 2\n\n---\n\nThanks to everyone!";
+
+    let (sections, _) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+
+    assert_eq!(sections.len(), 1);
+    assert_eq!(
+        sections["Features"],
+        vec!["- Feature 1", "---", "- Feature 2", "---", "Thanks to everyone!"]
+    );
+}
+
+#[test]
+fn test_parse_release_notes_skips_front_matter() {
+    let body = "---\ntitle: Release 1.0\n---\n# Features\n- Feature 1";
+
+    let (sections, _) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+
+    assert_eq!(sections.len(), 1);
+    assert_eq!(sections["Features"], vec!["- Feature 1"]);
+}
+
+#[test]
+fn test_compare_links_are_stripped() {
+    let body = "## What's Changed\n\
+                * Add feature by @alice in https://github.com/o/r/pull/1\n\
+                \n\
+                **Full Changelog**: https://github.com/o/r/compare/v1.0.0...v1.1.0";
+    let (sections, _) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+    assert_eq!(sections.len(), 1);
+    assert_eq!(
+        sections["What's Changed"],
+        vec!["* Add feature by @alice in https://github.com/o/r/pull/1"]
+    );
+
+    let releases = vec![Release {
+        id: 1,
+        tag_name: "v1.1.0".to_string(),
+        body: Some(body.to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    }];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    assert!(!merged.contains_key("Changelog Links"));
+
+    // With --keep-compare-links the URLs get a section of their own
+    let options = MergeOptions {
+        keep_compare_links: true,
+        ..Default::default()
+    };
+    let merged = merge_release_notes(&releases, &options);
+    assert_eq!(
+        merged["Changelog Links"][0].content,
+        "- https://github.com/o/r/compare/v1.0.0...v1.1.0"
+    );
+}
+
+#[test]
+fn test_link_references() {
+    // In-repo references use the release's repository
+    assert_eq!(
+        link_references("- Fix crash (#12), closes #13", "octo/example"),
+        "- Fix crash ([#12](https://github.com/octo/example/issues/12)), closes [#13](https://github.com/octo/example/issues/13)"
+    );
+
+    // Cross-repo references link to the named repository
+    assert_eq!(
+        link_references("- Port acme/widgets#7", "octo/example"),
+        "- Port [acme/widgets#7](https://github.com/acme/widgets/issues/7)"
+    );
+
+    // Code spans, headings, URLs and existing links are left alone
+    assert_eq!(link_references("- Run `git log #5` first", "octo/example"), "- Run `git log #5` first");
+    assert_eq!(link_references("## 1 #2", "octo/example"), "## 1 #2");
+    assert_eq!(
+        link_references("- See https://github.com/o/r/pull/1#issuecomment-9", "octo/example"),
+        "- See https://github.com/o/r/pull/1#issuecomment-9"
+    );
+    assert_eq!(
+        link_references("- [#3](https://example.com)", "octo/example"),
+        "- [#3](https://example.com)"
+    );
+
+    // Without a known repository only cross-repo references can be linked
+    assert_eq!(link_references("- Fix #1", ""), "- Fix #1");
+}
+
+#[test]
+fn test_merge_release_notes_by_heading_preserves_nesting() {
+    let body = "# Features\n- Parser\n  - Faster tokenizer\n  - Better errors\n- CLI\n  - New flag";
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v2.0.0".to_string(),
+            name: None,
+            body: Some(body.to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            name: None,
+            body: Some("# Features\n- CLI\n  - New flag".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            prerelease: false,
+            ..Default::default()
+        },
+    ];
+
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    let features = &merged["Features"];
+
+    // Sub-items stay attached to their parent and dedupe as a block
+    assert_eq!(features.len(), 2);
+    assert_eq!(features[0].content, "- CLI\n  - New flag");
+    assert_eq!(features[0].sources.len(), 2);
+    assert_eq!(features[1].content, "- Parser\n  - Faster tokenizer\n  - Better errors");
+
+    let markdown = generate_markdown_merged_headings(&merged, &MarkdownOptions::default());
+    assert!(markdown.contains("- Parser\n  - Faster tokenizer\n  - Better errors\n"));
+}
+
+#[test]
+fn test_gitlab_release_mapping() {
+    let json = r###"[
+        {
+            "tag_name": "v1.1.0",
+            "name": "Release 1.1.0",
+            "description": "## Features\n- Feature B",
+            "created_at": "2023-02-01T09:00:00.000Z",
+            "released_at": "2023-02-01T10:00:00.000Z",
+            "upcoming_release": false
+        },
+        {
+            "tag_name": "v1.2.0",
+            "name": null,
+            "description": null,
+            "released_at": "2099-01-01T00:00:00.000Z",
+            "upcoming_release": true
+        }
+    ]"###;
+
+    let gitlab_releases: Vec<sources::GitLabRelease> = serde_json::from_str(json).unwrap();
+    let releases: Vec<Release> = gitlab_releases
+        .into_iter()
+        .enumerate()
+        .map(|(i, release)| release.into_release(i as u64 + 1))
+        .collect();
+
+    assert_eq!(releases[0].id, 1);
+    assert_eq!(releases[0].tag_name, "v1.1.0");
+    assert_eq!(releases[0].name.as_deref(), Some("Release 1.1.0"));
+    assert_eq!(releases[0].body.as_deref(), Some("## Features\n- Feature B"));
+    assert_eq!(releases[0].published_at, "2023-02-01T10:00:00.000Z");
+    assert!(!releases[0].prerelease);
+    assert!(releases[1].prerelease);
+
+    // Mapped releases flow through the normal pipeline
+    let merged = merge_release_notes(&prepare_releases(releases, false, false), &MergeOptions::default());
+    assert_eq!(merged["Features"][0].content, "- Feature B");
+}
+
+#[tokio::test]
+async fn test_gitea_source_retries_and_pages() {
+    let release = |id: u64, tag: &str| {
+        serde_json::json!({"id": id, "tag_name": tag, "name": "", "body": "- Change", "published_at": "2023-01-01T00:00:00Z"})
+    };
+    let next = "</api/v1/repos/octo/example/releases?limit=50&page=2>; rel=\"next\", </api/v1/repos/octo/example/releases?limit=50&page=2>; rel=\"last\"";
+    let server = MockServer::start(vec![
+        MockResponse::new(429, "slow down").header("retry-after", "0"),
+        MockResponse::new(200, &serde_json::json!([release(2, "v1.1.0")]).to_string()).header("link", next),
+        MockResponse::new(200, &serde_json::json!([release(1, "v1.0.0")]).to_string()),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        base_url: Some(server.url.clone()),
+        versions: Some("v1.0.0".to_string()),
+        max_retries: 1,
+        ..Default::default()
+    };
+    
+    let releases = GiteaSource::from_config(&config, "octo", "example", &RequestCounter::default()).unwrap().fetch().await.unwrap();
+    let tags: Vec<&str> = releases.iter().map(|release| release.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.0"]);
+    let paths: Vec<String> = server.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(paths[2], "/api/v1/repos/octo/example/releases?limit=50&page=2");
+    
+    // A tag named without its `v` is found on the first page like any other
+    let server = MockServer::start(vec![
+        MockResponse::new(200, &serde_json::json!([release(2, "v1.1.0")]).to_string()).header("link", next),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        base_url: Some(server.url.clone()),
+        versions: Some("1.1.0".to_string()),
+        ..Default::default()
+    };
+    GiteaSource::from_config(&config, "octo", "example", &RequestCounter::default()).unwrap().fetch().await.unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_gitlab_source_retries_and_pages() {
+    let release = |tag: &str| serde_json::json!({"tag_name": tag, "name": null, "description": "- Change", "released_at": "2023-01-01T00:00:00Z"});
+    let server = MockServer::start(vec![
+        MockResponse::new(503, "unavailable").header("retry-after", "0"),
+        MockResponse::new(200, &serde_json::json!([release("v1.1.0")]).to_string()).header("x-next-page", "2"),
+        MockResponse::new(200, &serde_json::json!([release("v1.0.0")]).to_string()).header("x-next-page", ""),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        base_url: Some(server.url.clone()),
+        start_tag: Some("v1.0.0".to_string()),
+        max_retries: 1,
+        ..Default::default()
+    };
+    
+    // The older tag the selection names is on the second page
+    let releases = GitLabSource::from_config(&config, "octo", "example", &RequestCounter::default()).fetch().await.unwrap();
+    let tags: Vec<&str> = releases.iter().map(|release| release.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.0"]);
+    let paths: Vec<String> = server.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/api/v4/projects/octo%2Fexample/releases?per_page=100",
+            "/api/v4/projects/octo%2Fexample/releases?per_page=100",
+            "/api/v4/projects/octo%2Fexample/releases?per_page=100&page=2",
+        ]
+    );
+    let ids: Vec<u64> = releases.iter().map(|release| release.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+    
+    // A tag named without its `v` is found on the first page like any other
+    let server = MockServer::start(vec![
+        MockResponse::new(200, &serde_json::json!([release("v1.1.0")]).to_string()).header("x-next-page", "2"),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        base_url: Some(server.url.clone()),
+        start_tag: Some("1.1.0".to_string()),
+        ..Default::default()
+    };
+    GitLabSource::from_config(&config, "octo", "example", &RequestCounter::default()).fetch().await.unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_wanted_tags_are_matched_without_tag_prefix() {
+    let release = |tag: &str| serde_json::json!({"tag_name": tag, "name": null, "description": "- Change", "released_at": "2023-01-01T00:00:00Z"});
+    let server = MockServer::start(vec![
+        MockResponse::new(200, &serde_json::json!([release("frontend-v1.1.0"), release("backend-v1.0.0")]).to_string())
+            .header("x-next-page", "2"),
+        MockResponse::new(200, &serde_json::json!([release("frontend-v1.0.0")]).to_string()).header("x-next-page", ""),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        base_url: Some(server.url.clone()),
+        tag_prefix: Some("frontend-".to_string()),
+        start_tag: Some("v1.0.0".to_string()),
+        end_tag: Some("1.1.0".to_string()),
+        ..Default::default()
+    };
+    
+    // Another component's v1.0.0 doesn't count, so the second page is still fetched
+    let releases = GitLabSource::from_config(&config, "octo", "example", &RequestCounter::default()).fetch().await.unwrap();
+    assert_eq!(releases.len(), 3);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_gitea_release_mapping() {
+    let json = include_str!("../tests/fixtures/gitea_releases.json");
+
+    let gitea_releases: Vec<sources::GiteaRelease> = serde_json::from_str(json).unwrap();
+    let releases: Vec<Release> = gitea_releases.into_iter().map(sources::GiteaRelease::into_release).collect();
+    assert!(releases[0].draft);
+
+    // The draft is dropped, published releases keep their Gitea ids
+    let releases = prepare_releases(releases, true, false);
+    assert_eq!(releases.len(), 2);
+    assert_eq!(releases[0].id, 12);
+    assert_eq!(releases[0].tag_name, "v1.1.0");
+    assert_eq!(releases[0].name.as_deref(), Some("Version 1.1.0"));
+    assert_eq!(releases[0].published_at, "2023-02-01T10:00:00+01:00");
+    assert!(!releases[0].prerelease);
+    assert_eq!(releases[1].name, None);
+
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    assert_eq!(merged["Features"][0].content, "- Feature B");
+    assert_eq!(merged["Bug Fixes"][0].date, NaiveDate::from_ymd_opt(2023, 2, 1).unwrap());
+}
+
+// In-memory source so the pipeline can run without network access
+struct MockSource {
+    releases: Vec<Release>,
+}
+
+impl ReleaseSource for MockSource {
+    async fn fetch(&self) -> anyhow::Result<Vec<Release>> {
+        Ok(self.releases.clone())
+    }
+}
+
+#[tokio::test]
+async fn test_pipeline_with_mock_source() {
+    let source = MockSource {
+        releases: vec![
+            Release {
+                id: 1,
+                tag_name: "v1.0.0".to_string(),
+                name: None,
+                body: Some("# Features\n- Feature A".to_string()),
+                published_at: "2023-01-01T00:00:00Z".to_string(),
+                prerelease: false,
+                ..Default::default()
+            },
+            Release {
+                id: 3,
+                tag_name: "v1.2.0-beta".to_string(),
+                name: None,
+                body: Some("# Features\n- Beta feature".to_string()),
+                published_at: "2023-03-01T00:00:00Z".to_string(),
+                prerelease: true,
+                ..Default::default()
+            },
+            Release {
+                id: 2,
+                tag_name: "v1.1.0".to_string(),
+                name: None,
+                body: Some("# Features\n- Feature B\n# Bug Fixes\n- Fix B".to_string()),
+                published_at: "2023-02-01T00:00:00Z".to_string(),
+                prerelease: false,
+                ..Default::default()
+            },
+        ],
+    };
+
+    let releases = collect_releases(&source, false, false).await.unwrap();
+    let releases =
+        filter_releases_by_range(&releases, Some("v1.0.0"), Some("v1.1.0"), ReleaseOrder::Date, false, false, false).unwrap();
+    let markdown = generate_markdown(&merge_release_notes(&releases, &MergeOptions::default()), &MarkdownOptions::default());
+
+    assert_eq!(
+        markdown,
+        "# Aggregated Release Notes\n\n\
+         ## Bug Fixes\n\n### v1.1.0 (2023-02-01)\n\n- Fix B\n\n\
+         ## Features\n\n### v1.1.0 (2023-02-01)\n\n- Feature B\n\n### v1.0.0 (2023-01-01)\n\n- Feature A\n\n"
+    );
+}
+
+#[test]
+fn test_resolve_repositories() {
+    let owners = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    // One owner is shared by every repo
+    let pairs = resolve_repositories(&owners(&["octo"]), &owners(&["api", "web"]), &[]).unwrap();
+    assert_eq!(pairs, vec![RepositorySource::new("octo", "api"), RepositorySource::new("octo", "web")]);
+
+    // Otherwise owners pair up with repos in order
+    let pairs = resolve_repositories(&owners(&["octo", "acme"]), &owners(&["api", "web"]), &[]).unwrap();
+    assert_eq!(pairs[1], RepositorySource::new("acme", "web"));
+
+    assert!(resolve_repositories(&owners(&["a", "b"]), &owners(&["x", "y", "z"]), &[]).is_err());
+    assert!(resolve_repositories(&[], &[], &[]).is_err());
+
+    // Labeled sources follow the owner/repo pairs
+    let pairs = resolve_repositories(&owners(&["octo"]), &owners(&["api"]), &owners(&["fork/api:Fork"])).unwrap();
+    assert_eq!(pairs[1].label.as_deref(), Some("Fork"));
+}
+
+#[test]
+fn test_parse_repository_source() {
+    let source = RepositorySource::parse("rust-lang/rust:Upstream").unwrap();
+    assert_eq!(source.owner, "rust-lang");
+    assert_eq!(source.repo, "rust");
+    assert_eq!(source.label.as_deref(), Some("Upstream"));
+
+    // The label is optional, and an empty one counts as missing
+    assert_eq!(RepositorySource::parse("octo/example").unwrap(), RepositorySource::new("octo", "example"));
+    assert_eq!(RepositorySource::parse("octo/example:").unwrap(), RepositorySource::new("octo", "example"));
+
+    // GitLab subgroups stay part of the owner
+    let source = RepositorySource::parse("gitlab-org/ci/runner:Runner Fork").unwrap();
+    assert_eq!(source.owner, "gitlab-org/ci");
+    assert_eq!(source.repo, "runner");
+    assert_eq!(source.label.as_deref(), Some("Runner Fork"));
+
+    assert!(RepositorySource::parse("example").is_err());
+    assert!(RepositorySource::parse("octo/:Label").is_err());
+}
+
+fn repo_mock_source(tag: &str, published_at: &str, body: &str) -> MockSource {
+    MockSource {
+        releases: vec![Release {
+            id: 1,
+            tag_name: tag.to_string(),
+            body: Some(body.to_string()),
+            published_at: published_at.to_string(),
+            ..Default::default()
+        }],
+    }
+}
+
+#[tokio::test]
+async fn test_aggregate_multiple_repositories() {
+    let api = repo_mock_source("v2.0.0", "2023-02-01T00:00:00Z", "# Features\n- Shared change\n- API feature");
+    let web = repo_mock_source("v1.0.0", "2023-03-01T00:00:00Z", "# Features\n- Shared change");
+
+    let releases = combine_repository_releases(vec![
+        ("octo/api".to_string(), None, collect_releases(&api, false, false).await.unwrap()),
+        ("octo/web".to_string(), None, collect_releases(&web, false, false).await.unwrap()),
+    ]);
+    assert_eq!(releases[0].repo, "octo/web");
+
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    assert_eq!(merged["Features"][0].repo, "octo/web");
+
+    // Versions are prefixed with their repository when sections mix them
+    let markdown = generate_markdown(&merged, &MarkdownOptions::default());
+    assert!(markdown.contains("### octo/web@v1.0.0 (2023-03-01)\n\n- Shared change\n"));
+    assert!(markdown.contains("### octo/api@v2.0.0 (2023-02-01)\n\n- Shared change\n- API feature\n"));
+
+    let by_repo = MarkdownOptions {
+        group_by: GroupBy::Repo,
+        ..Default::default()
+    };
+    assert_eq!(
+        generate_markdown(&merged, &by_repo),
+        "# Aggregated Release Notes\n\n\
+         ## Features\n\n\
+         ### octo/api\n\n#### v2.0.0 (2023-02-01)\n\n- Shared change\n- API feature\n\n\
+         ### octo/web\n\n#### v1.0.0 (2023-03-01)\n\n- Shared change\n\n"
+    );
+
+    // Identical lines from different repositories stay separate when merging by heading
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    let shared: Vec<&str> = merged["Features"]
+        .iter()
+        .filter(|item| item.content == "- Shared change")
+        .map(|item| item.repo.as_str())
+        .collect();
+    assert_eq!(shared, vec!["octo/api", "octo/web"]);
+
+    let markdown = generate_markdown_merged_headings(&merged, &MarkdownOptions::default());
+    assert!(markdown.contains("- Shared change\n*(From version: octo/api@v2.0.0)*"));
+
+    let markdown = generate_markdown_merged_headings(&merged, &by_repo);
+    assert!(markdown.contains("### octo/web\n\n- Shared change\n*(From version: v1.0.0)*"));
+}
+
+#[tokio::test]
+async fn test_aggregate_labeled_sources() {
+    let upstream = repo_mock_source("v2.0.0", "2023-02-01T00:00:00Z", "# Features\n- Upstream feature");
+    let fork = repo_mock_source("v2.0.1", "2023-03-01T00:00:00Z", "# Features\n- Fork feature");
+    let other_fork = repo_mock_source("v2.0.2", "2023-04-01T00:00:00Z", "# Features\n- Other fork feature");
+
+    let releases = combine_repository_releases(vec![
+        ("octo/app".to_string(), Some("Upstream".to_string()), collect_releases(&upstream, false, false).await.unwrap()),
+        ("alice/app".to_string(), Some("Fork".to_string()), collect_releases(&fork, false, false).await.unwrap()),
+        ("bob/app".to_string(), Some("Fork".to_string()), collect_releases(&other_fork, false, false).await.unwrap()),
+    ]);
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+
+    // Labels stand in for the repository when attributing versions
+    let markdown = generate_markdown(&merged, &MarkdownOptions::default());
+    assert!(markdown.contains("### Upstream@v2.0.0 (2023-02-01)\n\n- Upstream feature\n"));
+    assert!(markdown.contains("### Fork@v2.0.1 (2023-03-01)\n\n- Fork feature\n"));
+
+    // Grouping by source puts repositories sharing a label together
+    let by_source = MarkdownOptions {
+        group_by: GroupBy::Source,
+        ..Default::default()
+    };
+    assert_eq!(
+        generate_markdown(&merged, &by_source),
+        "# Aggregated Release Notes\n\n\
+         ## Features\n\n\
+         ### Fork\n\n#### v2.0.2 (2023-04-01)\n\n- Other fork feature\n\n#### v2.0.1 (2023-03-01)\n\n- Fork feature\n\n\
+         ### Upstream\n\n#### v2.0.0 (2023-02-01)\n\n- Upstream feature\n\n"
+    );
+
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    let markdown = generate_markdown_merged_headings(&merged, &by_source);
+    assert!(markdown.contains("### Upstream\n\n- Upstream feature\n*(From version: v2.0.0)*"));
+}
+
+fn section_filter_fixture() -> Vec<Release> {
+    vec![Release {
+        id: 1,
+        tag_name: "v1.0.0".to_string(),
+        name: None,
+        body: Some(
+            "## Features\n- Feature A\n## Bug Fixes\n- Fix A\n## New Contributors\n- @alice\n## Full Changelog\n- v0.9.0...v1.0.0"
+                .to_string(),
+        ),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        prerelease: false,
+        ..Default::default()
+    }]
+}
+
+#[test]
+fn test_exclude_sections() {
+    let options = MergeOptions {
+        exclude_sections: parse_section_list("full changelog, NEW CONTRIBUTORS"),
+        ..Default::default()
+    };
+
+    let merged = merge_release_notes(&section_filter_fixture(), &options);
+    let mut names: Vec<&String> = merged.keys().collect();
+    names.sort();
+    assert_eq!(names, vec!["Bug Fixes", "Features"]);
+
+    let merged = merge_release_notes_by_heading(&section_filter_fixture(), &options);
+    let mut names: Vec<&String> = merged.keys().collect();
+    names.sort();
+    assert_eq!(names, vec!["Bug Fixes", "Features"]);
+}
+
+#[test]
+fn test_include_sections_with_exclude_precedence() {
+    let options = MergeOptions {
+        include_sections: Some(parse_section_list("features,bug fixes")),
+        exclude_sections: parse_section_list("Bug Fixes"),
+        ..Default::default()
+    };
+
+    // Only allowlisted sections survive, and the exclude list wins where they overlap
+    let merged = merge_release_notes(&section_filter_fixture(), &options);
+    let names: Vec<&String> = merged.keys().collect();
+    assert_eq!(names, vec!["Features"]);
+
+    let merged = merge_release_notes_by_heading(&section_filter_fixture(), &options);
+    let names: Vec<&String> = merged.keys().collect();
+    assert_eq!(names, vec!["Features"]);
+}
+
+// Canned response served by `MockServer`
+struct MockResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl MockResponse {
+    fn new(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+}
+
+// Request captured by `MockServer`
+#[derive(Debug, Clone)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl RecordedRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// Minimal HTTP server that answers each connection with the next canned response
+struct MockServer {
+    url: String,
+    requests: std::sync::Arc<std::sync::Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    async fn start(responses: Vec<MockResponse>) -> Self {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                // Read the head, then as much body as Content-Length announces
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let head_end = loop {
+                    let read = socket.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..read]);
+                    if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                        break position + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+                let mut lines = head.lines();
+                let mut request_line = lines.next().unwrap().split_whitespace();
+                let method = request_line.next().unwrap().to_string();
+                let path = request_line.next().unwrap().to_string();
+                let headers: Vec<(String, String)> = lines
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                    .collect();
+                let content_length = headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                    .map_or(0, |(_, value)| value.parse::<usize>().unwrap());
+                while buffer.len() < head_end + content_length {
+                    let read = socket.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..read]);
+                }
+                let body = String::from_utf8_lossy(&buffer[head_end..]).to_string();
+                recorded.lock().unwrap().push(RecordedRequest { method, path, headers, body });
+
+                let mut reply = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in &response.headers {
+                    reply.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                reply.push_str("\r\n");
+                reply.push_str(&response.body);
+                socket.write_all(reply.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+
+        MockServer { url, requests }
+    }
+
+    fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn mock_github_source(api_url: &str, max_retries: u32) -> GitHubSource {
+    GitHubSource {
+        api_url: api_url.to_string(),
+        owner: "octo".to_string(),
+        repo: "example".to_string(),
+        token: None,
+        fetch_full_bodies: false,
+        concurrency: 8,
+        verbose: false,
+        retry: sources::RetryPolicy {
+            max_retries,
+            base_delay: std::time::Duration::from_millis(1),
+        },
+        cache_dir: None,
+        wanted_tags: Vec::new(),
+        tag_prefix: None,
+        from_tags: false,
+        generate: None,
+        use_graphql: false,
+        progress: progress::Progress::default(),
+        requests: RequestCounter::default(),
+    }
+}
+
+// A full page of `count` releases tagged from `v0.{first}.0` downwards
+fn mock_release_page(first: usize, count: usize) -> String {
+    let releases: Vec<serde_json::Value> = (0..count)
+        .map(|i| {
+            serde_json::json!({
+                "id": first - i,
+                "tag_name": format!("v0.{}.0", first - i),
+                "body": "- Change",
+                "published_at": "2023-01-01T00:00:00Z",
+                "prerelease": false,
+            })
+        })
+        .collect();
+    serde_json::to_string(&releases).unwrap()
+}
+
+#[tokio::test]
+async fn test_github_source_explains_not_found_with_token() {
+    let not_found = r#"{"message": "Not Found", "documentation_url": "https://docs.github.com/rest/releases/releases"}"#;
+    let server = MockServer::start(vec![MockResponse::new(404, not_found), MockResponse::new(404, not_found)]).await;
+
+    let source = GitHubSource {
+        token: Some("github_pat_example".to_string()),
+        ..mock_github_source(&server.url, 0)
+    };
+    let err = source.fetch().await.unwrap_err();
+    assert!(err.downcast_ref::<ApiError>().unwrap().is_auth_failure());
+    let message = err.to_string();
+    assert!(message.contains("404 Not Found. If the repository is private, the token may not have access to it"));
+    assert!(message.contains("authorized for SSO"));
+
+    // Without a token a 404 is just a missing repository
+    let err = mock_github_source(&server.url, 0).fetch().await.unwrap_err();
+    assert!(!err.downcast_ref::<ApiError>().unwrap().is_auth_failure());
+    assert!(err.to_string().starts_with("GitHub API returned error status: 404 Not Found"));
+}
+
+#[tokio::test]
+async fn test_github_source_pages_until_wanted_tags_are_found() {
+    let server = MockServer::start(vec![
+        MockResponse::new(200, &mock_release_page(200, 100)),
+        MockResponse::new(200, &mock_release_page(100, 100)),
+    ])
+    .await;
+    let source = GitHubSource {
+        wanted_tags: vec!["v0.150.0".to_string(), "v0.50.0".to_string()],
+        ..mock_github_source(&server.url, 0)
+    };
+
+    let releases = source.fetch().await.unwrap();
+    assert_eq!(releases.len(), 200);
+    assert!(releases.iter().any(|release| release.tag_name == "v0.50.0"));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].path, "/repos/octo/example/releases?per_page=100&page=2");
+
+    // Tags on the first page need no further requests, however they're spelled
+    for tag in ["v0.150.0", "0.150.0", "V0.150.0"] {
+        let server = MockServer::start(vec![MockResponse::new(200, &mock_release_page(200, 100))]).await;
+        let source = GitHubSource {
+            wanted_tags: vec![tag.to_string()],
+            ..mock_github_source(&server.url, 0)
+        };
+        assert_eq!(source.fetch().await.unwrap().len(), 100);
+        assert_eq!(server.requests().len(), 1, "{}", tag);
+    }
+}
+
+const MOCK_RELEASES_JSON: &str = r#"[{"id": 1, "tag_name": "v1.0.0", "name": null, "body": "- One", "published_at": "2023-01-01T00:00:00Z", "prerelease": false}]"#;
+
+#[tokio::test]
+async fn test_github_source_retries_transient_errors() {
+    let server = MockServer::start(vec![
+        MockResponse::new(503, "unavailable"),
+        MockResponse::new(502, "bad gateway"),
+        MockResponse::new(200, MOCK_RELEASES_JSON),
+    ])
+    .await;
+
+    let releases = mock_github_source(&server.url, 3).fetch().await.unwrap();
+
+    assert_eq!(releases.len(), 1);
+    assert_eq!(releases[0].tag_name, "v1.0.0");
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|r| r.method == "GET" && r.path == "/repos/octo/example/releases?per_page=100"));
+    assert_eq!(requests[0].header("user-agent"), Some("github-release-notes-aggregator"));
+    assert!(requests[0].body.is_empty());
+}
+
+#[tokio::test]
+async fn test_github_source_gives_up_after_max_retries() {
+    let server = MockServer::start(vec![
+        MockResponse::new(504, "timeout"),
+        MockResponse::new(504, "timeout"),
+        MockResponse::new(504, "timeout"),
+    ])
+    .await;
+
+    let err = mock_github_source(&server.url, 2).fetch().await.unwrap_err();
+
+    assert!(err.to_string().contains("504"));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_github_source_does_not_retry_client_errors() {
+    let server = MockServer::start(vec![
+        MockResponse::new(404, r#"{"message": "Not Found"}"#),
+        MockResponse::new(200, MOCK_RELEASES_JSON),
+    ])
+    .await;
+
+    assert!(mock_github_source(&server.url, 3).fetch().await.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_github_source_errors_keep_their_status() {
+    let server = MockServer::start(vec![
+        MockResponse::new(401, r#"{"message": "Bad credentials"}"#),
+        MockResponse::new(404, r#"{"message": "Not Found"}"#),
+    ])
+    .await;
+
+    // Callers tell a rejected token apart from other failures by the status
+    let err = mock_github_source(&server.url, 0).fetch().await.unwrap_err();
+    assert!(err.downcast_ref::<ApiError>().unwrap().is_auth_failure());
+    let err = mock_github_source(&server.url, 0).fetch().await.unwrap_err();
+    assert!(!err.downcast_ref::<ApiError>().unwrap().is_auth_failure());
+}
+
+#[tokio::test]
+async fn test_github_source_retries_rate_limited_forbidden() {
+    let server = MockServer::start(vec![
+        MockResponse::new(403, "rate limited")
+            .header("x-ratelimit-limit", "60")
+            .header("x-ratelimit-remaining", "0")
+            .header("x-ratelimit-reset", "0"),
+        MockResponse::new(200, MOCK_RELEASES_JSON),
+    ])
+    .await;
+
+    assert!(mock_github_source(&server.url, 3).fetch().await.is_ok());
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_github_source_honors_retry_after() {
+    let server = MockServer::start(vec![
+        MockResponse::new(429, "secondary rate limit").header("retry-after", "0"),
+        MockResponse::new(403, "abuse detection").header("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT"),
+        MockResponse::new(200, MOCK_RELEASES_JSON),
+    ])
+    .await;
+
+    assert!(mock_github_source(&server.url, 3).fetch().await.is_ok());
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn test_parse_retry_after() {
+    use chrono::TimeZone;
+    use helpers::RetryAfter;
+    use std::time::Duration;
+
+    let now = chrono::Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+
+    let seconds = RetryAfter::parse("120").unwrap();
+    assert_eq!(seconds, RetryAfter::Seconds(120));
+    assert_eq!(seconds.delay_from(now), Duration::from_secs(120));
+
+    let date = RetryAfter::parse("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+    assert_eq!(date, RetryAfter::Date(chrono::Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap()));
+    assert_eq!(date.delay_from(now), Duration::from_secs(60));
+
+    // A date that has already passed means retry straight away
+    assert_eq!(date.delay_from(now + chrono::Duration::hours(1)), Duration::ZERO);
+
+    assert_eq!(RetryAfter::parse("soon"), None);
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    assert_eq!(helpers::parse_retry_after(&headers), None);
+    headers.insert("retry-after", "30".parse().unwrap());
+    assert_eq!(helpers::parse_retry_after(&headers), Some(RetryAfter::Seconds(30)));
+}
+
+#[tokio::test]
+async fn test_github_source_serves_not_modified_from_cache() {
+    let cache_dir = std::env::temp_dir().join(format!("ghnotes-cache-{}", std::process::id()));
+    let server = MockServer::start(vec![
+        MockResponse::new(200, MOCK_RELEASES_JSON).header("etag", "\"abc123\""),
+        MockResponse::new(304, ""),
+    ])
+    .await;
+    let source = GitHubSource {
+        cache_dir: Some(cache_dir.clone()),
+        ..mock_github_source(&server.url, 0)
+    };
+
+    // The first run populates the cache, the second revalidates it and gets a 304
+    let first = source.fetch().await.unwrap();
+    let second = source.fetch().await.unwrap();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].tag_name, first[0].tag_name);
+    assert_eq!(second[0].body.as_deref(), Some("- One"));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].header("if-none-match"), None);
+    assert_eq!(requests[1].header("if-none-match"), Some("\"abc123\""));
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_publish_gist() {
+    let server = MockServer::start(vec![
+        MockResponse::new(201, r#"{"html_url": "https://gist.github.com/octo/abc123"}"#)
+            .header("x-ratelimit-limit", "5000")
+            .header("x-ratelimit-remaining", "4999")
+            .header("x-ratelimit-reset", "1691591363"),
+        MockResponse::new(404, r#"{"message": "Not Found"}"#)
+            .header("x-ratelimit-limit", "5000")
+            .header("x-ratelimit-remaining", "4998")
+            .header("x-ratelimit-reset", "1691591363"),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["api".to_string(), "web".to_string()],
+        token: Some("secret".to_string()),
+        base_url: Some(server.url.clone()),
+        ..Default::default()
+    };
+
+    let url = publish_gist(&config, "# Aggregated Release Notes\n").await.unwrap();
+    assert_eq!(url, "https://gist.github.com/octo/abc123");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/gists");
+    assert_eq!(request.header("authorization"), Some("token secret"));
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "description": "Aggregated release notes for octo/api, octo/web",
+            "public": false,
+            "files": {"release-notes.md": {"content": "# Aggregated Release Notes\n"}},
+        })
+    );
+
+    // Failures explain the likely cause and report the remaining quota
+    let err = publish_gist(&config, "notes").await.unwrap_err().to_string();
+    assert!(err.contains("404 Not Found: Not Found (rate limit: 4998/5000 remaining); check that the token has the gist scope"));
+
+    let anonymous = AggregatorConfig { token: None, ..config };
+    assert!(publish_gist(&anonymous, "notes").await.is_err());
+}
+
+#[test]
+fn test_shift_headings() {
+    let markdown = "# Title\n\n## Section\n\n###### Deep\n\n```sh\n# comment\n```\n#hashtag\n";
+    assert_eq!(
+        helpers::shift_headings(markdown, 1),
+        "## Title\n\n### Section\n\n###### Deep\n\n```sh\n# comment\n```\n#hashtag\n"
+    );
+    assert_eq!(helpers::shift_headings(markdown, 0), markdown);
+    assert!(helpers::shift_headings(markdown, 9).starts_with("###### Title\n\n###### Section"));
+    
+    // A shorter or different fence inside a block doesn't close it
+    let markdown = "````md\n```\n# not a heading\n````\n# Title\n~~~\n```\n# comment\n~~~\n## Section";
+    assert_eq!(
+        helpers::shift_headings(markdown, 1),
+        "````md\n```\n# not a heading\n````\n## Title\n~~~\n```\n# comment\n~~~\n### Section"
+    );
+}
+
+#[test]
+fn test_clean_markdown() {
+    let markdown = "# Title\n\n\n\n## Section\ntext\n### Heading\n\n```sh\n# a comment\n\n\n\necho hi\n```\n\n\n";
+    let cleaned = clean_markdown(markdown);
+
+    assert_eq!(
+        cleaned,
+        "# Title\n\n## Section\ntext\n\n### Heading\n\n```sh\n# a comment\n\n\n\necho hi\n```\n"
+    );
+    
+    // Inner fences of another length or character don't end the block
+    let markdown = "````md\n```\n\n\ninner\n```\n````\n\n\ntext\n~~~\n```\n\n\n~~~\n";
+    assert_eq!(
+        clean_markdown(markdown),
+        "````md\n```\n\n\ninner\n```\n````\n\ntext\n~~~\n```\n\n\n~~~\n"
+    );
+}
+
+#[test]
+fn test_markdown_output_has_no_blank_line_runs() {
+    let releases = vec![
+        Release {
+            tag_name: "v1.1.0".to_string(),
+            body: Some("## Features\n\n\n\n- Feature B\n\n\n\n## Fixes\n- Fix B\n\n\n".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            tag_name: "v1.0.0".to_string(),
+            body: Some("Intro\n## Features\n- Feature A\n\n\n\n".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let markdown = clean_markdown(&generate_markdown(&merged, &MarkdownOptions::default()));
+    assert!(!markdown.contains("\n\n\n"));
+
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    let markdown = clean_markdown(&generate_markdown_merged_headings(&merged, &MarkdownOptions::default()));
+    assert!(!markdown.contains("\n\n\n"));
+}
+
+#[test]
+fn test_parse_conventional_commits() {
+    let body = "- feat(parser): support setext headings\n- fix: crash on empty body\n  - only with --stats\n- chore: bump deps\n- Update README\n- FEAT!: drop old API";
+    let options = MergeOptions {
+        parse_conventional: true,
+        ..Default::default()
+    };
+
+    let release = |body: &str| Release {
+        body: Some(body.to_string()),
+        ..Default::default()
+    };
+
+    let (sections, _) = parse_sections_for_merge(&release(body), &options);
+    assert_eq!(sections["Features"], vec!["- **parser:** support setext headings", "- drop old API"]);
+    assert_eq!(sections["Bug Fixes"], vec!["- crash on empty body", "  - only with --stats"]);
+    assert_eq!(sections["Chores"], vec!["- bump deps"]);
+    assert_eq!(sections["Uncategorized"], vec!["- Update README"]);
+
+    // Bodies with headings keep them, as do bodies parsed without the flag
+    let (sections, _) = parse_sections_for_merge(&release("## Changes\n- feat: thing"), &options);
+    assert_eq!(sections["Changes"], vec!["- feat: thing"]);
+    let (sections, _) = parse_sections_for_merge(&release(body), &MergeOptions::default());
+    assert_eq!(sections.len(), 1);
+}
+
+#[test]
+fn test_join_wrapped_bullets() {
+    let releases = vec![Release {
+        tag_name: "v1.0.0".to_string(),
+        body: Some(
+            "## Bug Fixes\n- Fix a crash when the configuration file\n  is empty\n  - Nested item\n- Fix B\n\n```sh\n- not a bullet\n  still code\n```"
+                .to_string(),
+        ),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    }];
+    let options = MergeOptions {
+        join_wrapped_bullets: true,
+        ..Default::default()
+    };
+    
+    // The wrapped bullet becomes one item; nested bullets and code blocks keep their own lines
+    let merged = merge_release_notes(&releases, &options);
+    let items: Vec<&str> = merged["Bug Fixes"].iter().map(|item| item.content.as_str()).collect();
+    assert_eq!(
+        items,
+        vec![
+            "- Fix a crash when the configuration file is empty",
+            "  - Nested item",
+            "- Fix B",
+            "```sh",
+            "- not a bullet",
+            "  still code",
+            "```",
+        ]
+    );
+    
+    // Without the option each physical line is its own item
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    assert_eq!(merged["Bug Fixes"][0].content, "- Fix a crash when the configuration file");
+    assert_eq!(merged["Bug Fixes"][1].content, "  is empty");
+}
+
+#[test]
+fn test_render_emoji() {
+    assert_eq!(render_emoji(":rocket: Launch :tada: :unknown: 10:30:45"), "🚀 Launch 🎉 :unknown: 10:30:45");
+    assert_eq!(render_emoji(":bug::sparkles:"), "🐛✨");
+    
+    let releases = vec![
+        Release {
+            tag_name: "v1.0.0".to_string(),
+            body: Some("## :bug: Bug Fixes\n- Fix A :white_check_mark:\n```\n:bug: literal\n```".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            tag_name: "v1.1.0".to_string(),
+            body: Some("## 🐛 Bug Fixes\n- Fix B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    let options = MergeOptions {
+        render_emoji: true,
+        ..Default::default()
+    };
+    
+    // Both spellings land in one section; code blocks keep their shortcodes
+    let merged = merge_release_notes(&releases, &options);
+    assert_eq!(merged.len(), 1);
+    let items: Vec<&str> = merged["🐛 Bug Fixes"].iter().map(|item| item.content.as_str()).collect();
+    assert!(items.contains(&"- Fix A ✅"));
+    assert!(items.contains(&"- Fix B"));
+    assert!(items.contains(&":bug: literal"));
+    
+    // Without the option they stay apart
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    assert_eq!(merged.len(), 2);
+}
+
+#[test]
+fn test_name_as_section() {
+    let releases = vec![
+        Release {
+            tag_name: "v1.1.0".to_string(),
+            body: Some("Fixed a crash on startup.".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            tag_name: "v1.0.0".to_string(),
+            body: Some("Intro line\n\n## Features\n- Feature A".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    let options = MergeOptions {
+        name_as_section: true,
+        ..Default::default()
+    };
+    
+    // The heading-less release gets its own section; text before a heading stays uncategorized
+    let merged = merge_release_notes(&releases, &options);
+    let mut section_names: Vec<&str> = merged.keys().map(String::as_str).collect();
+    section_names.sort();
+    assert_eq!(section_names, vec!["Features", "Uncategorized", "v1.1.0"]);
+    assert_eq!(merged["v1.1.0"][0].content, "Fixed a crash on startup.");
+    
+    // A release name other than the tag is preferred
+    let named = Release {
+        name: Some("Spring Release".to_string()),
+        ..releases[0].clone()
+    };
+    let (sections, _) = parse_sections_for_merge(&named, &options);
+    assert_eq!(sections["Spring Release"], vec!["Fixed a crash on startup."]);
+    
+    // Without the flag it's uncategorized as before
+    let (sections, _) = parse_sections_for_merge(&releases[0], &MergeOptions::default());
+    assert_eq!(sections[DEFAULT_UNCATEGORIZED_LABEL], vec!["Fixed a crash on startup."]);
+}
+
+#[test]
+fn test_max_items_per_section() {
+    let releases = vec![
+        Release {
+            tag_name: "v1.1.0".to_string(),
+            body: Some("## Features\n- One\n- Two\n- Three\n- Four\n- Five\n\n## Bug Fixes\n- Fix".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            tag_name: "v1.0.0".to_string(),
+            body: Some("## Features\n- Six\n- Seven".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let options = MarkdownOptions {
+        max_items_per_section: Some(2),
+        ..Default::default()
+    };
+
+    let markdown = generate_markdown(&merged, &options);
+    assert!(markdown.contains("### v1.1.0 (2023-02-01)\n\n- One\n- Two\n- …and 3 more\n\n"));
+    assert!(markdown.contains("### v1.0.0 (2023-01-01)\n\n- Six\n- Seven\n\n"));
+    assert!(markdown.contains("## Bug Fixes\n\n### v1.1.0 (2023-02-01)\n\n- Fix\n\n"));
+    assert_eq!(markdown.matches("more").count(), 1);
+
+    let options = MarkdownOptions {
+        group_by: GroupBy::Release,
+        ..options
+    };
+    let markdown = generate_markdown(&merged, &options);
+    assert!(markdown.contains("### Features\n\n- One\n- Two\n- …and 3 more\n"));
+}
+
+#[test]
+fn test_code_fences_are_kept_verbatim() {
+    let body = "## Usage\nRun it:\n```sh\n# comment\n\nghnotes --owner o --repo r\n---\n```\n## Fixes\n- Fix\n~~~~\n## not a heading\n```\n~~~~";
+    let (sections, _) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+
+    assert_eq!(
+        sections["Usage"],
+        vec!["Run it:", "```sh", "# comment", "", "ghnotes --owner o --repo r", "---", "```"]
+    );
+    assert_eq!(sections["Fixes"], vec!["- Fix", "~~~~", "## not a heading", "```", "~~~~"]);
+    assert_eq!(sections.len(), 2);
+    
+    // Fence markers repeat across code blocks and must survive the duplicate-bullet check
+    let release = Release {
+        tag_name: "v1.0.0".to_string(),
+        body: Some("## Usage\n```\na\n```\n```\nb\n```".to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    };
+    let merged = merge_release_notes(&[release], &MergeOptions::default());
+    let lines: Vec<&str> = merged["Usage"].iter().map(|item| item.content.as_str()).collect();
+    assert_eq!(lines, vec!["```", "a", "```", "```", "b", "```"]);
+}
+
+#[test]
+fn test_preserve_heading_levels() {
+    let body = "Setup\n=====\n- Install\n\n### Features\n- Feature A\n\n## Bug Fixes\n- Fix A";
+    let (_, levels) = parse_release_notes(body, DEFAULT_UNCATEGORIZED_LABEL, None, 3);
+    assert_eq!(levels["Setup"], 1);
+    assert_eq!(levels["Features"], 3);
+    assert_eq!(levels["Bug Fixes"], 2);
+
+    let releases = vec![Release {
+        tag_name: "v1.0.0".to_string(),
+        body: Some(body.to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    }];
+    let merge_options = MergeOptions {
+        max_heading_depth: 3,
+        ..Default::default()
+    };
+    let merged = merge_release_notes(&releases, &merge_options);
+
+    let options = MarkdownOptions {
+        preserve_levels: true,
+        ..Default::default()
+    };
+    let markdown = generate_markdown(&merged, &options);
+    assert!(markdown.contains("\n### Features\n\n#### v1.0.0 (2023-01-01)\n\n- Feature A\n"));
+    assert!(markdown.contains("\n## Bug Fixes\n\n### v1.0.0 (2023-01-01)\n"));
+    assert!(markdown.contains("\n# Setup\n\n## v1.0.0 (2023-01-01)\n"));
+
+    // Without the option every section is flattened to `##`
+    let markdown = generate_markdown(&merged, &MarkdownOptions::default());
+    assert!(markdown.contains("\n## Features\n\n### v1.0.0 (2023-01-01)\n"));
+}
+
+#[test]
+fn test_parse_rate_limit_response() {
+    let body = include_str!("../tests/fixtures/rate_limit.json");
+    let rate_limit = sources::parse_rate_limit_response(body).unwrap();
+
+    assert_eq!(rate_limit.limit, 60);
+    assert_eq!(rate_limit.remaining, 0);
+    assert_eq!(rate_limit.reset, 1691591363);
+    assert_eq!(
+        format_rate_limit(&rate_limit, false),
+        "GitHub API rate limit (unauthenticated): 0/60 requests remaining, resets at 2023-08-09 14:29:23 UTC\n"
+    );
+
+    assert!(sources::parse_rate_limit_response("{\"message\": \"Not Found\"}").is_err());
+}
+
+#[test]
+fn test_filter_releases_by_tag_pattern() {
+    let release = |tag: &str| Release {
+        tag_name: tag.to_string(),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("backend-v1.2.0"),
+        release("frontend-v1.2.0"),
+        release("backend-v1.1.0"),
+        release("backend-tools-v1.0.0"),
+        release("v1.0.0"),
+    ];
+
+    let tags = |releases: Vec<Release>| releases.into_iter().map(|r| r.tag_name).collect::<Vec<_>>();
+    let filtered = filter_releases_by_tag_pattern(releases.clone(), "backend-v*").unwrap();
+    assert_eq!(tags(filtered), vec!["backend-v1.2.0", "backend-v1.1.0"]);
+
+    let filtered = filter_releases_by_tag_pattern(releases.clone(), "*-v1.2.?").unwrap();
+    assert_eq!(tags(filtered), vec!["backend-v1.2.0", "frontend-v1.2.0"]);
+
+    assert!(filter_releases_by_tag_pattern(releases, "backend-[v").is_err());
+}
+
+#[tokio::test]
+async fn test_tag_prefix() {
+    let releases = serde_json::json!([
+        {"id": 4, "tag_name": "frontend-v1.1.0", "body": "## Features\n- Dark mode", "published_at": "2023-04-01T00:00:00Z", "prerelease": false},
+        {"id": 3, "tag_name": "backend-v1.1.0", "body": "## Features\n- Faster queries", "published_at": "2023-03-01T00:00:00Z", "prerelease": false},
+        {"id": 2, "tag_name": "frontend-v1.0.0", "body": "## Features\n- Login page", "published_at": "2023-02-01T00:00:00Z", "prerelease": false},
+        {"id": 1, "tag_name": "backend-v1.0.0", "body": "## Features\n- REST API", "published_at": "2023-01-01T00:00:00Z", "prerelease": false}
+    ]);
+    let releases: Vec<Release> = serde_json::from_value(releases).unwrap();
+    
+    let filtered = filter_releases_by_tag_prefix(releases.clone(), "frontend-");
+    let tags: Vec<&str> = filtered.iter().map(|release| release.tag_name.as_str()).collect();
+    assert_eq!(tags, vec!["v1.1.0", "v1.0.0"]);
+    assert_eq!(filtered[0].body.as_deref(), Some("## Features\n- Dark mode"));
+    
+    // End to end, the output only names clean versions, and range tags work with or without the prefix
+    let path = std::env::temp_dir().join(format!("ghnotes-tag-prefix-{}.json", std::process::id()));
+    std::fs::write(&path, serde_json::to_string(&releases).unwrap()).unwrap();
+    for start_tag in ["frontend-v1.0.0", "v1.0.0"] {
+        let config = AggregatorConfig {
+            owner: vec!["acme".to_string()],
+            repo: vec!["platform".to_string()],
+            from_file: Some(path.clone()),
+            tag_prefix: Some("frontend-".to_string()),
+            start_tag: Some(start_tag.to_string()),
+            ..Default::default()
+        };
+        let markdown = aggregate(config).await.unwrap();
+        assert!(markdown.contains("### v1.1.0 (2023-04-01)\n\n- Dark mode"));
+        assert!(markdown.contains("### v1.0.0 (2023-02-01)\n\n- Login page"));
+        assert!(!markdown.contains("frontend-"));
+        assert!(!markdown.contains("backend"));
+        assert!(!markdown.contains("Faster queries"));
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_changed_only() {
+    let releases = serde_json::json!([
+        {"id": 3, "tag_name": "v1.2.0", "body": "## Features\n- Unrelated", "published_at": "2023-03-01T00:00:00Z", "prerelease": false},
+        {"id": 2, "tag_name": "v1.1.0", "body": "## Features\n- Dark mode\n- Export to CSV\n\n## Bug Fixes\n- Fix login\n\n## Security\n- Patch CVE", "published_at": "2023-02-01T00:00:00Z", "prerelease": false},
+        {"id": 1, "tag_name": "v1.0.0", "body": "## Features\n- Dark mode\n\n## Bug Fixes\n- Fix login", "published_at": "2023-01-01T00:00:00Z", "prerelease": false}
+    ]);
+    let path = std::env::temp_dir().join(format!("ghnotes-changed-only-{}.json", std::process::id()));
+    std::fs::write(&path, releases.to_string()).unwrap();
+    let config = |versions: &str| AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["example".to_string()],
+        from_file: Some(path.clone()),
+        versions: Some(versions.to_string()),
+        changed_only: true,
+        ..Default::default()
+    };
+    
+    // Only what v1.1.0 adds over v1.0.0 is left, whichever order the tags are given in
+    for versions in ["v1.0.0,v1.1.0", "v1.1.0,v1.0.0"] {
+        let markdown = aggregate(config(versions)).await.unwrap();
+        assert!(markdown.contains("## Features\n\n### v1.1.0 (2023-02-01)\n\n- Export to CSV\n"));
+        assert!(markdown.contains("## Security\n\n### v1.1.0 (2023-02-01)\n\n- Patch CVE\n"));
+        assert!(!markdown.contains("Dark mode"));
+        assert!(!markdown.contains("Bug Fixes"));
+        assert!(!markdown.contains("v1.0.0"));
+    }
+    
+    let err = aggregate(config("v1.0.0,v1.1.0,v1.2.0")).await.unwrap_err().to_string();
+    assert!(err.contains("--changed-only compares exactly two releases, but the selection has 3"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_section_aliases_merge_synonyms() {
+    let release = |tag: &str, body: &str, date: &str| Release {
+        tag_name: tag.to_string(),
+        body: Some(body.to_string()),
+        published_at: date.to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("v1.2.0", "## Fixed\n- Fix C", "2023-03-01T00:00:00Z"),
+        release("v1.1.0", "## fixes\n- Fix B\n## Goodies\n- Thing", "2023-02-01T00:00:00Z"),
+        release("v1.0.0", "## Bug Fixes\n- Fix A", "2023-01-01T00:00:00Z"),
+    ];
+
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let fixes: Vec<&str> = merged["Bug Fixes"].iter().map(|item| item.content.as_str()).collect();
+    assert_eq!(fixes, vec!["- Fix C", "- Fix B", "- Fix A"]);
+    assert!(!merged.contains_key("Fixed") && !merged.contains_key("fixes"));
+
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    assert_eq!(merged["Bug Fixes"].len(), 3);
+
+    // Aliases from a file add to the built-in ones
+    let dir = std::env::temp_dir().join(format!("ghnotes-aliases-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("aliases.toml");
+    std::fs::write(&path, "Features = [\"Goodies\"]\n").unwrap();
+    let options = MergeOptions {
+        section_aliases: SectionAliases::load(&path).unwrap(),
+        ..Default::default()
+    };
+    let merged = merge_release_notes(&releases, &options);
+    assert_eq!(merged["Features"][0].content, "- Thing");
+    assert_eq!(merged["Bug Fixes"].len(), 3);
+
+    let path = dir.join("aliases.json");
+    std::fs::write(&path, "{\"Features\": [\"goodies\"]}").unwrap();
+    assert_eq!(SectionAliases::load(&path).unwrap().resolve("Goodies"), "Features");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reversed_range_strict_and_lenient() {
+    let release = |tag: &str, date: &str| Release {
+        tag_name: tag.to_string(),
+        published_at: date.to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("v1.2.0", "2023-03-01T00:00:00Z"),
+        release("v1.1.0", "2023-02-01T00:00:00Z"),
+        release("v1.0.0", "2023-01-01T00:00:00Z"),
+    ];
+
+    // Lenient: reversed tags select the same releases as the right order
+    let lenient = filter_releases_by_range(&releases, Some("v1.2.0"), Some("v1.1.0"), ReleaseOrder::Date, false, false, false).unwrap();
+    let ordered = filter_releases_by_range(&releases, Some("v1.1.0"), Some("v1.2.0"), ReleaseOrder::Date, false, false, false).unwrap();
+    let tags = |releases: &[Release]| releases.iter().map(|r| r.tag_name.clone()).collect::<Vec<_>>();
+    assert_eq!(tags(&lenient), vec!["v1.2.0", "v1.1.0"]);
+    assert_eq!(tags(&lenient), tags(&ordered));
+
+    // Strict: reversed tags are an error naming both dates, the right order still works
+    let err = filter_releases_by_range(&releases, Some("v1.2.0"), Some("v1.1.0"), ReleaseOrder::Date, true, false, false).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("Start tag 'v1.2.0' (2023-03-01) is newer than end tag 'v1.1.0' (2023-02-01)"));
+    let strict = filter_releases_by_range(&releases, Some("v1.1.0"), Some("v1.2.0"), ReleaseOrder::Date, true, false, false).unwrap();
+    assert_eq!(tags(&strict), tags(&ordered));
+
+    // The same tag at both ends is a single-release range, not a reversal
+    let single = filter_releases_by_range(&releases, Some("v1.1.0"), Some("v1.1.0"), ReleaseOrder::Date, true, false, false).unwrap();
+    assert_eq!(tags(&single), vec!["v1.1.0"]);
+}
+
+#[test]
+fn test_relative_dates() {
+    let today = NaiveDate::from_ymd_opt(2023, 6, 15).unwrap();
+    let ago = |y, m, d| relative_date(NaiveDate::from_ymd_opt(y, m, d).unwrap(), today);
+    assert_eq!(ago(2023, 6, 15), "today");
+    assert_eq!(ago(2023, 6, 14), "yesterday");
+    assert_eq!(ago(2023, 6, 12), "3 days ago");
+    assert_eq!(ago(2023, 6, 8), "1 week ago");
+    assert_eq!(ago(2023, 3, 10), "3 months ago");
+    assert_eq!(ago(2021, 6, 1), "2 years ago");
+    assert_eq!(ago(2023, 7, 1), "upcoming");
+
+    let releases = vec![Release {
+        tag_name: "v1.0.0".to_string(),
+        name: Some("First".to_string()),
+        body: Some("## Features\n- Feature A".to_string()),
+        published_at: "2023-03-10T00:00:00Z".to_string(),
+        ..Default::default()
+    }];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let options = MarkdownOptions {
+        relative_to: Some(today),
+        ..Default::default()
+    };
+    let markdown = generate_markdown(&merged, &options);
+    assert!(markdown.contains("### v1.0.0 \u{2014} \"First\" (2023-03-10, 3 months ago)\n"));
+}
+
+#[test]
+fn test_unparseable_published_at_is_skipped() {
+    let release = |tag: &str, published_at: &str| Release {
+        tag_name: tag.to_string(),
+        body: Some(format!("## Features\n- Feature {}", tag)),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("v1.0.0", "2023-01-01T00:00:00Z"),
+        release("v1.1.0", "not-a-date"),
+        release("v1.2.0", ""),
+    ];
+
+    // Sorting keeps every release, pushing the undated ones to the end
+    let mut sorted = releases.clone();
+    sort_releases_by_date(&mut sorted);
+    assert_eq!(sorted[0].tag_name, "v1.0.0");
+
+    // Neither merging nor preparing panics, and only the dated release survives
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let versions: Vec<&str> = merged.values().flatten().map(|item| item.version.as_str()).collect();
+    assert_eq!(versions, vec!["v1.0.0"]);
+
+    let prepared = prepare_releases(releases, false, false);
+    assert_eq!(prepared.len(), 1);
+    assert_eq!(prepared[0].tag_name, "v1.0.0");
+
+    // The error names both the offending value and the release
+    let err = release("v1.1.0", "not-a-date").published().unwrap_err();
+    assert!(err.to_string().contains("Invalid published_at 'not-a-date' for release 'v1.1.0'"));
+}
+
+#[test]
+fn test_sort_items() {
+    let releases = vec![Release {
+        tag_name: "v1.0.0".to_string(),
+        body: Some("## Features\n- banana support\n* Apple pie\n- a much longer cherry feature\n* Date".to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    }];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let render = |sort_items| {
+        let options = MarkdownOptions {
+            sort_items,
+            ..Default::default()
+        };
+        generate_markdown(&merged, &options)
+    };
+
+    // Bullet markers and case don't affect alphabetical order
+    let alphabetical = render(ItemOrder::Alphabetical);
+    assert!(alphabetical.contains("- a much longer cherry feature\n* Apple pie\n- banana support\n* Date\n"));
+
+    let by_length = render(ItemOrder::Length);
+    assert!(by_length.contains("* Date\n* Apple pie\n- banana support\n- a much longer cherry feature\n"));
+
+    let as_is = render(ItemOrder::AsIs);
+    assert!(as_is.contains("- banana support\n* Apple pie\n- a much longer cherry feature\n* Date\n"));
+}
+
+#[test]
+fn test_format_size() {
+    assert_eq!(helpers::format_size(0), "0 B");
+    assert_eq!(helpers::format_size(1023), "1023 B");
+    assert_eq!(helpers::format_size(1536), "1.5 KB");
+    assert_eq!(helpers::format_size(2_621_440), "2.5 MB");
+    assert_eq!(helpers::format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+}
+
+#[test]
+fn test_fuzzy_dedup_key() {
+    let key = helpers::fuzzy_dedup_key;
+    assert_eq!(key("- Fix crash on startup."), key("- fix crash on startup"));
+    assert_eq!(key("- Bump serde to 1.0.188"), key("- Bump serde to v1.0.190"));
+    assert_eq!(key("- Update  docs for 2.0.0-rc.1!"), "- update docs for <version>");
+    assert_ne!(key("- Fix crash on startup"), key("- Fix crash on shutdown"));
+    // Plain numbers aren't versions
+    assert_ne!(key("- Support 3 backends"), key("- Support 4 backends"));
+
+    let release = |tag: &str, published_at: &str, body: &str| Release {
+        tag_name: tag.to_string(),
+        body: Some(body.to_string()),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("v1.1.0", "2023-02-01T00:00:00Z", "## Dependencies\n- Bump serde to 1.0.190.\n- Fix crash"),
+        release("v1.0.0", "2023-01-01T00:00:00Z", "## Dependencies\n- bump serde to 1.0.188\n- Fix crash"),
+    ];
+
+    // Off by default, so only the exact duplicate merges
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    assert_eq!(merged["Dependencies"].len(), 3);
+
+    // The first-seen wording is kept for display
+    let options = MergeOptions {
+        fuzzy_dedup: true,
+        ..Default::default()
+    };
+    let merged = merge_release_notes_by_heading(&releases, &options);
+    let bump = merged["Dependencies"].iter().find(|item| item.content.contains("serde")).unwrap();
+    assert_eq!(merged["Dependencies"].len(), 2);
+    assert_eq!(bump.content, "- Bump serde to 1.0.190.");
+    assert_eq!(bump.sources, vec!["v1.1.0", "v1.0.0"]);
+}
+
+#[test]
+fn test_write_section_files() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-split-{}", std::process::id()));
+    let releases = vec![Release {
+        tag_name: "v1.0.0".to_string(),
+        body: Some("## Features\n- Feature A\n## Bug Fixes\n- Fix A\n## Bug-Fixes\n- Fix B\n## Index\n- Reindexed".to_string()),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    }];
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    let options = MarkdownOptions::default();
+
+    let index = write_section_files(&dir, merged, &SectionOrder::new(parse_section_order("Features"), DEFAULT_UNCATEGORIZED_LABEL), |section| {
+        generate_markdown(section, &options)
+    })
+    .unwrap();
+
+    // Slug collisions get a numeric suffix, and nothing may overwrite the index
+    assert_eq!(
+        index,
+        "# Aggregated Release Notes\n\n\
+         - [Features](features.md)\n\
+         - [Bug Fixes](bug-fixes.md)\n\
+         - [Bug-Fixes](bug-fixes-1.md)\n\
+         - [Index](index-1.md)\n"
+    );
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["bug-fixes-1.md", "bug-fixes.md", "features.md", "index-1.md"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("features.md")).unwrap(),
+        "## Features\n\n### v1.0.0 (2023-01-01)\n\n- Feature A\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_exclusive_start() {
+    let release = |tag: &str, published_at: &str| Release {
+        tag_name: tag.to_string(),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    // Newest first, as fetched
+    let releases = vec![
+        release("v1.3.0", "2023-04-01T00:00:00Z"),
+        release("v1.2.0", "2023-03-01T00:00:00Z"),
+        release("v1.1.0", "2023-02-01T00:00:00Z"),
+        release("v1.0.0", "2023-01-01T00:00:00Z"),
+    ];
+    let tags = |filtered: &[Release]| filtered.iter().map(|r| r.tag_name.clone()).collect::<Vec<_>>();
+    let range = |start, end, exclusive| {
+        tags(&filter_releases_by_range(&releases, start, end, ReleaseOrder::Date, false, exclusive, false).unwrap())
+    };
+
+    assert_eq!(range(Some("v1.1.0"), None, false), vec!["v1.3.0", "v1.2.0", "v1.1.0"]);
+    assert_eq!(range(Some("v1.1.0"), None, true), vec!["v1.3.0", "v1.2.0"]);
+    assert!(range(Some("v1.3.0"), None, true).is_empty());
+
+    assert_eq!(range(Some("v1.1.0"), Some("v1.2.0"), false), vec!["v1.2.0", "v1.1.0"]);
+    assert_eq!(range(Some("v1.1.0"), Some("v1.2.0"), true), vec!["v1.2.0"]);
+}
+
+#[test]
+fn test_one_sided_ranges_select_the_named_side() {
+    let release = |tag: &str, published_at: &str| Release {
+        tag_name: tag.to_string(),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    // Newest first, as fetched
+    let releases = vec![
+        release("v1.2.0", "2023-03-01T00:00:00Z"),
+        release("v1.1.0", "2023-02-01T00:00:00Z"),
+        release("v1.0.0", "2023-01-01T00:00:00Z"),
+    ];
+    let tags = |start, end| {
+        filter_releases_by_range(&releases, start, end, ReleaseOrder::Date, false, false, false)
+            .unwrap()
+            .iter()
+            .map(|r| r.tag_name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    // A start tag alone reaches up to the latest release, not back to the earliest
+    assert_eq!(tags(Some("v1.1.0"), None), vec!["v1.2.0", "v1.1.0"]);
+    assert_eq!(tags(Some("v1.2.0"), None), vec!["v1.2.0"]);
+    
+    // An end tag alone reaches back to the earliest release, not up to the latest
+    assert_eq!(tags(None, Some("v1.1.0")), vec!["v1.1.0", "v1.0.0"]);
+    assert_eq!(tags(None, Some("v1.0.0")), vec!["v1.0.0"]);
+}
+
+#[test]
+fn test_exclusive_end() {
+    let release = |tag: &str, published_at: &str| Release {
+        tag_name: tag.to_string(),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    // Newest first, as fetched
+    let releases = vec![
+        release("v1.2.0", "2023-03-01T00:00:00Z"),
+        release("v1.1.0", "2023-02-01T00:00:00Z"),
+        release("v1.0.0", "2023-01-01T00:00:00Z"),
+    ];
+    let tags = |filtered: &[Release]| filtered.iter().map(|r| r.tag_name.clone()).collect::<Vec<_>>();
+    let range = |start, end, exclusive| {
+        tags(&filter_releases_by_range(&releases, start, end, ReleaseOrder::Date, false, false, exclusive).unwrap())
+    };
+
+    assert_eq!(range(Some("v1.0.0"), Some("v1.2.0"), false), vec!["v1.2.0", "v1.1.0", "v1.0.0"]);
+    assert_eq!(range(Some("v1.0.0"), Some("v1.2.0"), true), vec!["v1.1.0", "v1.0.0"]);
+
+    // An end tag alone reaches back to the earliest release
+    assert_eq!(range(None, Some("v1.1.0"), false), vec!["v1.1.0", "v1.0.0"]);
+    assert_eq!(range(None, Some("v1.1.0"), true), vec!["v1.0.0"]);
+}
+
+#[test]
+fn test_diff_against_baseline() {
+    let release = |tag: &str, published_at: &str, body: &str| Release {
+        tag_name: tag.to_string(),
+        body: Some(body.to_string()),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    let old = release("v1.0.0", "2023-01-01T00:00:00Z", "## Features\n- Feature A\n- Feature B");
+    let new = release("v1.1.0", "2023-02-01T00:00:00Z", "## Features\n- Feature B \n- Feature C\n## Bug Fixes\n- Fix A");
+
+    // The last report only covered the old release
+    let baseline = generate_markdown(&merge_release_notes(std::slice::from_ref(&old), &MergeOptions::default()), &MarkdownOptions::default());
+    let baseline = Baseline::parse(&baseline);
+
+    let mut merged = merge_release_notes(&[new.clone(), old.clone()], &MergeOptions::default());
+    baseline.remove_known_items(&mut merged, |item| &item.content);
+    let markdown = generate_markdown(&merged, &MarkdownOptions::default());
+    assert_eq!(
+        markdown,
+        "# Aggregated Release Notes\n\n\
+         ## Bug Fixes\n\n### v1.1.0 (2023-02-01)\n\n- Fix A\n\n\
+         ## Features\n\n### v1.1.0 (2023-02-01)\n\n- Feature C\n\n"
+    );
+
+    // Items are matched within their section, whatever the layout
+    let mut merged = merge_release_notes_by_heading(&[new, old], &MergeOptions::default());
+    baseline.remove_known_items(&mut merged, |item| &item.content);
+    let mut remaining: Vec<&str> = merged.values().flatten().map(|item| item.content.as_str()).collect();
+    remaining.sort();
+    assert_eq!(remaining, vec!["- Feature C", "- Fix A"]);
+}
+
+#[test]
+fn test_parse_window() {
+    assert_eq!(parse_window("30d").unwrap(), chrono::Duration::days(30));
+    assert_eq!(parse_window("2w").unwrap(), chrono::Duration::days(14));
+    
+    for invalid in ["", "d", "30", "0d", "3m", "-2w", "1.5w"] {
+        assert!(parse_window(invalid).is_err(), "'{}' should not parse", invalid);
+    }
+}
+
+#[test]
+fn test_uncategorized_label() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            body: None,
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("Intro line\n\n# Zebra Support\n- Stripes".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    
+    let merge_options = MergeOptions {
+        uncategorized: "Divers".to_string(),
+        ..Default::default()
+    };
+    let merged = merge_release_notes(&releases, &merge_options);
+    assert!(!merged.contains_key("Uncategorized"));
+    assert_eq!(merged["Divers"].len(), 2);
+    
+    // The renamed bucket still sorts after everything else, even sections that come later alphabetically
+    let markdown_options = MarkdownOptions {
+        section_order: SectionOrder::new(Vec::new(), "Divers"),
+        ..Default::default()
+    };
+    let markdown = generate_markdown(&merged, &markdown_options);
+    assert!(markdown.find("## Zebra Support").unwrap() < markdown.find("## Divers").unwrap());
+    assert!(markdown.contains("## Divers\n\n### v1.1.0 (2023-02-01)\n\n*No release notes provided.*\n"));
+}
+
+#[test]
+fn test_hide_source_annotations() {
+    let releases = vec![
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            body: Some("## Features\n- Shared feature\n- New feature".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            repo: "octo/api".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("## Features\n- Shared feature".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            repo: "octo/web".to_string(),
+            ..Default::default()
+        },
+    ];
+    let merged = merge_release_notes_by_heading(&releases, &MergeOptions::default());
+    
+    let annotated = generate_markdown_merged_headings(&merged, &MarkdownOptions::default());
+    assert!(annotated.contains("*(From version: "));
+    
+    // Both the flat layout and the per-repository one keep the content without any annotation
+    for group_by in [GroupBy::Section, GroupBy::Repo] {
+        let options = MarkdownOptions {
+            group_by,
+            hide_source_annotations: true,
+            ..Default::default()
+        };
+        let markdown = generate_markdown_merged_headings(&merged, &options);
+        assert!(!markdown.contains("Present in versions"));
+        assert!(!markdown.contains("From version"));
+        assert!(markdown.contains("- Shared feature\n"));
+        assert!(markdown.contains("- New feature\n"));
+    }
+    
+    let options = MarkdownOptions {
+        hide_source_annotations: true,
+        ..Default::default()
+    };
+    // An item repeated across versions of one repository loses its "Present in versions" line too
+    let same_repo = vec![
+        releases[0].clone(),
+        Release {
+            repo: "octo/api".to_string(),
+            ..releases[1].clone()
+        },
+    ];
+    let merged = merge_release_notes_by_heading(&same_repo, &MergeOptions::default());
+    assert!(generate_markdown_merged_headings(&merged, &MarkdownOptions::default()).contains("*(Present in versions: v1.0.0, v1.1.0)*"));
+    assert_eq!(
+        generate_markdown_merged_headings(&merged, &options),
+        "# Aggregated Release Notes (Merged by Heading)\n\n## Features\n\n- Shared feature\n- New feature\n\n"
+    );
+}
+
+#[test]
+fn test_gzip_round_trip() {
+    use std::io::Read;
+    
+    let markdown = "# Aggregated Release Notes\n\n## Features\n\n### v1.0.0 (2023-01-01)\n\n- Feature A\n";
+    let compressed = gzip(markdown.as_bytes()).unwrap();
+    assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+    
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, markdown.as_bytes());
+}
+
+#[tokio::test]
+async fn test_metrics_count_requests() {
+    // A retried request and a second page each count, and clones of the counter share one total
+    let server = MockServer::start(vec![
+        MockResponse::new(503, "unavailable"),
+        MockResponse::new(200, &mock_release_page(200, 100)),
+        MockResponse::new(200, &mock_release_page(100, 100)),
+    ])
+    .await;
+    let mut metrics = metrics::Metrics::start();
+    let source = GitHubSource {
+        wanted_tags: vec!["v0.50.0".to_string()],
+        requests: metrics.requests().clone(),
+        ..mock_github_source(&server.url, 1)
+    };
+    source.fetch().await.unwrap();
+    assert_eq!(metrics.requests().count(), 3);
+    assert_eq!(metrics.requests().count(), server.requests().len());
+    
+    metrics.requests().clone().record();
+    metrics.add(metrics::Phase::Parsing, std::time::Duration::from_millis(1500));
+    metrics.add(metrics::Phase::Parsing, std::time::Duration::from_millis(500));
+    let summary = metrics.summary();
+    assert!(summary.contains("with 4 API requests"), "{}", summary);
+    assert!(summary.contains("parsing 2.00s"), "{}", summary);
+}
+
+#[test]
+fn test_progress_disabled_is_a_no_op() {
+    let progress = progress::Progress::new(false);
+    assert!(!progress.is_enabled());
+    
+    // Nothing to draw into, so these must simply return
+    progress.fetched_page(1, 100);
+    progress.fetched_body(1, 3);
+    progress.finish();
+    assert!(!progress::Progress::default().is_enabled());
+}
+
+#[tokio::test]
+async fn test_versions_file() {
+    let path = std::env::temp_dir().join(format!("ghnotes-versions-{}.txt", std::process::id()));
+    std::fs::write(&path, "# Releases for the quarterly digest\n\nv1.0.0\n  v1.1.0  \n\n# v1.2.0 is still a draft\n").unwrap();
+    assert_eq!(load_versions_file(&path).unwrap(), vec!["v1.0.0", "v1.1.0"]);
+    
+    // File tags join the inline ones
+    let config = AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["example".to_string()],
+        from_file: Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/releases.json")),
+        versions: Some("v1.2.0".to_string()),
+        versions_file: Some(path.clone()),
+        include_drafts: true,
+        format: OutputFormat::Json,
+        ..Default::default()
+    };
+    let json: HashMap<String, Vec<serde_json::Value>> = serde_json::from_str(&aggregate(config).await.unwrap()).unwrap();
+    let versions: HashSet<&str> = json.values().flatten().map(|item| item["version"].as_str().unwrap()).collect();
+    assert_eq!(versions, HashSet::from(["v1.0.0", "v1.1.0", "v1.2.0"]));
+    
+    std::fs::write(&path, "# Nothing selected yet\n\n").unwrap();
+    assert!(load_versions_file(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_validate_releases() {
+    let release = |tag: &str, body: Option<&str>| Release {
+        tag_name: tag.to_string(),
+        body: body.map(str::to_string),
+        published_at: "2023-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    };
+    let releases = vec![
+        release("v1.4.0", Some("## Features\n- Feature E\n\n```md\n#### Not a heading\n```")),
+        release("v1.3.0", Some("## Features\n- Feature D\n\n#### Internals\n- Refactor D")),
+        release("v1.2.0", None),
+        release("v1.1.0", Some("Fixed a crash on startup.")),
+        release("v1.0.0", Some("## Features\n- Feature A")),
+    ];
+    
+    let options = MergeOptions {
+        max_heading_depth: 3,
+        ..Default::default()
+    };
+    let violations = validate_releases(&releases, &options);
+    assert_eq!(
+        violations,
+        vec![
+            ReleaseViolations {
+                repo: String::new(),
+                tag_name: "v1.3.0".to_string(),
+                problems: vec![Problem::HeadingTooDeep { heading: "Internals".to_string(), level: 4, max: 3 }],
+            },
+            ReleaseViolations {
+                repo: String::new(),
+                tag_name: "v1.2.0".to_string(),
+                problems: vec![Problem::EmptyBody],
+            },
+            ReleaseViolations {
+                repo: String::new(),
+                tag_name: "v1.1.0".to_string(),
+                problems: vec![Problem::NoSections],
             },
-        ];
-        
-        merged_sections.insert("Features".to_string(), features);
-        merged_sections.insert("Bug Fixes".to_string(), bugs);
-        
-        let markdown = generate_markdown(&merged_sections);
-        
-        // Check that the markdown contains all expected sections and versions
-        assert!(markdown.contains("# Aggregated Release Notes"));
-        assert!(markdown.contains("## Bug Fixes"));
-        assert!(markdown.contains("## Features"));
-        assert!(markdown.contains("### v1.0.0 (2023-01-01)"));
-        assert!(markdown.contains("### v2.0.0 (2023-02-01)"));
-        
-        // Check that content items are included
-        assert!(markdown.contains("- Feature A v1"));
-        assert!(markdown.contains("- Feature B v1"));
-        assert!(markdown.contains("- Feature A v2"));
-        assert!(markdown.contains("- Bug Fix A v1"));
-    }
-}
\ No newline at end of file
+        ]
+    );
+    
+    let failure = ValidationFailure { checked: releases.len(), releases: violations };
+    assert_eq!(
+        failure.to_string(),
+        "3 of 5 releases failed validation:\n  v1.3.0: heading \"Internals\" is level 4, deeper than the maximum of 3\n  v1.2.0: has no release notes\n  v1.1.0: has no section headings, so all of its notes would be uncategorized"
+    );
+    
+    // Deep enough headings are fine
+    let options = MergeOptions {
+        max_heading_depth: 4,
+        ..Default::default()
+    };
+    assert_eq!(validate_releases(&releases[..2], &options), Vec::new());
+}
+
+#[test]
+fn test_uncategorized_diagnostics() {
+    let releases = vec![
+        Release {
+            id: 3,
+            tag_name: "v1.2.0".to_string(),
+            body: Some("Fixed a crash on startup.\nThanks to everyone who reported it!".to_string()),
+            published_at: "2023-03-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            body: Some("Intro line\n\n## Features\n- Feature B".to_string()),
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: None,
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    
+    // Only the heading-less body is reported; partly structured and empty releases are not
+    assert_eq!(
+        uncategorized_diagnostics(&releases, &MergeOptions::default(), true),
+        vec!["Release v1.2.0 has no section headings, so all of its notes are listed under Uncategorized"]
+    );
+    assert_eq!(
+        uncategorized_diagnostics(&releases, &MergeOptions::default(), false),
+        vec!["1 of 3 releases have no section headings, so all of their notes are listed under Uncategorized (run with --verbose to see which)"]
+    );
+    
+    // Commit prefixes give a heading-less body structure after all
+    let conventional = vec![Release {
+        body: Some("- feat: Add export\n- fix: Handle empty input".to_string()),
+        ..releases[0].clone()
+    }];
+    let options = MergeOptions {
+        parse_conventional: true,
+        ..Default::default()
+    };
+    assert!(uncategorized_diagnostics(&conventional, &options, true).is_empty());
+}
+
+#[test]
+fn test_date_format() {
+    assert_eq!(parse_date_format("%d %b %Y").unwrap(), "%d %b %Y");
+    assert!(parse_date_format("%Y-%Q").is_err());
+    assert_eq!(format_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), "%d %b %Y"), "01 Jan 2023");
+    
+    let item = ReleaseNoteItem {
+        content: "- Feature A".to_string(),
+        version: "v1.0.0".to_string(),
+        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+        ..Default::default()
+    };
+    let mut merged = HashMap::new();
+    merged.insert("Features".to_string(), vec![item]);
+    
+    let options = MarkdownOptions {
+        date_format: Some("%d %b %Y".to_string()),
+        ..Default::default()
+    };
+    assert!(generate_markdown(&merged, &options).contains("### v1.0.0 (01 Jan 2023)\n"));
+    assert!(generate_markdown(&merged, &MarkdownOptions::default()).contains("### v1.0.0 (2023-01-01)\n"));
+}
+
+#[test]
+fn test_link_versions() {
+    let releases: Vec<Release> = serde_json::from_value(serde_json::json!([{
+        "id": 1,
+        "tag_name": "v1.0.0",
+        "name": null,
+        "body": "## Features\n- Feature A",
+        "published_at": "2023-01-01T00:00:00Z",
+        "prerelease": false,
+        "html_url": "https://github.com/octo/example/releases/tag/v1.0.0"
+    }]))
+    .unwrap();
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    
+    let options = MarkdownOptions {
+        link_versions: true,
+        ..Default::default()
+    };
+    assert!(generate_markdown(&merged, &options)
+        .contains("### [v1.0.0](https://github.com/octo/example/releases/tag/v1.0.0) (2023-01-01)\n"));
+    assert!(generate_markdown(&merged, &MarkdownOptions::default()).contains("### v1.0.0 (2023-01-01)\n"));
+    
+    // Releases without a page keep a plain heading
+    let mut unlinked = releases.clone();
+    unlinked[0].html_url = None;
+    let merged = merge_release_notes(&unlinked, &MergeOptions::default());
+    assert!(generate_markdown(&merged, &options).contains("### v1.0.0 (2023-01-01)\n"));
+}
+
+#[tokio::test]
+async fn test_github_source_from_tags() {
+    let refs = serde_json::json!([
+        {"ref": "refs/tags/v1.1.0", "object": {"type": "tag", "sha": "t110"}},
+        {"ref": "refs/tags/v1.0.0", "object": {"type": "commit", "sha": "c100"}}
+    ]);
+    let tag = serde_json::json!({
+        "tag": "v1.1.0",
+        "message": "## Features\n- Tagged feature\n-----BEGIN PGP SIGNATURE-----\n\niQEzBAABCAAdFiEE\n-----END PGP SIGNATURE-----\n",
+        "tagger": {"name": "Octo Cat", "date": "2023-02-01T00:00:00Z"}
+    });
+    let commit = serde_json::json!({
+        "message": "Bump version",
+        "committer": {"name": "Octo Cat", "date": "2023-01-01T00:00:00Z"}
+    });
+    let server = MockServer::start(vec![
+        MockResponse::new(200, &refs.to_string()),
+        MockResponse::new(200, &tag.to_string()),
+        MockResponse::new(200, &commit.to_string()),
+    ])
+    .await;
+    let source = GitHubSource {
+        from_tags: true,
+        concurrency: 1,
+        ..mock_github_source(&server.url, 0)
+    };
+    
+    let releases = source.fetch().await.unwrap();
+    let paths: Vec<String> = server.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/repos/octo/example/git/matching-refs/tags",
+            "/repos/octo/example/git/tags/t110",
+            "/repos/octo/example/git/commits/c100",
+        ]
+    );
+    
+    // Annotated tags bring their message without the signature; lightweight ones only a date
+    assert_eq!(releases.len(), 2);
+    assert_eq!(releases[0].tag_name, "v1.1.0");
+    assert_eq!(releases[0].body.as_deref(), Some("## Features\n- Tagged feature"));
+    assert_eq!(releases[0].published_at, "2023-02-01T00:00:00Z");
+    assert_eq!(releases[1].tag_name, "v1.0.0");
+    assert_eq!(releases[1].body, None);
+    assert_eq!(releases[1].published_at, "2023-01-01T00:00:00Z");
+    
+    let merged = merge_release_notes(&releases, &MergeOptions::default());
+    assert_eq!(merged["Features"][0].content, "- Tagged feature");
+}
+
+#[tokio::test]
+async fn test_github_source_graphql() {
+    let json = include_str!("../tests/fixtures/graphql_releases.json");
+    
+    // Drafts come without a publication date, like the REST list
+    let page = sources::parse_graphql_releases(json).unwrap();
+    assert!(!page.page_info.has_next_page);
+    let releases: Vec<Release> = page.nodes.into_iter().map(sources::GraphQlRelease::into_release).collect();
+    assert_eq!(releases.len(), 3);
+    assert!(releases[0].draft);
+    assert_eq!(releases[0].published_at, "");
+    assert_eq!(releases[1].id, 12);
+    assert_eq!(releases[1].tag_name, "v1.1.0");
+    assert_eq!(releases[1].name.as_deref(), Some("Version 1.1.0"));
+    assert_eq!(releases[1].body.as_deref(), Some("## Features\n- Feature B\n\n## Bug Fixes\n- Fix B"));
+    assert_eq!(releases[1].published_at, "2023-02-01T00:00:00Z");
+    assert_eq!(releases[1].html_url.as_deref(), Some("https://github.com/octo/example/releases/tag/v1.1.0"));
+    assert!(releases[2].prerelease);
+    
+    let errors = r#"{"data": null, "errors": [{"message": "Could not resolve to a Repository with the name 'octo/missing'."}]}"#;
+    assert!(sources::parse_graphql_releases(errors).unwrap_err().to_string().contains("Could not resolve"));
+    
+    // Pages are requested by cursor while a wanted tag is still missing
+    let first_page = serde_json::json!({"data": {"repository": {"releases": {
+        "pageInfo": {"hasNextPage": true, "endCursor": "Y3Vyc29yOjE="},
+        "nodes": [{"databaseId": 2, "tagName": "v2.0.0", "name": null, "description": "- Two", "publishedAt": "2023-02-01T00:00:00Z", "createdAt": null, "isPrerelease": false, "isDraft": false, "url": null}]
+    }}}});
+    let server = MockServer::start(vec![
+        MockResponse::new(200, &first_page.to_string()),
+        MockResponse::new(200, json),
+    ])
+    .await;
+    let source = GitHubSource {
+        token: Some("ghp_example".to_string()),
+        use_graphql: true,
+        wanted_tags: vec!["v1.0.0".to_string()],
+        ..mock_github_source(&server.url, 0)
+    };
+    
+    let releases = source.fetch().await.unwrap();
+    assert_eq!(releases.len(), 4);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|request| request.method == "POST" && request.path == "/graphql"));
+    let second: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+    assert_eq!(second["variables"]["cursor"], "Y3Vyc29yOjE=");
+    assert_eq!(second["variables"]["owner"], "octo");
+}
+
+#[tokio::test]
+async fn test_quota_check_only_without_token() {
+    let server = MockServer::start(vec![
+        MockResponse::new(200, MOCK_RELEASES_JSON),
+        MockResponse::new(503, "unavailable"),
+        MockResponse::new(200, MOCK_RELEASES_JSON),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["example".to_string()],
+        base_url: Some(server.url.clone()),
+        max_retries: 3,
+        ..Default::default()
+    };
+    
+    // A token raises the quota enough that releases are fetched straight away
+    aggregate(AggregatorConfig { token: Some("secret".to_string()), ..config.clone() }).await.unwrap();
+    assert_eq!(server.requests()[0].path, "/repos/octo/example/releases?per_page=100");
+    
+    // Anonymous runs check once, and a failing check isn't retried
+    aggregate(config).await.unwrap();
+    let paths: Vec<String> = server.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(paths[1..], ["/rate_limit", "/repos/octo/example/releases?per_page=100"]);
+}
+
+#[tokio::test]
+async fn test_generate_notes() {
+    let notes = serde_json::json!({
+        "name": "v1.1.0",
+        "body": "## What's Changed\n* Add dark mode by @octo in https://github.com/octo/example/pull/7\n\n**Full Changelog**: https://github.com/octo/example/compare/v1.0.0...v1.1.0"
+    });
+    let server = MockServer::start(vec![
+        // GitHub Enterprise servers without rate limiting answer 404 here, which is skipped
+        MockResponse::new(404, r#"{"message": "Not Found"}"#),
+        MockResponse::new(200, &notes.to_string()),
+    ])
+    .await;
+    let config = AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["example".to_string()],
+        base_url: Some(server.url.clone()),
+        start_tag: Some("v1.0.0".to_string()),
+        end_tag: Some("v1.1.0".to_string()),
+        generate: true,
+        max_retries: 0,
+        ..Default::default()
+    };
+    
+    let markdown = aggregate(config).await.unwrap();
+    let request = &server.requests()[1];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/repos/octo/example/releases/generate-notes");
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body, serde_json::json!({"tag_name": "v1.1.0", "previous_tag_name": "v1.0.0"}));
+    
+    // The generated body goes through the usual parsing, so its heading becomes a section
+    assert!(markdown.contains("## What's Changed"));
+    assert!(markdown.contains("* Add dark mode by @octo in https://github.com/octo/example/pull/7"));
+    assert!(!markdown.contains("Full Changelog"));
+}
+
+#[tokio::test]
+async fn test_retry_missing_tags() {
+    let older = serde_json::json!([
+        {"id": 1, "tag_name": "v1.0.0", "body": "## Features\n- Feature A", "published_at": "2023-01-01T00:00:00Z", "prerelease": false}
+    ]);
+    let newer = serde_json::json!([
+        {"id": 2, "tag_name": "v1.1.0", "body": "## Features\n- Feature B", "published_at": "2023-02-01T00:00:00Z", "prerelease": false},
+        {"id": 1, "tag_name": "v1.0.0", "body": "## Features\n- Feature A", "published_at": "2023-01-01T00:00:00Z", "prerelease": false}
+    ]);
+    let config = |server: &MockServer, retry_missing_tags| AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["example".to_string()],
+        base_url: Some(server.url.clone()),
+        versions: Some("v1.1.0".to_string()),
+        max_retries: 0,
+        retry_missing_tags,
+        ..Default::default()
+    };
+    
+    // The new release only shows up in the second listing
+    let server = MockServer::start(vec![
+        MockResponse::new(404, r#"{"message": "Not Found"}"#),
+        MockResponse::new(200, &older.to_string()),
+        MockResponse::new(200, &newer.to_string()),
+    ])
+    .await;
+    let markdown = aggregate(config(&server, 1)).await.unwrap();
+    assert!(markdown.contains("- Feature B"));
+    let listings = server.requests().iter().filter(|request| request.path.contains("/releases")).count();
+    assert_eq!(listings, 2);
+    
+    // Without retries the first listing is final
+    let server = MockServer::start(vec![
+        MockResponse::new(404, r#"{"message": "Not Found"}"#),
+        MockResponse::new(200, &older.to_string()),
+    ])
+    .await;
+    let err = aggregate(config(&server, 0)).await.unwrap_err().to_string();
+    assert!(err.contains("Could not find the following tags: v1.1.0"));
+    
+    // Tags named without --tag-prefix are found in the first listing, so nothing is refetched
+    let prefixed = serde_json::json!([
+        {"id": 2, "tag_name": "frontend-v1.1.0", "body": "## Features\n- Feature B", "published_at": "2023-02-01T00:00:00Z", "prerelease": false},
+        {"id": 1, "tag_name": "frontend-v1.0.0", "body": "## Features\n- Feature A", "published_at": "2023-01-01T00:00:00Z", "prerelease": false}
+    ]);
+    let server = MockServer::start(vec![
+        MockResponse::new(404, r#"{"message": "Not Found"}"#),
+        MockResponse::new(200, &prefixed.to_string()),
+    ])
+    .await;
+    let markdown = aggregate(AggregatorConfig {
+        versions: None,
+        tag_prefix: Some("frontend-".to_string()),
+        start_tag: Some("v1.0.0".to_string()),
+        end_tag: Some("frontend-v1.1.0".to_string()),
+        ..config(&server, 1)
+    })
+    .await
+    .unwrap();
+    assert!(markdown.contains("- Feature A") && markdown.contains("- Feature B"));
+    let listings = server.requests().iter().filter(|request| request.path.contains("/releases")).count();
+    assert_eq!(listings, 1);
+}
+
+#[test]
+fn test_generate_summary() {
+    let releases = vec![
+        Release {
+            id: 1,
+            tag_name: "v1.0.0".to_string(),
+            body: Some("## Features\n\n- Initial import of the parser".to_string()),
+            published_at: "2023-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 3,
+            tag_name: "v1.2.0".to_string(),
+            name: Some("Spring Release".to_string()),
+            body: Some("## Features\n- Feature C".to_string()),
+            published_at: "2023-03-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+        Release {
+            id: 2,
+            tag_name: "v1.1.0".to_string(),
+            name: Some("v1.1.0".to_string()),
+            body: None,
+            published_at: "2023-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        },
+    ];
+    
+    // Names win, a name repeating the tag doesn't count, and headings are skipped when falling back to the notes
+    assert_eq!(
+        generate_summary(&releases),
+        "# Release Summary\n\n\
+         - v1.2.0 (2023-03-01): Spring Release\n\
+         - v1.1.0 (2023-02-01): No release notes provided\n\
+         - v1.0.0 (2023-01-01): Initial import of the parser\n"
+    );
+}
+
+#[test]
+fn test_crlf_bodies_match_lf() {
+    let lf_body = "## Features\n- Shared feature\n- Feature A\n\n## Bug Fixes\n- Fix A\n";
+    let release = |id: u64, tag: &str, body: String, published_at: &str| Release {
+        id,
+        tag_name: tag.to_string(),
+        body: Some(body),
+        published_at: published_at.to_string(),
+        ..Default::default()
+    };
+    let lf = prepare_releases(
+        vec![
+            release(2, "v1.1.0", "## Features\n- Shared feature\n".to_string(), "2023-02-01T00:00:00Z"),
+            release(1, "v1.0.0", lf_body.to_string(), "2023-01-01T00:00:00Z"),
+        ],
+        false,
+        false,
+    );
+    // CRLF in one release and mixed endings in the other still dedup against each other
+    let crlf = prepare_releases(
+        vec![
+            release(2, "v1.1.0", "## Features\r\n- Shared feature\r\n".to_string(), "2023-02-01T00:00:00Z"),
+            release(1, "v1.0.0", lf_body.replacen('\n', "\r\n", 3).replace("\n\n", "\r\r"), "2023-01-01T00:00:00Z"),
+        ],
+        false,
+        false,
+    );
+    assert!(crlf.iter().all(|release| !release.body.as_deref().unwrap().contains('\r')));
+    
+    let (lf_sections, _) = parse_release_notes(lf[1].body.as_deref().unwrap(), DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+    let (crlf_sections, _) = parse_release_notes(crlf[1].body.as_deref().unwrap(), DEFAULT_UNCATEGORIZED_LABEL, None, DEFAULT_MAX_HEADING_DEPTH);
+    assert_eq!(crlf_sections, lf_sections);
+    
+    let lf_merged = merge_release_notes_by_heading(&lf, &MergeOptions::default());
+    let crlf_merged = merge_release_notes_by_heading(&crlf, &MergeOptions::default());
+    let contents = |merged: &HashMap<String, Vec<MergedHeadingItem>>| {
+        let mut items: Vec<(String, String, Vec<String>)> = merged
+            .iter()
+            .flat_map(|(section, items)| items.iter().map(move |item| (section.clone(), item.content.clone(), item.sources.clone())))
+            .collect();
+        items.sort();
+        items
+    };
+    assert_eq!(contents(&crlf_merged), contents(&lf_merged));
+    assert_eq!(crlf_merged["Features"].iter().filter(|item| item.content == "- Shared feature").count(), 1);
+}
@@ -0,0 +1,400 @@
+use std::io::Read;
+use std::process::Command;
+
+fn ghnotes() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ghnotes"));
+    command.args(["--owner", "octo", "--repo", "example"]);
+    command
+}
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn writes_markdown_to_stdout() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("# Aggregated Release Notes\n\n## Bug Fixes\n\n### v1.1.0 — \"Version 1.1.0\" (2023-02-01)\n\n- Fix B\n"));
+    assert!(stdout.contains("### v1.0.0 — \"Version 1.0.0\" (2023-01-01)\n\n- Feature A\n"));
+}
+
+#[test]
+fn treats_dash_output_as_stdout() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--output", "-"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("- Feature B"));
+}
+
+#[test]
+fn latest_conflicts_with_tag_selection() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--latest", "2", "--versions", "v1.0.0"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("'--latest <N>' cannot be used with '--versions <VERSIONS>'"));
+}
+
+#[test]
+fn rejects_repository_without_releases() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("empty.json"), "--stdout"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("No releases found"));
+}
+
+#[test]
+fn fail_on_empty_rejects_empty_aggregation() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("empty.json"), "--stdout", "--fail-on-empty"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("No releases found"));
+}
+
+#[test]
+fn fail_on_empty_rejects_empty_selection() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--fail-on-empty", "--since", "2024-01-01"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("No releases matched the selection"));
+}
+
+#[test]
+fn exit_codes_tell_failures_apart() {
+    let code = |args: &[&str]| ghnotes().args(args).output().unwrap().status.code();
+
+    assert_eq!(code(&["--from-file", &fixture("releases.json"), "--stdout"]), Some(0));
+    assert_eq!(code(&["--from-file", &fixture("empty.json"), "--stdout"]), Some(2));
+    assert_eq!(code(&["--from-file", &fixture("releases.json"), "--stdout", "--fail-on-empty", "--since", "2024-01-01"]), Some(2));
+    assert_eq!(code(&["--from-file", &fixture("releases.json"), "--stdout", "--start-tag", "v0.9.0"]), Some(3));
+    assert_eq!(code(&["--from-file", &fixture("releases.json"), "--stdout", "--versions", "v1.0.0,v2.0.0"]), Some(3));
+
+    // Usage errors stay out of the way of the codes above
+    assert_eq!(code(&["--from-file", &fixture("releases.json"), "--latest", "2", "--versions", "v1.0.0"]), Some(1));
+}
+
+#[test]
+fn append_merges_new_releases_without_duplicates() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-append-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("CHANGELOG.md");
+    let run = |extra: &[&str]| {
+        let output = ghnotes()
+            .args(["--from-file", &fixture("releases.json"), "--append", "--output"])
+            .arg(&path)
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        std::fs::read_to_string(&path).unwrap()
+    };
+
+    let first = run(&["--versions", "v1.0.0"]);
+    assert!(!first.contains("v1.1.0"));
+
+    let second = run(&[]);
+    assert_eq!(second.matches("### v1.0.0").count(), 1);
+    assert_eq!(second.matches("### v1.1.0").count(), 2);
+    let features = &second[second.find("## Features").unwrap()..];
+    assert!(features.find("### v1.1.0").unwrap() < features.find("### v1.0.0").unwrap());
+
+    // Running again changes nothing
+    assert_eq!(run(&[]), second);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn writes_release_manifest() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-manifest-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let manifest = dir.join("manifest.json");
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--exclude-sections", "Bug Fixes,Features"])
+        .arg("--manifest")
+        .arg(&manifest)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&manifest).unwrap()).unwrap();
+    let releases = json["releases"].as_array().unwrap();
+    assert_eq!(releases.len(), 2);
+    assert_eq!(releases[0]["id"], 2);
+    assert_eq!(releases[0]["tag_name"], "v1.1.0");
+    assert_eq!(releases[0]["published_at"], "2023-02-01T00:00:00Z");
+    assert_eq!(releases[0]["repo"], "octo/example");
+    // Every section was excluded, so nothing made it into the output
+    assert_eq!(releases[0]["contributed"], false);
+    assert_eq!(releases[1]["tag_name"], "v1.0.0");
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--versions", "v1.0.0"])
+        .arg("--manifest")
+        .arg(&manifest)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&manifest).unwrap()).unwrap();
+    assert_eq!(json["releases"].as_array().unwrap().len(), 1);
+    assert_eq!(json["releases"][0]["contributed"], true);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn drafts_are_excluded_unless_requested() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("Feature C"));
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--include-drafts"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("## Features\n\n### v1.2.0 — \"Version 1.2.0\" (2023-03-01)\n\n- Feature C\n"));
+}
+
+#[test]
+fn config_file_supplies_defaults_that_flags_override() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("ghnotes.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "owner = \"octo\"\nrepo = [\"example\"]\nfrom_file = {:?}\nexclude_sections = [\"Bug Fixes\"]\nformat = \"json\"\nstdout = true\n",
+            fixture("releases.json")
+        ),
+    )
+    .unwrap();
+
+    // Owner and repo come from the file alone, the format flag wins over the file
+    let output = Command::new(env!("CARGO_BIN_EXE_ghnotes"))
+        .arg("--config")
+        .arg(&config)
+        .args(["--format", "markdown"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("# Aggregated Release Notes\n"));
+    assert!(stdout.contains("- Feature A"));
+    assert!(!stdout.contains("Fix B"));
+
+    std::fs::write(&config, "colour = \"red\"\n").unwrap();
+    let output = ghnotes().arg("--config").arg(&config).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Unknown option 'colour' in config file"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn quiet_suppresses_logging_and_conflicts_with_verbose() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--quiet"])
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("- Feature A"));
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--quiet", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("'--quiet' cannot be used with '--verbose'"));
+}
+
+#[test]
+fn split_by_section_writes_a_file_per_section() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-split-cli-{}", std::process::id()));
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--split-by", "section", "--output-dir"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["bug-fixes.md", "features.md", "index.md"]);
+
+    let index = std::fs::read_to_string(dir.join("index.md")).unwrap();
+    assert!(index.contains("- [Bug Fixes](bug-fixes.md)\n- [Features](features.md)\n"));
+    assert!(std::fs::read_to_string(dir.join("bug-fixes.md")).unwrap().starts_with("## Bug Fixes\n"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn split_by_rejects_other_formats() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-split-json-cli-{}", std::process::id()));
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--split-by", "section", "--format", "json", "--output-dir"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--split-by only writes the built-in Markdown layouts"));
+    assert!(!dir.exists());
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--split-by", "section", "--template", "default", "--output-dir"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("cannot be used with"));
+}
+
+#[test]
+fn list_assets_adds_an_assets_section() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("release_assets.json"), "--stdout", "--list-assets"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(
+        "## Assets\n\n### v1.0.0 — \"Version 1.0.0\" (2023-01-01)\n\n\
+         - [ghnotes-x86_64-linux.tar.gz](https://github.com/octo/example/releases/download/v1.0.0/ghnotes-x86_64-linux.tar.gz) (2.5 MB)\n\
+         - [checksums.txt](https://github.com/octo/example/releases/download/v1.0.0/checksums.txt) (512 B)\n"
+    ));
+
+    // Assets are left out unless asked for
+    let output = ghnotes()
+        .args(["--from-file", &fixture("release_assets.json"), "--stdout"])
+        .output()
+        .unwrap();
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("## Assets"));
+}
+
+#[test]
+fn terminal_format_only_colors_when_asked() {
+    let terminal = |color: &str| {
+        let output = ghnotes()
+            .args(["--from-file", &fixture("releases.json"), "--stdout", "--format", "terminal", "--color", color])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let plain = terminal("never");
+    assert!(!plain.contains('\x1b'));
+    assert!(plain.starts_with("Aggregated Release Notes\n\nBug Fixes\n\nv1.1.0 — \"Version 1.1.0\" (2023-02-01)\n\n- Fix B\n"));
+
+    let colored = terminal("always");
+    assert!(colored.contains("\x1b[1m\x1b[36mv1.1.0\x1b[0m — \"Version 1.1.0\" \x1b[2m(2023-02-01)\x1b[0m\n"));
+
+    // Piped output has no terminal, so auto leaves colors off
+    assert!(!terminal("auto").contains('\x1b'));
+}
+
+#[test]
+fn shift_headings_demotes_the_title() {
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout", "--shift-headings", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("## Aggregated Release Notes\n\n### Bug Fixes\n\n#### v1.1.0"));
+}
+
+#[test]
+fn gzip_compresses_the_output_file() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-gzip-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let plain = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--stdout"])
+        .output()
+        .unwrap();
+
+    // --gzip adds the extension, while an --output already ending in .gz is compressed without the flag
+    for (output_name, args, written) in [("notes.md", vec!["--gzip"], "notes.md.gz"), ("archive.md.gz", vec![], "archive.md.gz")] {
+        let output = ghnotes()
+            .args(["--from-file", &fixture("releases.json"), "--output"])
+            .arg(dir.join(output_name))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let compressed = std::fs::read(dir.join(written)).unwrap();
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(compressed.as_slice()).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, plain.stdout);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validate_fails_on_malformed_releases() {
+    let dir = std::env::temp_dir().join(format!("ghnotes-validate-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output_path = dir.join("notes.md");
+
+    let output = ghnotes()
+        .args(["--from-file", &fixture("validate.json"), "--validate", "--max-heading-depth", "3", "--output"])
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("3 of 4 releases failed validation"));
+    assert!(stderr.contains("v1.3.0: heading \"Internals\" is level 4, deeper than the maximum of 3"));
+    assert!(stderr.contains("v1.2.0: has no release notes"));
+    assert!(stderr.contains("v1.1.0: has no section headings"));
+    assert!(!stderr.contains("v1.0.0:"));
+    assert!(!output_path.exists());
+
+    // Well-formed releases pass, and validating still writes nothing
+    let output = ghnotes()
+        .args(["--from-file", &fixture("releases.json"), "--validate", "--output"])
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!output_path.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
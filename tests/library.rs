@@ -0,0 +1,43 @@
+use ghnotes::{aggregate, AggregatorConfig, OutputFormat};
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn config() -> AggregatorConfig {
+    AggregatorConfig {
+        owner: vec!["octo".to_string()],
+        repo: vec!["example".to_string()],
+        from_file: Some(fixture("releases.json")),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn aggregates_markdown_from_a_file() {
+    let markdown = aggregate(config()).await.unwrap();
+
+    assert!(markdown.starts_with("# Aggregated Release Notes\n\n## Bug Fixes\n\n### v1.1.0 — \"Version 1.1.0\" (2023-02-01)\n\n- Fix B\n"));
+    assert!(markdown.contains("- Feature A"));
+}
+
+#[tokio::test]
+async fn aggregates_selected_versions_as_json() {
+    let config = AggregatorConfig {
+        versions: Some("v1.0.0".to_string()),
+        format: OutputFormat::Json,
+        ..config()
+    };
+    let json: serde_json::Value = serde_json::from_str(&aggregate(config).await.unwrap()).unwrap();
+
+    let versions: Vec<&str> = json
+        .as_object()
+        .unwrap()
+        .values()
+        .flat_map(|items| items.as_array().unwrap())
+        .map(|item| item["version"].as_str().unwrap())
+        .collect();
+    assert!(!versions.is_empty());
+    assert!(versions.iter().all(|version| *version == "v1.0.0"));
+}